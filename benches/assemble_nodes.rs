@@ -1,6 +1,7 @@
 use criterion::{criterion_group, criterion_main, Criterion};
 use sleepyhead_kdl::assembler::*;
 use sleepyhead_kdl::parser::*;
+use sleepyhead_kdl::tape::Tape;
 
 fn bench_parse(c: &mut Criterion) {
     let input = include_str!("schema.kdl");
@@ -10,6 +11,10 @@ fn bench_parse(c: &mut Criterion) {
         b.iter(|| parse_document(&mut Parser::from_str(input)))
     });
 
+    group.bench_function("sleepyhead-kdl tape", |b| {
+        b.iter(|| Tape::from_parser(&mut Parser::from_str(input)))
+    });
+
     group.bench_function("kdl-rs", |b| b.iter(|| input.parse::<kdl::KdlDocument>()));
 
     group.finish();
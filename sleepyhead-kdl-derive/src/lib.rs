@@ -0,0 +1,489 @@
+//! derive macros for sleepyhead-kdl. use them through `sleepyhead_kdl::decode` and `sleepyhead_kdl::encode`, with the `derive` feature.
+
+use proc_macro::TokenStream;
+use proc_macro2::{Span, TokenStream as TokenStream2};
+use quote::quote;
+use syn::punctuated::Punctuated;
+use syn::token::Comma;
+use syn::{
+    parse_macro_input, Data, DeriveInput, Field, Fields, GenericArgument, GenericParam, Ident,
+    Lifetime, LifetimeParam, LitStr, PathArguments, Type,
+};
+
+/// Derives `DecodeKdl` for a struct with named fields. Every field needs one of these attributes:
+///
+/// - `#[kdl(argument)]`: the next positional argument.
+/// - `#[kdl(arguments)]`: all remaining arguments, as a `Vec`.
+/// - `#[kdl(property)]` or `#[kdl(property(name = "..."))]`: a property; the last one wins if it's repeated.
+/// - `#[kdl(child)]` or `#[kdl(child(name = "..."))]`: the first child node with that name.
+/// - `#[kdl(children)]` or `#[kdl(children(name = "..."))]`: every child node (with that name), as a `Vec`.
+///   Without a name, children with names another `child` or `children` field claims are left out.
+/// - `#[kdl(type_name)]`: the node's type annotation, as an `Option`.
+/// - `#[kdl(node_name)]`: the node's own name.
+///
+/// Properties and children default to the field name with `_` replaced by `-`. Put `#[kdl(rename_all = "...")]` on the struct to use another case instead:
+/// one of `"kebab-case"`, `"snake_case"`, `"camelCase"`, `"PascalCase"`, `"lowercase"`, `"UPPERCASE"`, `"SCREAMING_SNAKE_CASE"` or `"SCREAMING-KEBAB-CASE"`.
+/// Names given with `name = "..."` are used as they are.
+/// `Option` fields are left as `None` when missing; other fields are an error when missing, unless they're also marked `#[kdl(default)]` or `#[kdl(skip_if_default)]`.
+#[proc_macro_derive(DecodeKdl, attributes(kdl))]
+pub fn derive_decode_kdl(input: TokenStream) -> TokenStream {
+    let input = parse_macro_input!(input as DeriveInput);
+
+    match decode_kdl(input) {
+        Ok(tokens) => tokens.into(),
+        Err(e) => e.to_compile_error().into(),
+    }
+}
+
+/// Derives `EncodeKdl` for a struct with named fields, taking the same attributes as `DecodeKdl`.
+///
+/// `None` properties and children are left out, while `None` arguments are written as `null` to keep later arguments in place.
+/// Fields marked `#[kdl(skip_if_default)]` are left out when they equal their type's default.
+/// Unnamed `children` are named after the field, unless their type has a `node_name` field.
+#[proc_macro_derive(EncodeKdl, attributes(kdl))]
+pub fn derive_encode_kdl(input: TokenStream) -> TokenStream {
+    let input = parse_macro_input!(input as DeriveInput);
+
+    match encode_kdl(input) {
+        Ok(tokens) => tokens.into(),
+        Err(e) => e.to_compile_error().into(),
+    }
+}
+
+enum Kind {
+    Argument,
+    Arguments,
+    Property(String),
+    Child(String),
+    Children(Option<String>),
+    TypeName,
+    NodeName,
+}
+
+/// How field names are turned into kdl names, from the struct's `#[kdl(rename_all = "...")]`.
+#[derive(Clone, Copy, Default)]
+enum RenameRule {
+    #[default]
+    KebabCase,
+    SnakeCase,
+    CamelCase,
+    PascalCase,
+    Lowercase,
+    Uppercase,
+    ScreamingSnakeCase,
+    ScreamingKebabCase,
+}
+
+impl RenameRule {
+    fn parse(rule: &str) -> Option<RenameRule> {
+        Some(match rule {
+            "kebab-case" => RenameRule::KebabCase,
+            "snake_case" => RenameRule::SnakeCase,
+            "camelCase" => RenameRule::CamelCase,
+            "PascalCase" => RenameRule::PascalCase,
+            "lowercase" => RenameRule::Lowercase,
+            "UPPERCASE" => RenameRule::Uppercase,
+            "SCREAMING_SNAKE_CASE" => RenameRule::ScreamingSnakeCase,
+            "SCREAMING-KEBAB-CASE" => RenameRule::ScreamingKebabCase,
+            _ => return None,
+        })
+    }
+
+    /// Renames a snake_case field name.
+    fn apply(self, field: &str) -> String {
+        match self {
+            RenameRule::KebabCase => field.replace('_', "-"),
+            RenameRule::SnakeCase => field.to_string(),
+            RenameRule::CamelCase | RenameRule::PascalCase => {
+                let mut out = String::with_capacity(field.len());
+                let mut upper = matches!(self, RenameRule::PascalCase);
+                for c in field.chars() {
+                    if c == '_' {
+                        upper = true;
+                    } else if upper {
+                        out.extend(c.to_uppercase());
+                        upper = false;
+                    } else {
+                        out.push(c);
+                    }
+                }
+                out
+            }
+            RenameRule::Lowercase => field.to_string(),
+            RenameRule::Uppercase => field.to_uppercase(),
+            RenameRule::ScreamingSnakeCase => field.to_uppercase(),
+            RenameRule::ScreamingKebabCase => field.replace('_', "-").to_uppercase(),
+        }
+    }
+}
+
+struct FieldAttrs<'f> {
+    ident: &'f Ident,
+    ty: &'f Type,
+    /// The field name as kdl would write it, following the struct's rename rule.
+    kdl_name: String,
+    kind: Kind,
+    default: bool,
+    skip_if_default: bool,
+}
+
+fn decode_kdl(input: DeriveInput) -> syn::Result<TokenStream2> {
+    let fields = struct_fields(&input, "DecodeKdl")?;
+    let rename = rename_rule(&input)?;
+
+    let mut generics = input.generics.clone();
+    let lifetime = match input.generics.lifetimes().next() {
+        Some(param) => param.lifetime.clone(),
+        None => {
+            let lifetime = Lifetime::new("'kdl", Span::call_site());
+            generics.params.insert(
+                0,
+                GenericParam::Lifetime(LifetimeParam::new(lifetime.clone())),
+            );
+            lifetime
+        }
+    };
+
+    let (impl_generics, _, _) = generics.split_for_impl();
+    let (_, ty_generics, where_clause) = input.generics.split_for_impl();
+    let ident = &input.ident;
+
+    let fields = fields
+        .iter()
+        .map(|field| field_attrs(field, rename))
+        .collect::<syn::Result<Vec<_>>>()?;
+    // names an unnamed `children` field leaves to the other fields
+    let claimed: Vec<String> = fields
+        .iter()
+        .filter_map(|attrs| match &attrs.kind {
+            Kind::Child(name) | Kind::Children(Some(name)) => Some(name.clone()),
+            _ => None,
+        })
+        .collect();
+
+    let mut next_argument = 0usize;
+    let mut inits = Vec::with_capacity(fields.len());
+
+    for attrs in fields {
+        let name = attrs.ident;
+        let optional = option_inner(attrs.ty).is_some();
+        let nullable = matches!(attrs.kind, Kind::Argument | Kind::Property(_));
+
+        let (value, missing) = match attrs.kind {
+            Kind::Argument => {
+                let idx = next_argument;
+                next_argument += 1;
+                (
+                    quote! { ::sleepyhead_kdl::decode::argument(node, #idx)? },
+                    quote! { ::sleepyhead_kdl::decode::DecodeErrorKind::MissingArgument(#idx) },
+                )
+            }
+            Kind::Arguments => {
+                let idx = next_argument;
+                inits.push(quote! { #name: ::sleepyhead_kdl::decode::arguments(node, #idx)? });
+                continue;
+            }
+            Kind::Property(key) => (
+                quote! { ::sleepyhead_kdl::decode::property(node, #key)? },
+                quote! { ::sleepyhead_kdl::decode::DecodeErrorKind::MissingProperty(#key.into()) },
+            ),
+            Kind::Child(child) => (
+                quote! { ::sleepyhead_kdl::decode::child(node, #child)? },
+                quote! { ::sleepyhead_kdl::decode::DecodeErrorKind::MissingChild(#child.into()) },
+            ),
+            Kind::Children(Some(child)) => {
+                inits.push(quote! {
+                    #name: ::sleepyhead_kdl::decode::children(node, ::core::option::Option::Some(#child))?
+                });
+                continue;
+            }
+            Kind::Children(None) => {
+                inits.push(quote! {
+                    #name: ::sleepyhead_kdl::decode::other_children(node, &[#(#claimed),*])?
+                });
+                continue;
+            }
+            Kind::TypeName => {
+                inits.push(quote! {
+                    #name: ::sleepyhead_kdl::decode::type_name(node).map(::core::convert::Into::into)
+                });
+                continue;
+            }
+            Kind::NodeName => {
+                inits.push(quote! { #name: ::core::convert::Into::into(node.name.clone()) });
+                continue;
+            }
+        };
+
+        // `Option` fields decode their inner type, so a missing entry is `None` rather than an error.
+        // Arguments and properties can also be `null`, which is how `None` arguments are encoded.
+        let init = if optional && nullable {
+            quote! { ::core::option::Option::flatten(#value) }
+        } else if optional {
+            value
+        } else if attrs.default || attrs.skip_if_default {
+            quote! { #value.unwrap_or_default() }
+        } else {
+            quote! {
+                match #value {
+                    ::core::option::Option::Some(value) => value,
+                    ::core::option::Option::None => {
+                        return ::core::result::Result::Err(::sleepyhead_kdl::decode::missing(node, #missing))
+                    }
+                }
+            }
+        };
+
+        inits.push(quote! { #name: #init });
+    }
+
+    Ok(quote! {
+        impl #impl_generics ::sleepyhead_kdl::decode::DecodeKdl<#lifetime> for #ident #ty_generics #where_clause {
+            fn decode_node(
+                node: &::sleepyhead_kdl::assembler::KdlNode<#lifetime>,
+            ) -> ::core::result::Result<Self, ::sleepyhead_kdl::decode::DecodeError> {
+                ::core::result::Result::Ok(#ident {
+                    #(#inits,)*
+                })
+            }
+        }
+    })
+}
+
+fn encode_kdl(input: DeriveInput) -> syn::Result<TokenStream2> {
+    let fields = struct_fields(&input, "EncodeKdl")?;
+    let rename = rename_rule(&input)?;
+
+    let (impl_generics, ty_generics, where_clause) = input.generics.split_for_impl();
+    let ident = &input.ident;
+
+    let mut steps = Vec::with_capacity(fields.len());
+
+    for field in fields {
+        let attrs = field_attrs(field, rename)?;
+        let name = attrs.ident;
+        let ty = attrs.ty;
+        let optional = option_inner(ty).is_some();
+
+        let step = match attrs.kind {
+            Kind::Argument => quote! {
+                node.values.push(::sleepyhead_kdl::encode::EncodeValue::encode_value(&self.#name)?);
+            },
+            Kind::Arguments => quote! {
+                for value in &self.#name {
+                    node.values.push(::sleepyhead_kdl::encode::EncodeValue::encode_value(value)?);
+                }
+            },
+            Kind::Property(key) if optional => quote! {
+                if let ::core::option::Option::Some(value) = &self.#name {
+                    node.attrs.push(::sleepyhead_kdl::encode::property(
+                        #key,
+                        ::sleepyhead_kdl::encode::EncodeValue::encode_value(value)?,
+                    ));
+                }
+            },
+            Kind::Property(key) => quote! {
+                node.attrs.push(::sleepyhead_kdl::encode::property(
+                    #key,
+                    ::sleepyhead_kdl::encode::EncodeValue::encode_value(&self.#name)?,
+                ));
+            },
+            Kind::Child(child) if optional => quote! {
+                if let ::core::option::Option::Some(child) = &self.#name {
+                    node.children.push(::sleepyhead_kdl::encode::EncodeKdl::encode_node(child, #child)?);
+                }
+            },
+            Kind::Child(child) => quote! {
+                node.children.push(::sleepyhead_kdl::encode::EncodeKdl::encode_node(&self.#name, #child)?);
+            },
+            Kind::Children(child) => {
+                let child = child.unwrap_or(attrs.kdl_name);
+                quote! {
+                    for child in &self.#name {
+                        node.children.push(::sleepyhead_kdl::encode::EncodeKdl::encode_node(child, #child)?);
+                    }
+                }
+            }
+            Kind::TypeName => quote! {
+                node.ty = self.#name.as_ref().map(|ty| ::core::convert::AsRef::<str>::as_ref(ty));
+            },
+            Kind::NodeName => quote! {
+                node.name = ::core::convert::From::from(::core::convert::AsRef::<str>::as_ref(&self.#name));
+            },
+        };
+
+        steps.push(if attrs.skip_if_default {
+            quote! {
+                if self.#name != <#ty as ::core::default::Default>::default() {
+                    #step
+                }
+            }
+        } else {
+            step
+        });
+    }
+
+    Ok(quote! {
+        impl #impl_generics ::sleepyhead_kdl::encode::EncodeKdl for #ident #ty_generics #where_clause {
+            fn encode_node<'__kdl>(
+                &'__kdl self,
+                name: &'__kdl str,
+            ) -> ::core::result::Result<
+                ::sleepyhead_kdl::assembler::KdlNode<'__kdl>,
+                ::sleepyhead_kdl::encode::EncodeError,
+            > {
+                let mut node = ::sleepyhead_kdl::encode::node(name);
+                #(#steps)*
+                ::core::result::Result::Ok(node)
+            }
+        }
+    })
+}
+
+fn struct_fields<'i>(
+    input: &'i DeriveInput,
+    derive: &str,
+) -> syn::Result<&'i Punctuated<Field, Comma>> {
+    match &input.data {
+        Data::Struct(data) => match &data.fields {
+            Fields::Named(fields) => Ok(&fields.named),
+            _ => Err(syn::Error::new_spanned(
+                &input.ident,
+                format!(
+                    "{} can only be derived for structs with named fields",
+                    derive
+                ),
+            )),
+        },
+        _ => Err(syn::Error::new_spanned(
+            &input.ident,
+            format!("{} can only be derived for structs", derive),
+        )),
+    }
+}
+
+/// Reads the struct's `#[kdl(rename_all = "...")]`, if it has one.
+fn rename_rule(input: &DeriveInput) -> syn::Result<RenameRule> {
+    let mut rule = RenameRule::default();
+
+    for attr in input
+        .attrs
+        .iter()
+        .filter(|attr| attr.path().is_ident("kdl"))
+    {
+        attr.parse_nested_meta(|meta| {
+            if !meta.path.is_ident("rename_all") {
+                return Err(meta.error("expected `rename_all = \"...\"`"));
+            }
+
+            let value = meta.value()?.parse::<LitStr>()?;
+            rule = RenameRule::parse(&value.value())
+                .ok_or_else(|| syn::Error::new_spanned(&value, "unknown rename rule"))?;
+            Ok(())
+        })?;
+    }
+
+    Ok(rule)
+}
+
+fn field_attrs(field: &Field, rename: RenameRule) -> syn::Result<FieldAttrs<'_>> {
+    let ident = field.ident.as_ref().expect("named fields have names");
+    let kdl_name = rename.apply(ident.to_string().trim_start_matches("r#"));
+
+    let mut kind = None;
+    let mut default = false;
+    let mut skip_if_default = false;
+
+    for attr in field
+        .attrs
+        .iter()
+        .filter(|attr| attr.path().is_ident("kdl"))
+    {
+        attr.parse_nested_meta(|meta| {
+            let ident = meta
+                .path
+                .get_ident()
+                .map(|ident| ident.to_string())
+                .unwrap_or_default();
+
+            match ident.as_str() {
+                "default" => {
+                    default = true;
+                    return Ok(());
+                }
+                "skip_if_default" => {
+                    skip_if_default = true;
+                    return Ok(());
+                }
+                _ => (),
+            }
+
+            // `property`, `child` and `children` take an optional `(name = "...")`.
+            let mut name = None;
+            if meta.input.peek(syn::token::Paren) {
+                meta.parse_nested_meta(|inner| {
+                    if inner.path.is_ident("name") {
+                        name = Some(inner.value()?.parse::<LitStr>()?.value());
+                        Ok(())
+                    } else {
+                        Err(inner.error("expected `name = \"...\"`"))
+                    }
+                })?;
+            }
+
+            let parsed = match ident.as_str() {
+                "argument" => Kind::Argument,
+                "arguments" => Kind::Arguments,
+                "property" => Kind::Property(name.take().unwrap_or_else(|| kdl_name.clone())),
+                "child" => Kind::Child(name.take().unwrap_or_else(|| kdl_name.clone())),
+                "children" => Kind::Children(name.take()),
+                "type_name" => Kind::TypeName,
+                "node_name" => Kind::NodeName,
+                _ => return Err(meta.error("unknown kdl attribute")),
+            };
+
+            if name.is_some() {
+                return Err(meta.error("only `property`, `child` and `children` take a name"));
+            }
+
+            if kind.replace(parsed).is_some() {
+                return Err(meta.error("a field can only map to one place in the node"));
+            }
+
+            Ok(())
+        })?;
+    }
+
+    match kind {
+        Some(kind) => Ok(FieldAttrs {
+            ident,
+            ty: &field.ty,
+            kdl_name,
+            kind,
+            default,
+            skip_if_default,
+        }),
+        None => Err(syn::Error::new_spanned(
+            field,
+            "field needs a #[kdl(...)] attribute saying where it goes in the node",
+        )),
+    }
+}
+
+/// Returns `T` if the type is an `Option<T>`.
+fn option_inner(ty: &Type) -> Option<&Type> {
+    let Type::Path(path) = ty else { return None };
+    let segment = path.path.segments.last()?;
+
+    if segment.ident != "Option" {
+        return None;
+    }
+
+    match &segment.arguments {
+        PathArguments::AngleBracketed(args) => match args.args.first()? {
+            GenericArgument::Type(ty) => Some(ty),
+            _ => None,
+        },
+        _ => None,
+    }
+}
@@ -0,0 +1,237 @@
+#[cfg(all(not(feature = "std"), feature = "alloc"))]
+use alloc::{
+    collections::BTreeMap,
+    string::{String, ToString},
+    vec,
+    vec::Vec,
+};
+
+#[cfg(feature = "std")]
+use std::collections::BTreeMap;
+
+use core::fmt;
+
+use crate::assembler::{KdlDocument, KdlNode};
+use crate::ast::*;
+use crate::ParseError;
+
+/// What went wrong while resolving references.
+#[derive(Debug, Clone)]
+pub enum AnchorErrorKind {
+    Parse(ParseError),
+    /// A reference to an anchor that doesn't exist.
+    Undefined(String),
+    /// Two nodes with the same anchor.
+    Duplicate(String),
+    /// An anchor that ends up referencing itself; contains the anchors involved, in the order they were followed.
+    Cycle(Vec<String>),
+    /// An `(anchor)` or `(ref)` value that isn't a string.
+    NotAName,
+}
+
+/// An error resolving references, with the path to the node it happened at.
+#[derive(Debug, Clone)]
+pub struct AnchorError {
+    pub kind: AnchorErrorKind,
+    /// Names of the nodes leading to the one that failed, outermost first.
+    pub path: Vec<String>,
+}
+
+impl fmt::Display for AnchorErrorKind {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            AnchorErrorKind::Parse(e) => write!(f, "parse error: {:?}", e),
+            AnchorErrorKind::Undefined(name) => write!(f, "no anchor named `{}`", name),
+            AnchorErrorKind::Duplicate(name) => write!(f, "anchor `{}` is defined twice", name),
+            AnchorErrorKind::Cycle(names) => {
+                write!(f, "anchor `{}` references itself", names[0])?;
+                if names.len() > 1 {
+                    write!(f, " through `{}`", names[1..].join("`, `"))?;
+                }
+                Ok(())
+            }
+            AnchorErrorKind::NotAName => f.write_str("anchors and references need a string name"),
+        }
+    }
+}
+
+impl fmt::Display for AnchorError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "{}: {}", self.path.join(" > "), self.kind)
+    }
+}
+
+#[cfg(feature = "std")]
+impl std::error::Error for AnchorError {}
+
+/// Resolves anchors and references. A node with an `(anchor)"name"` argument can be reused by any other node with a `(ref)"name"` argument:
+///
+/// ```kdl
+/// defaults (anchor)"service" timeout=30 {
+///     retries 3
+/// }
+/// api "api.example.com" (ref)"service" timeout=60 {
+///     port 8080
+/// }
+/// ```
+///
+/// The reference is replaced by the anchor's other arguments, and the anchor's properties and children come before the node's own, so `api` ends up with `timeout=60`, then `retries 3` and `port 8080` as children. A property brought in more than once is only kept once, with the value that's in effect.
+/// Anchors can reference other anchors, and can be anywhere in the document. The `(anchor)` arguments are removed, but the anchored nodes are otherwise left as they were.
+pub fn resolve<'a>(doc: &KdlDocument<'a>) -> Result<KdlDocument<'a>, AnchorError> {
+    let mut anchors = BTreeMap::new();
+    find_anchors(&doc.nodes, &mut anchors)?;
+
+    let mut resolver = Resolver {
+        anchors,
+        resolved: BTreeMap::new(),
+        following: Vec::new(),
+    };
+    let nodes = doc
+        .nodes
+        .iter()
+        .map(|node| resolver.node(node))
+        .collect::<Result<_, _>>()?;
+
+    Ok(KdlDocument { nodes })
+}
+
+/// The name in an `(anchor)` or `(ref)` value, if it's annotated with `ty`.
+fn name_of(value: &TypedValue<'_>, ty: &str) -> Result<Option<String>, AnchorErrorKind> {
+    if value.ty != Some(ty) {
+        return Ok(None);
+    }
+
+    match value.val {
+        KdlValue::String(s) => Ok(Some(
+            s.unescape().map_err(AnchorErrorKind::Parse)?.into_owned(),
+        )),
+        _ => Err(AnchorErrorKind::NotAName),
+    }
+}
+
+fn find_anchors<'r, 'a>(
+    nodes: &'r [KdlNode<'a>],
+    anchors: &mut BTreeMap<String, &'r KdlNode<'a>>,
+) -> Result<(), AnchorError> {
+    for node in nodes {
+        for value in &node.values {
+            let name = name_of(value, "anchor").map_err(|kind| error(node, kind))?;
+            if let Some(name) = name {
+                if anchors.insert(name.clone(), node).is_some() {
+                    return Err(error(node, AnchorErrorKind::Duplicate(name)));
+                }
+            }
+        }
+
+        find_anchors(&node.children, anchors).map_err(|e| within(e, node))?;
+    }
+
+    Ok(())
+}
+
+struct Resolver<'r, 'a> {
+    anchors: BTreeMap<String, &'r KdlNode<'a>>,
+    /// Anchors that have already been resolved, so each is only resolved once however often it's referenced.
+    resolved: BTreeMap<String, KdlNode<'a>>,
+    /// The anchors being resolved right now, to catch cycles.
+    following: Vec<String>,
+}
+
+impl<'r, 'a> Resolver<'r, 'a> {
+    fn node(&mut self, node: &KdlNode<'a>) -> Result<KdlNode<'a>, AnchorError> {
+        let mut anchored = 0;
+        for value in &node.values {
+            if let Some(name) = name_of(value, "anchor").map_err(|kind| error(node, kind))? {
+                self.enter(name).map_err(|kind| error(node, kind))?;
+                anchored += 1;
+            }
+        }
+
+        let resolved = self.resolve(node);
+        self.following.truncate(self.following.len() - anchored);
+        resolved
+    }
+
+    fn resolve(&mut self, node: &KdlNode<'a>) -> Result<KdlNode<'a>, AnchorError> {
+        let mut resolved = KdlNode {
+            ty: node.ty,
+            name: node.name.clone(),
+            attrs: Vec::new(),
+            values: Vec::new(),
+            children: Vec::new(),
+        };
+
+        for value in &node.values {
+            if name_of(value, "anchor")
+                .map_err(|kind| error(node, kind))?
+                .is_some()
+            {
+                continue;
+            }
+
+            let name = match name_of(value, "ref").map_err(|kind| error(node, kind))? {
+                Some(name) => name,
+                None => {
+                    resolved.values.push(*value);
+                    continue;
+                }
+            };
+
+            // only the anchor's own contents are brought in; it's name stays behind
+            let anchor = self.anchor(node, name)?;
+
+            resolved.values.extend_from_slice(&anchor.values);
+            for attr in &anchor.attrs {
+                resolved.set_prop(attr.key, attr.value);
+            }
+            resolved.children.extend_from_slice(&anchor.children);
+        }
+
+        // the node's own properties come last, so they win
+        for attr in &node.attrs {
+            resolved.set_prop(attr.key, attr.value);
+        }
+
+        for child in &node.children {
+            let child = self.node(child).map_err(|e| within(e, node))?;
+            resolved.children.push(child);
+        }
+
+        Ok(resolved)
+    }
+
+    /// The resolved anchor with the given name, referenced from `node`.
+    fn anchor(&mut self, node: &KdlNode<'a>, name: String) -> Result<&KdlNode<'a>, AnchorError> {
+        if !self.resolved.contains_key(&name) {
+            let anchor = *self
+                .anchors
+                .get(&name)
+                .ok_or_else(|| error(node, AnchorErrorKind::Undefined(name.clone())))?;
+            let anchor = self.node(anchor).map_err(|e| within(e, node))?;
+            self.resolved.insert(name.clone(), anchor);
+        }
+
+        Ok(&self.resolved[&name])
+    }
+
+    fn enter(&mut self, name: String) -> Result<(), AnchorErrorKind> {
+        if let Some(start) = self.following.iter().position(|other| *other == name) {
+            return Err(AnchorErrorKind::Cycle(self.following[start..].to_vec()));
+        }
+
+        self.following.push(name);
+        Ok(())
+    }
+}
+
+fn error(node: &KdlNode<'_>, kind: AnchorErrorKind) -> AnchorError {
+    AnchorError {
+        kind,
+        path: vec![node.name.to_string()],
+    }
+}
+
+fn within(mut e: AnchorError, parent: &KdlNode<'_>) -> AnchorError {
+    e.path.insert(0, parent.name.to_string());
+    e
+}
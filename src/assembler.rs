@@ -1,47 +1,412 @@
 #[cfg(all(not(feature = "std"), feature = "alloc"))]
-use alloc::borrow::Cow;
+use alloc::{borrow::Cow, vec, vec::Vec};
 
 #[cfg(all(not(feature = "alloc"), feature = "std"))]
 use std::borrow::Cow;
 
+use core::ops::Range;
+
 use crate::ast::*;
 use crate::lex::Token;
 use crate::parser::Parser;
-use crate::{KdlEvent, ParseResult};
+use crate::{KdlEvent, ParseError, ParseResult};
 
 /// An assembled KdlNode.
 #[derive(Debug, Clone, PartialEq)]
 pub struct KdlNode<'a> {
+    /// The node's type annotation, e.g. `array` in `(array)numbers 1 2 3`.
+    pub ty: Option<&'a str>,
     pub name: Cow<'a, str>,
     pub attrs: Vec<KdlProperty<'a>>,
     pub values: Vec<TypedValue<'a>>,
     pub children: Vec<KdlNode<'a>>,
 }
 
-/// Parses a document into a vector of it's top-level nodes.
-pub fn parse_document<'a, T: Iterator<Item = Token<'a>>>(
-    parser: &mut Parser<'a, T>,
+/// An assembled document; it's top-level nodes.
+#[derive(Debug, Clone, PartialEq, Default)]
+pub struct KdlDocument<'a> {
+    pub nodes: Vec<KdlNode<'a>>,
+}
+
+impl<'a> KdlDocument<'a> {
+    /// Parses a document from a string.
+    pub fn parse(input: &'a str) -> ParseResult<KdlDocument<'a>> {
+        KdlDocument::from_parser(&mut Parser::from_str(input))
+    }
+
+    /// Parses a document, consuming events from a parser.
+    pub fn from_parser<T: Iterator<Item = Token<'a>>, const ENTRIES: usize, const DEPTH: usize>(
+        parser: &mut Parser<'a, T, ENTRIES, DEPTH>,
+    ) -> ParseResult<KdlDocument<'a>> {
+        parse_document(parser).map(KdlDocument::from)
+    }
+
+    /// The first top-level node with the given name.
+    pub fn get(&self, name: &str) -> Option<&KdlNode<'a>> {
+        self.nodes.iter().find(|node| node.name == name)
+    }
+
+    /// Every top-level node with the given name.
+    pub fn get_all<'r>(&'r self, name: &'r str) -> impl Iterator<Item = &'r KdlNode<'a>> {
+        self.nodes.iter().filter(move |node| node.name == name)
+    }
+
+    /// Every node in the document, depth-first, parents before their children.
+    pub fn descendants(&self) -> Descendants<'_, 'a> {
+        Descendants::new(&self.nodes)
+    }
+
+    /// The first top-level node with the given name.
+    pub fn get_mut(&mut self, name: &str) -> Option<&mut KdlNode<'a>> {
+        self.nodes.iter_mut().find(|node| node.name == name)
+    }
+
+    /// Inserts a top-level node.
+    pub fn insert_node(&mut self, idx: usize, node: KdlNode<'a>) {
+        self.nodes.insert(idx, node);
+    }
+
+    /// Removes a top-level node, returning `None` if there aren't that many nodes.
+    pub fn remove_node(&mut self, idx: usize) -> Option<KdlNode<'a>> {
+        (idx < self.nodes.len()).then(|| self.nodes.remove(idx))
+    }
+
+    /// The node at a path of indices, e.g. `[0, 2]` for the third child of the first node.
+    pub fn node_at(&self, path: &[usize]) -> Option<&KdlNode<'a>> {
+        let (first, rest) = path.split_first()?;
+        rest.iter()
+            .try_fold(self.nodes.get(*first)?, |node, idx| node.children.get(*idx))
+    }
+
+    /// The node at a path of indices, as in [KdlDocument::node_at].
+    pub fn node_at_mut(&mut self, path: &[usize]) -> Option<&mut KdlNode<'a>> {
+        let (first, rest) = path.split_first()?;
+        rest.iter()
+            .try_fold(self.nodes.get_mut(*first)?, |node, idx| {
+                node.children.get_mut(*idx)
+            })
+    }
+
+    /// Moves the subtree at `from` so it ends up at `to`, both paths of indices; `to` is looked up after the subtree's been taken out.
+    /// Returns false, leaving the document as it was, if either path doesn't exist or `to` is inside the subtree itself.
+    pub fn move_node(&mut self, from: &[usize], to: &[usize]) -> bool {
+        if from.is_empty() || to.is_empty() || (to.len() > from.len() && to.starts_with(from)) {
+            return false;
+        }
+
+        let node = match self
+            .siblings_mut(from)
+            .and_then(|(nodes, idx)| (idx < nodes.len()).then(|| nodes.remove(idx)))
+        {
+            Some(node) => node,
+            None => return false,
+        };
+
+        match self.siblings_mut(to) {
+            Some((nodes, idx)) if idx <= nodes.len() => {
+                nodes.insert(idx, node);
+                true
+            }
+            _ => {
+                // put it back where it was
+                if let Some((nodes, idx)) = self.siblings_mut(from) {
+                    nodes.insert(idx, node);
+                }
+                false
+            }
+        }
+    }
+
+    /// The list of nodes a path's last index points into, along with that index.
+    fn siblings_mut(&mut self, path: &[usize]) -> Option<(&mut Vec<KdlNode<'a>>, usize)> {
+        let (last, parent) = path.split_last()?;
+        let nodes = if parent.is_empty() {
+            &mut self.nodes
+        } else {
+            &mut self.node_at_mut(parent)?.children
+        };
+
+        Some((nodes, *last))
+    }
+}
+
+/// Where a node's name was in memory, kept by errors so they can point back into the source with [NameLocation::span_in].
+///
+/// Nodes don't carry spans, so this is best-effort: it only works for names borrowed straight from the input, not ones that had escapes or were built or cloned into owned strings.
+#[derive(Debug, Copy, Clone)]
+pub(crate) struct NameLocation {
+    addr: usize,
+    len: usize,
+}
+
+impl NameLocation {
+    pub(crate) fn of(node: &KdlNode<'_>) -> Option<NameLocation> {
+        match &node.name {
+            Cow::Borrowed(name) => Some(NameLocation {
+                addr: name.as_ptr() as usize,
+                len: name.len(),
+            }),
+            Cow::Owned(_) => None,
+        }
+    }
+
+    /// The byte range of the name within `source`, if it points into it.
+    pub(crate) fn span_in(self, source: &str) -> Option<Range<usize>> {
+        let start = self.addr.checked_sub(source.as_ptr() as usize)?;
+
+        if start + self.len <= source.len() {
+            Some(start..start + self.len)
+        } else {
+            None
+        }
+    }
+}
+
+impl<'a> From<Vec<KdlNode<'a>>> for KdlDocument<'a> {
+    fn from(nodes: Vec<KdlNode<'a>>) -> KdlDocument<'a> {
+        KdlDocument { nodes }
+    }
+}
+
+impl<'a> KdlNode<'a> {
+    /// Builds an empty node.
+    pub fn new(name: impl Into<Cow<'a, str>>) -> KdlNode<'a> {
+        KdlNode {
+            ty: None,
+            name: name.into(),
+            attrs: Vec::new(),
+            values: Vec::new(),
+            children: Vec::new(),
+        }
+    }
+
+    /// The first child with the given name.
+    pub fn get(&self, name: &str) -> Option<&KdlNode<'a>> {
+        self.children.iter().find(|child| child.name == name)
+    }
+
+    /// Every child with the given name.
+    pub fn get_all<'r>(&'r self, name: &'r str) -> impl Iterator<Item = &'r KdlNode<'a>> {
+        self.children.iter().filter(move |child| child.name == name)
+    }
+
+    /// The value of a property. If the key is repeated, the last one wins.
+    pub fn prop(&self, key: &str) -> Option<&TypedValue<'a>> {
+        self.attrs
+            .iter()
+            .rev()
+            .find(|attr| attr.key == KdlString::Escapeless(key))
+            .map(|attr| &attr.value)
+    }
+
+    /// The `idx`th argument.
+    pub fn arg(&self, idx: usize) -> Option<&TypedValue<'a>> {
+        self.values.get(idx)
+    }
+
+    /// Every node below this one, depth-first, parents before their children.
+    pub fn descendants(&self) -> Descendants<'_, 'a> {
+        Descendants::new(&self.children)
+    }
+
+    /// The first child with the given name.
+    pub fn get_mut(&mut self, name: &str) -> Option<&mut KdlNode<'a>> {
+        self.children.iter_mut().find(|child| child.name == name)
+    }
+
+    /// Renames the node; the name can be borrowed or owned.
+    pub fn rename(&mut self, name: impl Into<Cow<'a, str>>) {
+        self.name = name.into();
+    }
+
+    /// Sets a property, replacing the value of the last property with that key in place and dropping any earlier ones, or appending a new property if there isn't one.
+    /// Returns the value it replaced.
+    ///
+    /// Values borrow their strings, so the key and value have to live as long as the node does.
+    pub fn set_prop(
+        &mut self,
+        key: impl Into<KdlString<'a>>,
+        value: TypedValue<'a>,
+    ) -> Option<TypedValue<'a>> {
+        let key = key.into();
+
+        match self.attrs.iter().rposition(|attr| attr.key == key) {
+            Some(pos) => {
+                let old = core::mem::replace(&mut self.attrs[pos].value, value);
+
+                let mut idx = 0;
+                self.attrs.retain(|attr| {
+                    idx += 1;
+                    idx > pos || attr.key != key
+                });
+
+                Some(old)
+            }
+            None => {
+                self.attrs.push(KdlProperty { key, value });
+                None
+            }
+        }
+    }
+
+    /// Removes every property with the given key, returning the value that was in effect.
+    pub fn remove_prop<'k>(&mut self, key: impl Into<KdlString<'k>>) -> Option<TypedValue<'a>> {
+        let key = key.into();
+        let old = self
+            .attrs
+            .iter()
+            .rev()
+            .find(|attr| attr.key == key)
+            .map(|attr| attr.value);
+        self.attrs.retain(|attr| attr.key != key);
+
+        old
+    }
+
+    /// Appends an argument.
+    pub fn push_arg(&mut self, value: TypedValue<'a>) {
+        self.values.push(value);
+    }
+
+    /// Inserts an argument before the `idx`th one; panics if there are fewer than `idx` arguments.
+    pub fn insert_arg(&mut self, idx: usize, value: TypedValue<'a>) {
+        self.values.insert(idx, value);
+    }
+
+    /// Replaces the `idx`th argument, returning the old one, or `None` if there aren't that many arguments.
+    pub fn set_arg(&mut self, idx: usize, value: TypedValue<'a>) -> Option<TypedValue<'a>> {
+        self.values
+            .get_mut(idx)
+            .map(|old| core::mem::replace(old, value))
+    }
+
+    /// Removes the `idx`th argument, returning `None` if there aren't that many arguments.
+    pub fn remove_arg(&mut self, idx: usize) -> Option<TypedValue<'a>> {
+        (idx < self.values.len()).then(|| self.values.remove(idx))
+    }
+
+    /// Appends a child.
+    pub fn push_child(&mut self, child: KdlNode<'a>) {
+        self.children.push(child);
+    }
+
+    /// Inserts a child before the `idx`th one; panics if there are fewer than `idx` children.
+    pub fn insert_child(&mut self, idx: usize, child: KdlNode<'a>) {
+        self.children.insert(idx, child);
+    }
+
+    /// Removes the `idx`th child, returning `None` if there aren't that many children.
+    pub fn remove_child(&mut self, idx: usize) -> Option<KdlNode<'a>> {
+        (idx < self.children.len()).then(|| self.children.remove(idx))
+    }
+}
+
+/// Iterator over nodes and all their children, from [KdlDocument::descendants] and [KdlNode::descendants].
+pub struct Descendants<'r, 'a> {
+    /// The siblings left to visit at each level.
+    stack: Vec<core::slice::Iter<'r, KdlNode<'a>>>,
+}
+
+impl<'r, 'a> Descendants<'r, 'a> {
+    fn new(nodes: &'r [KdlNode<'a>]) -> Descendants<'r, 'a> {
+        Descendants {
+            stack: vec![nodes.iter()],
+        }
+    }
+}
+
+impl<'r, 'a> Iterator for Descendants<'r, 'a> {
+    type Item = &'r KdlNode<'a>;
+
+    fn next(&mut self) -> Option<&'r KdlNode<'a>> {
+        loop {
+            match self.stack.last_mut()?.next() {
+                Some(node) => {
+                    if !node.children.is_empty() {
+                        self.stack.push(node.children.iter());
+                    }
+                    return Some(node);
+                }
+                None => {
+                    self.stack.pop();
+                }
+            }
+        }
+    }
+}
+
+/// How deeply [parse_document] lets children blocks nest.
+/// Assembling doesn't recurse, but dropping, cloning or comparing nodes does, so this keeps untrusted input from overflowing the stack later on.
+pub const DEFAULT_MAX_DEPTH: usize = 1024;
+
+/// Parses a document into a vector of it's top-level nodes, failing with [ParseError::TooDeeplyNested] past [DEFAULT_MAX_DEPTH] levels.
+/// Use [parse_document_with_max_depth] for another limit.
+pub fn parse_document<
+    'a,
+    T: Iterator<Item = Token<'a>>,
+    const ENTRIES: usize,
+    const DEPTH: usize,
+>(
+    parser: &mut Parser<'a, T, ENTRIES, DEPTH>,
+) -> ParseResult<Vec<KdlNode<'a>>> {
+    parse_document_with_max_depth(parser, DEFAULT_MAX_DEPTH)
+}
+
+/// Parses a document into a vector of it's top-level nodes, failing with [ParseError::TooDeeplyNested] if nodes are nested more than `max_depth` children blocks deep; e.g. with a `max_depth` of 1, top-level nodes can have children, but those can't.
+///
+/// Assembling doesn't recurse, so the limit can be as high as memory allows; but dropping, cloning or comparing nodes does, so a very high one needs a big enough stack.
+pub fn parse_document_with_max_depth<
+    'a,
+    T: Iterator<Item = Token<'a>>,
+    const ENTRIES: usize,
+    const DEPTH: usize,
+>(
+    parser: &mut Parser<'a, T, ENTRIES, DEPTH>,
+    max_depth: usize,
 ) -> ParseResult<Vec<KdlNode<'a>>> {
     let mut output = Vec::new();
-    add_children(parser, &mut output)?;
+    add_children_with_max_depth(parser, &mut output, max_depth)?;
     Ok(output)
 }
 
-/// Adds children nodes to a vector, consuming events from a parser.
-pub(crate) fn add_children<'a, T: Iterator<Item = Token<'a>>>(
-    parser: &mut Parser<'a, T>,
+/// Adds children nodes to a vector, consuming events from a parser, up to [DEFAULT_MAX_DEPTH] levels deep.
+pub(crate) fn add_children<
+    'a,
+    T: Iterator<Item = Token<'a>>,
+    const ENTRIES: usize,
+    const DEPTH: usize,
+>(
+    parser: &mut Parser<'a, T, ENTRIES, DEPTH>,
+    children: &mut Vec<KdlNode<'a>>,
+) -> ParseResult<()> {
+    add_children_with_max_depth(parser, children, DEFAULT_MAX_DEPTH)
+}
+
+fn add_children_with_max_depth<
+    'a,
+    T: Iterator<Item = Token<'a>>,
+    const ENTRIES: usize,
+    const DEPTH: usize,
+>(
+    parser: &mut Parser<'a, T, ENTRIES, DEPTH>,
     children: &mut Vec<KdlNode<'a>>,
+    max_depth: usize,
 ) -> ParseResult<()> {
-    while let Some(next_event) = parser.next() {
+    // nodes whose children are still being read, innermost last
+    let mut open: Vec<KdlNode<'a>> = Vec::new();
+
+    for next_event in parser.by_ref() {
         let next_event = next_event?;
         match next_event {
             KdlEvent::NodeOpen {
+                ty,
                 name,
                 attrs,
                 values,
                 has_children,
             } => {
-                let mut child = KdlNode {
+                let child = KdlNode {
+                    ty,
                     name: name.unescape()?,
                     children: Vec::new(),
                     attrs,
@@ -49,15 +414,37 @@ pub(crate) fn add_children<'a, T: Iterator<Item = Token<'a>>>(
                 };
 
                 if has_children {
-                    add_children(parser, &mut child.children)?;
+                    if open.len() >= max_depth {
+                        return Err(ParseError::TooDeeplyNested);
+                    }
+                    open.push(child);
+                } else {
+                    siblings(children, &mut open).push(child);
                 }
-
-                children.push(child);
             }
-            KdlEvent::BracketedNodeClose(_) => return Ok(()),
+            KdlEvent::BracketedNodeClose(_) => match open.pop() {
+                Some(closed) => siblings(children, &mut open).push(closed),
+                None => return Ok(()),
+            },
             KdlEvent::NodeClose(_) => continue,
         }
     }
 
+    // anything still open at the end of input is closed by it
+    while let Some(closed) = open.pop() {
+        siblings(children, &mut open).push(closed);
+    }
+
     Ok(())
 }
+
+/// Where nodes are added: the innermost open node's children, or the top level.
+fn siblings<'r, 'a>(
+    children: &'r mut Vec<KdlNode<'a>>,
+    open: &'r mut [KdlNode<'a>],
+) -> &'r mut Vec<KdlNode<'a>> {
+    match open.last_mut() {
+        Some(parent) => &mut parent.children,
+        None => children,
+    }
+}
@@ -135,6 +135,13 @@ impl<'a> KdlString<'a> {
     }
 }
 
+/// A plain string, which doesn't have escapes to process.
+impl<'a> From<&'a str> for KdlString<'a> {
+    fn from(s: &'a str) -> KdlString<'a> {
+        KdlString::Escapeless(s)
+    }
+}
+
 impl<'a> PartialEq for KdlString<'a> {
     fn eq(&self, other: &Self) -> bool {
         #[cfg(any(feature = "std", feature = "alloc"))]
@@ -0,0 +1,664 @@
+use std::collections::{HashMap, HashSet};
+use std::fmt::{self, Write as _};
+use std::path::Path;
+use std::{fs, io};
+
+use crate::assembler::KdlNode;
+use crate::schema::{count_of, flag_of, rule_name, string_of, string_prop, Schema, SchemaError};
+
+const DECODE: &str = "::sleepyhead_kdl::decode";
+
+/// Names the generated code uses from the prelude, which generated types mustn't shadow.
+const RESERVED: &[&str] = &[
+    "Box", "Err", "None", "Ok", "Option", "Result", "Self", "Some", "String", "Vec",
+];
+
+const KEYWORDS: &[&str] = &[
+    "abstract", "as", "async", "await", "become", "box", "break", "const", "continue", "do", "dyn",
+    "else", "enum", "extern", "false", "final", "fn", "for", "gen", "if", "impl", "in", "let",
+    "loop", "macro", "match", "mod", "move", "mut", "override", "priv", "pub", "ref", "return",
+    "static", "struct", "trait", "true", "try", "type", "typeof", "unsafe", "unsized", "use",
+    "virtual", "where", "while", "yield",
+];
+
+/// Options for [generate].
+#[derive(Debug, Clone)]
+pub struct CodegenOptions {
+    /// Name of the struct generated for the schema's `document` rule.
+    pub document_name: String,
+    /// Derives added to every generated struct and enum.
+    pub derives: Vec<String>,
+}
+
+impl Default for CodegenOptions {
+    fn default() -> CodegenOptions {
+        CodegenOptions {
+            document_name: "Document".into(),
+            derives: vec!["Debug".into(), "Clone".into(), "PartialEq".into()],
+        }
+    }
+}
+
+/// An error from [generate_file].
+#[derive(Debug)]
+pub enum CodegenError {
+    Io(io::Error),
+    Schema(SchemaError),
+}
+
+impl fmt::Display for CodegenError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            CodegenError::Io(e) => write!(f, "{}", e),
+            CodegenError::Schema(e) => write!(f, "{}", e),
+        }
+    }
+}
+
+impl std::error::Error for CodegenError {}
+
+/// Generates Rust types for a schema, with [crate::decode::DecodeKdl] impls to read them from [KdlNode]s.
+///
+/// Every named `node` rule becomes a struct, and the `document` rule becomes a struct named [CodegenOptions::document_name], to be decoded with [crate::decode::decode_document] and friends.
+/// - A `value` rule with `max 1` becomes a `value` field, otherwise a `values` field holding every argument.
+/// - Each named `prop` rule becomes a field, which is an `Option` unless the prop is `required`.
+/// - Each named `node` in a `children` rule becomes a field: a `Vec`, or an `Option` if it has `max 1` (or a plain value if it also has `min 1`).
+///
+/// Values are typed from their `type` and `format` validations, and string `enum`s become enums. Values of mixed or unknown type are [crate::decode::Value]s.
+/// Rules without names, and `other-nodes-allowed`/`other-props-allowed`, aren't represented; nothing else is validated, so pair this with [Schema::validate] if that matters.
+///
+/// The generated code uses `String`, `Vec` and `Box` from the std prelude.
+pub fn generate(schema: &Schema<'_>, options: &CodegenOptions) -> String {
+    let mut generator = Generator {
+        schema,
+        types: HashMap::new(),
+        names: RESERVED.iter().map(|name| name.to_string()).collect(),
+        structs: Vec::new(),
+        enums: Vec::new(),
+    };
+
+    let document = schema.document();
+    let name = generator.type_name(&options.document_name, "");
+    generator.structs.push(Struct {
+        name,
+        docs: None,
+        rule: document,
+        is_document: true,
+        fields: Vec::new(),
+    });
+
+    // rules found while filling in a struct are queued up as new structs
+    let mut idx = 0;
+    while idx < generator.structs.len() {
+        generator.fill(idx);
+        idx += 1;
+    }
+
+    generator.box_recursive_fields();
+    generator.render(options)
+}
+
+/// Generates code for the schema at `schema`, writing it to `out`; meant for `build.rs`:
+///
+/// ```no_run
+/// # use sleepyhead_kdl::codegen::*;
+/// let out = std::path::Path::new(&std::env::var("OUT_DIR").unwrap()).join("config.rs");
+/// generate_file("config.kdl", out, &CodegenOptions::default()).unwrap();
+/// println!("cargo:rerun-if-changed=config.kdl");
+/// ```
+///
+/// and then `include!(concat!(env!("OUT_DIR"), "/config.rs"));` in the crate. `out` is left alone if it's contents wouldn't change.
+pub fn generate_file(
+    schema: impl AsRef<Path>,
+    out: impl AsRef<Path>,
+    options: &CodegenOptions,
+) -> Result<(), CodegenError> {
+    let input = fs::read_to_string(schema).map_err(CodegenError::Io)?;
+    let schema = Schema::parse(&input).map_err(CodegenError::Schema)?;
+    let code = generate(&schema, options);
+
+    if fs::read_to_string(out.as_ref()).ok().as_deref() != Some(code.as_str()) {
+        fs::write(out, code).map_err(CodegenError::Io)?;
+    }
+
+    Ok(())
+}
+
+struct Struct<'g, 's> {
+    name: String,
+    docs: Option<String>,
+    rule: &'g KdlNode<'s>,
+    /// Whether this is the `document` rule, which holds `node` rules directly instead of in `children`.
+    is_document: bool,
+    fields: Vec<Field>,
+}
+
+struct Enum {
+    name: String,
+    docs: Option<String>,
+    /// Each variant, with the string it's decoded from.
+    variants: Vec<(String, String)>,
+}
+
+struct Field {
+    name: String,
+    docs: Option<String>,
+    source: Source,
+    ty: FieldType,
+    count: Count,
+}
+
+enum Source {
+    Argument,
+    Arguments,
+    Property(String),
+    Child(String),
+}
+
+enum FieldType {
+    Value(String),
+    /// Index of the child's struct, and whether it needs boxing to break a cycle.
+    Node(usize, bool),
+}
+
+#[derive(Clone, Copy, PartialEq)]
+enum Count {
+    Required,
+    Optional,
+    Many,
+}
+
+struct Generator<'g, 's> {
+    schema: &'g Schema<'s>,
+    /// Structs already made for `node` rules, by the rule's address.
+    types: HashMap<*const KdlNode<'s>, usize>,
+    /// Every type name in use.
+    names: HashSet<String>,
+    structs: Vec<Struct<'g, 's>>,
+    enums: Vec<Enum>,
+}
+
+impl<'g, 's> Generator<'g, 's> {
+    /// Picks an unused type name, prefixing it with the parent's name if it's taken.
+    fn type_name(&mut self, name: &str, parent: &str) -> String {
+        let mut name = camel_case(name);
+        if name.is_empty() || name.starts_with(|c: char| c.is_ascii_digit()) {
+            name = format!("{}Rule", parent);
+        }
+
+        if self.names.contains(&name) {
+            name = format!("{}{}", parent, name);
+        }
+
+        let mut unique = name.clone();
+        let mut n = 2;
+        while self.names.contains(&unique) {
+            unique = format!("{}{}", name, n);
+            n += 1;
+        }
+
+        self.names.insert(unique.clone());
+        unique
+    }
+
+    /// The struct for a `node` rule, queueing it up if it's new.
+    fn struct_for(&mut self, rule: &'g KdlNode<'s>, name: &str, parent: &str) -> usize {
+        if let Some(&idx) = self.types.get(&(rule as *const _)) {
+            return idx;
+        }
+
+        let name = self.type_name(name, parent);
+        let idx = self.structs.len();
+        self.structs.push(Struct {
+            name,
+            docs: description(rule),
+            rule,
+            is_document: false,
+            fields: Vec::new(),
+        });
+        self.types.insert(rule as *const _, idx);
+
+        idx
+    }
+
+    fn fill(&mut self, idx: usize) {
+        let schema = self.schema;
+        let rule = self.structs[idx].rule;
+        let name = self.structs[idx].name.clone();
+        let mut fields = Vec::new();
+
+        if self.structs[idx].is_document {
+            self.child_fields(&name, rule, &mut fields);
+        } else {
+            let mut values_done = false;
+
+            for part in schema.parts(rule) {
+                match &*part.name {
+                    "value" if !values_done => {
+                        values_done = true;
+
+                        let min = count_of(schema, part, "min").unwrap_or(0);
+                        let max = count_of(schema, part, "max");
+                        let ty = self.value_type(part, &format!("{}Value", name));
+
+                        let (field, source, count) = match max {
+                            Some(1) if min >= 1 => ("value", Source::Argument, Count::Required),
+                            Some(1) => ("value", Source::Argument, Count::Optional),
+                            _ => ("values", Source::Arguments, Count::Many),
+                        };
+
+                        fields.push(Field {
+                            name: field.into(),
+                            docs: description(part),
+                            source,
+                            ty: FieldType::Value(ty),
+                            count,
+                        });
+                    }
+                    "prop" => {
+                        let key = match rule_name(part) {
+                            Some(key) => key,
+                            None => continue,
+                        };
+
+                        let ty = self.value_type(part, &format!("{}{}", name, camel_case(&key)));
+                        let count = if flag_of(schema, part, "required") {
+                            Count::Required
+                        } else {
+                            Count::Optional
+                        };
+
+                        fields.push(Field {
+                            name: field_name(&key, "prop", &fields),
+                            docs: description(part),
+                            source: Source::Property(key.into_owned()),
+                            ty: FieldType::Value(ty),
+                            count,
+                        });
+                    }
+                    "children" => self.child_fields(&name, part, &mut fields),
+                    _ => (),
+                }
+            }
+        }
+
+        self.structs[idx].fields = fields;
+    }
+
+    /// Adds a field for each named `node` in a `children` rule.
+    fn child_fields(&mut self, parent: &str, rule: &'g KdlNode<'s>, fields: &mut Vec<Field>) {
+        let schema = self.schema;
+
+        for part in schema.parts(rule).filter(|part| part.name == "node") {
+            let name = match rule_name(part) {
+                Some(name) => name,
+                None => continue,
+            };
+
+            let min = count_of(schema, part, "min").unwrap_or(0);
+            let count = match count_of(schema, part, "max") {
+                Some(1) if min >= 1 => Count::Required,
+                Some(1) => Count::Optional,
+                _ => Count::Many,
+            };
+
+            let idx = self.struct_for(part, &name, parent);
+            fields.push(Field {
+                name: field_name(&name, "child", fields),
+                docs: description(part),
+                source: Source::Child(name.into_owned()),
+                ty: FieldType::Node(idx, false),
+                count,
+            });
+        }
+    }
+
+    /// The Rust type for values checked by a `value` or `prop` rule's validations.
+    fn value_type(&mut self, rule: &'g KdlNode<'s>, enum_name: &str) -> String {
+        let schema = self.schema;
+        let mut types = Vec::new();
+        let mut format = None;
+        let mut variants = None;
+
+        for part in schema.parts(rule) {
+            match &*part.name {
+                "type" => types.extend(part.values.iter().filter_map(|v| string_of(&v.val))),
+                "format" => format = part.values.first().and_then(|v| string_of(&v.val)),
+                "enum" => {
+                    variants = part
+                        .values
+                        .iter()
+                        .map(|v| string_of(&v.val))
+                        .collect::<Option<Vec<_>>>()
+                }
+                _ => (),
+            }
+        }
+
+        if let Some(values) = variants {
+            if types.is_empty() || types.iter().all(|ty| ty == "string") {
+                if let Some(name) = self.enum_for(rule, &values, enum_name) {
+                    return name;
+                }
+            }
+        }
+
+        let format = format.as_deref().unwrap_or("");
+        let ty = match format {
+            "i8" | "i16" | "i32" | "i64" | "u8" | "u16" | "u32" | "u64" | "f32" | "f64" => format,
+            "decimal64" | "decimal128" => "f64",
+            _ => match types.first().map(|ty| &**ty) {
+                _ if types.len() != 1 => "",
+                Some("string") => "String",
+                Some("number") => "f64",
+                Some("integer") => "i64",
+                Some("boolean" | "bool") => "bool",
+                _ => "",
+            },
+        };
+
+        if ty.is_empty() {
+            format!("{}::Value", DECODE)
+        } else {
+            ty.into()
+        }
+    }
+
+    /// An enum for a string `enum` validation, unless the strings don't make distinct variant names.
+    fn enum_for(
+        &mut self,
+        rule: &KdlNode<'_>,
+        values: &[std::borrow::Cow<'_, str>],
+        name: &str,
+    ) -> Option<String> {
+        let mut variants: Vec<(String, String)> = Vec::new();
+
+        for value in values {
+            let mut variant = camel_case(value);
+            if variant == "Self" {
+                variant.push_str("Value");
+            }
+
+            let valid = variant.starts_with(|c: char| c.is_ascii_alphabetic());
+            if !valid || variants.iter().any(|(existing, _)| *existing == variant) {
+                return None;
+            }
+
+            variants.push((variant, value.to_string()));
+        }
+
+        let name = self.type_name(name, "");
+        self.enums.push(Enum {
+            name: name.clone(),
+            docs: description(rule),
+            variants,
+        });
+
+        Some(name)
+    }
+
+    /// Boxes single children whose type can contain the parent again, which would otherwise have an infinite size.
+    fn box_recursive_fields(&mut self) {
+        let mut boxed = Vec::new();
+
+        for (idx, parent) in self.structs.iter().enumerate() {
+            for (field_idx, field) in parent.fields.iter().enumerate() {
+                if let FieldType::Node(child, _) = field.ty {
+                    if field.count != Count::Many && self.reaches(child, idx, &mut HashSet::new()) {
+                        boxed.push((idx, field_idx, child));
+                    }
+                }
+            }
+        }
+
+        for (idx, field_idx, child) in boxed {
+            self.structs[idx].fields[field_idx].ty = FieldType::Node(child, true);
+        }
+    }
+
+    /// Whether `from` holds a `target` through single children; `Vec`s are already on the heap.
+    fn reaches(&self, from: usize, target: usize, seen: &mut HashSet<usize>) -> bool {
+        if from == target {
+            return true;
+        }
+
+        if !seen.insert(from) {
+            return false;
+        }
+
+        self.structs[from]
+            .fields
+            .iter()
+            .any(|field| match field.ty {
+                FieldType::Node(child, _) if field.count != Count::Many => {
+                    self.reaches(child, target, seen)
+                }
+                _ => false,
+            })
+    }
+
+    fn render(&self, options: &CodegenOptions) -> String {
+        let mut out = String::new();
+        let derives = options.derives.join(", ");
+
+        out.push_str("// Generated by sleepyhead-kdl from a KDL Schema; don't edit by hand.\n");
+
+        for def in &self.structs {
+            out.push('\n');
+            push_docs(&mut out, "", &def.docs);
+            if !derives.is_empty() {
+                let _ = writeln!(out, "#[derive({})]", derives);
+            }
+            let _ = writeln!(out, "pub struct {} {{", def.name);
+            for field in &def.fields {
+                push_docs(&mut out, "    ", &field.docs);
+                let _ = writeln!(out, "    pub {}: {},", field.name, self.field_type(field));
+            }
+            out.push_str("}\n\n");
+
+            let _ = writeln!(
+                out,
+                "impl<'kdl> {decode}::DecodeKdl<'kdl> for {name} {{\n    \
+                 fn decode_node(\n        \
+                 node: &::sleepyhead_kdl::assembler::KdlNode<'kdl>,\n    \
+                 ) -> ::core::result::Result<Self, {decode}::DecodeError> {{\n        \
+                 ::core::result::Result::Ok({name} {{",
+                decode = DECODE,
+                name = def.name,
+            );
+            for field in &def.fields {
+                let _ = writeln!(out, "            {}: {},", field.name, decode_expr(field));
+            }
+            out.push_str("        })\n    }\n}\n");
+        }
+
+        for def in &self.enums {
+            out.push('\n');
+            push_docs(&mut out, "", &def.docs);
+            if !derives.is_empty() {
+                let _ = writeln!(out, "#[derive({})]", derives);
+            }
+            let _ = writeln!(out, "pub enum {} {{", def.name);
+            for (variant, _) in &def.variants {
+                let _ = writeln!(out, "    {},", variant);
+            }
+            out.push_str("}\n\n");
+
+            let _ = writeln!(
+                out,
+                "impl<'kdl> {decode}::DecodeValue<'kdl> for {name} {{\n    \
+                 fn decode_value(\n        \
+                 value: &::sleepyhead_kdl::ast::TypedValue<'kdl>,\n    \
+                 ) -> ::core::result::Result<Self, {decode}::DecodeErrorKind> {{\n        \
+                 let s: ::std::borrow::Cow<'kdl, str> = {decode}::DecodeValue::decode_value(value)?;\n        \
+                 match &*s {{",
+                decode = DECODE,
+                name = def.name,
+            );
+            for (variant, value) in &def.variants {
+                let _ = writeln!(
+                    out,
+                    "            {:?} => ::core::result::Result::Ok({}::{}),",
+                    value, def.name, variant
+                );
+            }
+            let _ = writeln!(
+                out,
+                "            _ => ::core::result::Result::Err({}::DecodeErrorKind::InvalidValue(s.into_owned())),",
+                DECODE
+            );
+            out.push_str("        }\n    }\n}\n");
+        }
+
+        out
+    }
+
+    fn field_type(&self, field: &Field) -> String {
+        let ty = match &field.ty {
+            FieldType::Value(ty) => ty.clone(),
+            FieldType::Node(idx, false) => self.structs[*idx].name.clone(),
+            FieldType::Node(idx, true) => format!("Box<{}>", self.structs[*idx].name),
+        };
+
+        match field.count {
+            Count::Required => ty,
+            Count::Optional => format!("Option<{}>", ty),
+            Count::Many => format!("Vec<{}>", ty),
+        }
+    }
+}
+
+/// The expression decoding a field from `node`.
+fn decode_expr(field: &Field) -> String {
+    let (call, missing) = match &field.source {
+        Source::Argument => (
+            format!("{}::argument(node, 0)?", DECODE),
+            "MissingArgument(0)".to_string(),
+        ),
+        Source::Arguments => return format!("{}::arguments(node, 0)?", DECODE),
+        Source::Property(key) => (
+            format!("{}::property(node, {:?})?", DECODE, key),
+            format!("MissingProperty({:?}.into())", key),
+        ),
+        Source::Child(name) if field.count == Count::Many => {
+            return format!("{}::children(node, Some({:?}))?", DECODE, name)
+        }
+        Source::Child(name) => (
+            format!("{}::child(node, {:?})?", DECODE, name),
+            format!("MissingChild({:?}.into())", name),
+        ),
+    };
+
+    match field.count {
+        Count::Required => format!(
+            "{call}.ok_or_else(|| {{\n                \
+             {decode}::missing(\n                    \
+             node,\n                    \
+             {decode}::DecodeErrorKind::{missing},\n                \
+             )\n            \
+             }})?",
+            call = call,
+            decode = DECODE,
+            missing = missing,
+        ),
+        _ => call,
+    }
+}
+
+/// A rule's `description` property.
+fn description(rule: &KdlNode<'_>) -> Option<String> {
+    string_prop(rule, "description").map(|docs| docs.into_owned())
+}
+
+fn push_docs(out: &mut String, indent: &str, docs: &Option<String>) {
+    if let Some(docs) = docs {
+        for line in docs.lines() {
+            let _ = writeln!(out, "{}/// {}", indent, line.trim());
+        }
+    }
+}
+
+/// Splits a name into words for identifiers, spelling out symbols like the ones in `>=`.
+fn words(name: &str) -> Vec<String> {
+    let mut words = Vec::new();
+    let mut word = String::new();
+
+    for c in name.chars() {
+        if c.is_alphanumeric() {
+            word.push(c);
+            continue;
+        }
+
+        if !word.is_empty() {
+            words.push(core::mem::take(&mut word));
+        }
+
+        let symbol = match c {
+            '%' => "percent",
+            '>' => "gt",
+            '<' => "lt",
+            '=' => "eq",
+            '!' => "not",
+            '+' => "plus",
+            '*' => "star",
+            '/' => "slash",
+            '^' => "caret",
+            '$' => "dollar",
+            '~' => "tilde",
+            '?' => "question",
+            _ => continue,
+        };
+        words.push(symbol.into());
+    }
+
+    if !word.is_empty() {
+        words.push(word);
+    }
+
+    words
+}
+
+/// `other-nodes-allowed` becomes `OtherNodesAllowed`.
+fn camel_case(name: &str) -> String {
+    words(name)
+        .iter()
+        .flat_map(|word| {
+            let mut chars = word.chars();
+            chars
+                .next()
+                .into_iter()
+                .flat_map(char::to_uppercase)
+                .chain(chars)
+        })
+        .collect()
+}
+
+/// A field name for a key or node name, suffixed with `kind` if another field already has it.
+fn field_name(name: &str, kind: &str, fields: &[Field]) -> String {
+    let mut field = words(name).join("_").to_lowercase();
+    if field.is_empty() || field.starts_with(|c: char| c.is_ascii_digit()) {
+        field.insert_str(0, "n_");
+    }
+
+    if fields
+        .iter()
+        .any(|existing| existing.name.trim_start_matches("r#") == field)
+    {
+        field = format!("{}_{}", field, kind);
+    }
+
+    match &*field {
+        "self" | "super" | "crate" => field.push('_'),
+        _ if KEYWORDS.contains(&&*field) => field.insert_str(0, "r#"),
+        _ => (),
+    }
+
+    let mut unique = field.clone();
+    let mut n = 2;
+    while fields.iter().any(|existing| existing.name == unique) {
+        unique = format!("{}_{}", field, n);
+        n += 1;
+    }
+
+    unique
+}
@@ -0,0 +1,701 @@
+#[cfg(all(not(feature = "std"), feature = "alloc"))]
+use alloc::{borrow::Cow, format, string::String, vec::Vec};
+
+#[cfg(feature = "std")]
+use std::borrow::Cow;
+
+use core::fmt;
+use core::iter::Peekable;
+use core::ops::Range;
+use logos::{Logos, SpannedIter};
+
+use crate::ast::*;
+use crate::format::{self, FormatOptions};
+use crate::lex::Token;
+use crate::{ParseError, ParseResult};
+
+/// A format-preserving document. Every byte of the source - comments, whitespace, slashdashed nodes and the exact spelling of literals - is kept around, so rendering it back with [Display](fmt::Display) reproduces the source exactly, and edits only touch the text they change.
+#[derive(Debug, Clone, PartialEq)]
+pub struct CstDocument<'a> {
+    pub nodes: Vec<CstNode<'a>>,
+    /// Trivia after the last node.
+    pub trailing: Cow<'a, str>,
+}
+
+/// A node in a [CstDocument].
+#[derive(Debug, Clone, PartialEq)]
+pub struct CstNode<'a> {
+    /// Trivia before the node; the preceding newline, comments and indentation.
+    pub leading: Cow<'a, str>,
+    /// The type annotation as it's spelled in the source, parentheses included; empty if there isn't one.
+    ty: Cow<'a, str>,
+    name: Cow<'a, str>,
+    pub entries: Vec<CstEntry<'a>>,
+    pub children: Option<CstChildren<'a>>,
+    /// Trivia after the node, up to and including it's terminating semicolon or line comment.
+    pub trailing: Cow<'a, str>,
+}
+
+/// A children block.
+#[derive(Debug, Clone, PartialEq)]
+pub struct CstChildren<'a> {
+    /// Trivia before the opening bracket.
+    pub leading: Cow<'a, str>,
+    pub nodes: Vec<CstNode<'a>>,
+    /// Trivia before the closing bracket.
+    pub trailing: Cow<'a, str>,
+}
+
+/// An argument or property of a [CstNode].
+#[derive(Debug, Clone, PartialEq)]
+pub struct CstEntry<'a> {
+    /// Trivia before the entry, including any line continuations.
+    pub leading: Cow<'a, str>,
+    pub kind: CstEntryKind<'a>,
+}
+
+/// Whether an entry is an argument or a property. Both keep the literal text of their parts.
+#[derive(Debug, Clone, PartialEq)]
+pub enum CstEntryKind<'a> {
+    Argument {
+        value: Cow<'a, str>,
+    },
+    Property {
+        key: Cow<'a, str>,
+        /// Everything between the key and the value; usually just `=`.
+        equals: Cow<'a, str>,
+        value: Cow<'a, str>,
+    },
+}
+
+impl<'a> CstDocument<'a> {
+    /// Parses a document, keeping all of it's trivia.
+    pub fn parse(input: &'a str) -> ParseResult<CstDocument<'a>> {
+        let mut builder = Builder {
+            input,
+            tokens: Token::lexer(input).spanned().peekable(),
+            pos: 0,
+        };
+
+        let (nodes, trailing) = builder.nodes()?;
+        if builder.tokens.peek().is_some() {
+            return Err(ParseError::MismatchedNodeClosing);
+        }
+
+        Ok(CstDocument {
+            nodes,
+            trailing: Cow::Borrowed(trailing),
+        })
+    }
+
+    /// The first top-level node with the given name.
+    pub fn get_mut(&mut self, name: &str) -> Option<&mut CstNode<'a>> {
+        self.nodes
+            .iter_mut()
+            .find(|node| node.name() == KdlString::Escapeless(name))
+    }
+
+    /// Inserts a top-level node, indenting it like it's siblings.
+    pub fn insert_node(&mut self, idx: usize, node: CstNode<'a>) {
+        insert_node(&mut self.nodes, idx, node, "");
+    }
+
+    /// Removes a top-level node, along with it's leading comments.
+    pub fn remove_node(&mut self, idx: usize) -> Option<CstNode<'a>> {
+        remove_node(&mut self.nodes, idx)
+    }
+}
+
+impl<'a> CstNode<'a> {
+    /// Builds a new node on a line of it's own.
+    pub fn new(name: &str) -> CstNode<'a> {
+        CstNode {
+            leading: Cow::Borrowed("\n"),
+            ty: Cow::Borrowed(""),
+            name: Cow::Owned(write_name(name)),
+            entries: Vec::new(),
+            children: None,
+            trailing: Cow::Borrowed(""),
+        }
+    }
+
+    /// The node's name, with escapes processed.
+    pub fn name(&self) -> KdlString<'_> {
+        string_literal(&self.name).expect("node names are always valid strings")
+    }
+
+    /// The node's name, as it's spelled in the source.
+    pub fn name_literal(&self) -> &str {
+        &self.name
+    }
+
+    pub fn set_name(&mut self, name: &str) {
+        self.name = Cow::Owned(write_name(name));
+    }
+
+    /// The node's type annotation, e.g. `ty` in `(ty)node`.
+    pub fn ty(&self) -> Option<&str> {
+        self.ty.strip_prefix('(')?.strip_suffix(')')
+    }
+
+    pub fn set_ty(&mut self, ty: Option<&str>) {
+        self.ty = match ty {
+            Some(ty) => Cow::Owned(format!("({})", ty)),
+            None => Cow::Borrowed(""),
+        };
+    }
+
+    /// Iterates over the node's arguments.
+    pub fn args(&self) -> impl Iterator<Item = TypedValue<'_>> {
+        self.entries.iter().filter_map(|entry| match &entry.kind {
+            CstEntryKind::Argument { value } => value_literal(value),
+            _ => None,
+        })
+    }
+
+    /// Replaces the `idx`th argument, returning false if there aren't that many arguments.
+    pub fn set_arg(&mut self, idx: usize, value: &TypedValue<'_>) -> bool {
+        match self
+            .entries
+            .iter_mut()
+            .filter_map(|entry| match &mut entry.kind {
+                CstEntryKind::Argument { value } => Some(value),
+                _ => None,
+            })
+            .nth(idx)
+        {
+            Some(old) => {
+                *old = Cow::Owned(write_value(value));
+                true
+            }
+            None => false,
+        }
+    }
+
+    /// Appends an argument after all other entries.
+    pub fn push_arg(&mut self, value: &TypedValue<'_>) {
+        self.entries.push(CstEntry {
+            leading: Cow::Borrowed(" "),
+            kind: CstEntryKind::Argument {
+                value: Cow::Owned(write_value(value)),
+            },
+        });
+    }
+
+    /// Removes the `idx`th argument, returning false if there aren't that many arguments.
+    pub fn remove_arg(&mut self, idx: usize) -> bool {
+        match self
+            .entries
+            .iter()
+            .enumerate()
+            .filter(|(_, entry)| matches!(entry.kind, CstEntryKind::Argument { .. }))
+            .nth(idx)
+        {
+            Some((pos, _)) => {
+                self.entries.remove(pos);
+                true
+            }
+            None => false,
+        }
+    }
+
+    /// Gets a property; if the key is repeated, the last one wins.
+    pub fn prop(&self, key: &str) -> Option<TypedValue<'_>> {
+        self.entries
+            .iter()
+            .rev()
+            .find_map(|entry| match &entry.kind {
+                CstEntryKind::Property {
+                    key: literal,
+                    value,
+                    ..
+                } if string_literal(literal)? == KdlString::Escapeless(key) => value_literal(value),
+                _ => None,
+            })
+    }
+
+    /// Sets a property, replacing the value of the last property with that key in place, or appending a new property if there isn't one.
+    pub fn set_prop(&mut self, key: &str, value: &TypedValue<'_>) {
+        let existing = self
+            .entries
+            .iter_mut()
+            .rev()
+            .find_map(|entry| match &mut entry.kind {
+                CstEntryKind::Property {
+                    key: literal,
+                    value,
+                    ..
+                } if string_literal(literal) == Some(KdlString::Escapeless(key)) => Some(value),
+                _ => None,
+            });
+
+        match existing {
+            Some(old) => *old = Cow::Owned(write_value(value)),
+            None => self.entries.push(CstEntry {
+                leading: Cow::Borrowed(" "),
+                kind: CstEntryKind::Property {
+                    key: Cow::Owned(write_name(key)),
+                    equals: Cow::Borrowed("="),
+                    value: Cow::Owned(write_value(value)),
+                },
+            }),
+        }
+    }
+
+    /// Removes every property with the given key, returning whether there were any.
+    pub fn remove_prop(&mut self, key: &str) -> bool {
+        let len = self.entries.len();
+        self.entries.retain(|entry| match &entry.kind {
+            CstEntryKind::Property { key: literal, .. } => {
+                string_literal(literal) != Some(KdlString::Escapeless(key))
+            }
+            _ => true,
+        });
+
+        self.entries.len() != len
+    }
+
+    /// Iterates over the node's children.
+    pub fn children(&self) -> impl Iterator<Item = &CstNode<'a>> {
+        self.children.iter().flat_map(|block| block.nodes.iter())
+    }
+
+    /// The first child with the given name.
+    pub fn get_mut(&mut self, name: &str) -> Option<&mut CstNode<'a>> {
+        self.children
+            .as_mut()?
+            .nodes
+            .iter_mut()
+            .find(|node| node.name() == KdlString::Escapeless(name))
+    }
+
+    /// Inserts a child, indenting it like it's siblings. Adds a children block if the node doesn't have one yet.
+    pub fn insert_child(&mut self, idx: usize, child: CstNode<'a>) {
+        let indent = String::from(indentation(&self.leading));
+        let block = self.children.get_or_insert_with(|| CstChildren {
+            leading: Cow::Borrowed(" "),
+            nodes: Vec::new(),
+            trailing: Cow::Owned(format!("\n{}", indent)),
+        });
+
+        let default_indent = format!("{}    ", indent);
+        insert_node(&mut block.nodes, idx, child, &default_indent);
+    }
+
+    /// Removes a child, along with it's leading comments.
+    pub fn remove_child(&mut self, idx: usize) -> Option<CstNode<'a>> {
+        remove_node(&mut self.children.as_mut()?.nodes, idx)
+    }
+}
+
+impl<'a> fmt::Display for CstDocument<'a> {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        for node in &self.nodes {
+            write!(f, "{}", node)?;
+        }
+
+        f.write_str(&self.trailing)
+    }
+}
+
+impl<'a> fmt::Display for CstNode<'a> {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.write_str(&self.leading)?;
+        f.write_str(&self.ty)?;
+        f.write_str(&self.name)?;
+
+        for entry in &self.entries {
+            f.write_str(&entry.leading)?;
+            match &entry.kind {
+                CstEntryKind::Argument { value } => f.write_str(value)?,
+                CstEntryKind::Property { key, equals, value } => {
+                    f.write_str(key)?;
+                    f.write_str(equals)?;
+                    f.write_str(value)?;
+                }
+            }
+        }
+
+        if let Some(block) = &self.children {
+            f.write_str(&block.leading)?;
+            f.write_str("{")?;
+            for child in &block.nodes {
+                write!(f, "{}", child)?;
+            }
+            f.write_str(&block.trailing)?;
+            f.write_str("}")?;
+        }
+
+        f.write_str(&self.trailing)
+    }
+}
+
+/// Inserts a node among it's siblings, giving it the same indentation as them (or `default_indent` if there aren't any).
+fn insert_node<'a>(
+    nodes: &mut Vec<CstNode<'a>>,
+    idx: usize,
+    mut node: CstNode<'a>,
+    default_indent: &str,
+) {
+    let indent = String::from(
+        nodes[..idx]
+            .iter()
+            .rev()
+            .chain(nodes[idx..].iter())
+            .find(|sibling| sibling.leading.contains('\n'))
+            .map_or(default_indent, |sibling| indentation(&sibling.leading)),
+    );
+
+    node.leading = Cow::Owned(format!("\n{}", indent));
+
+    // a node that doesn't start on a fresh line (at the start of the input, or after a semicolon) hands it's spot over to the new one
+    if let Some(next) = nodes.get_mut(idx) {
+        if !next.leading.contains('\n') {
+            core::mem::swap(&mut next.leading, &mut node.leading);
+        }
+    }
+
+    nodes.insert(idx, node);
+}
+
+/// Removes a node. If the node's trailing line comment was what put the next node on a fresh line, the next node takes over the line break the removed one started with.
+fn remove_node<'a>(nodes: &mut Vec<CstNode<'a>>, idx: usize) -> Option<CstNode<'a>> {
+    if idx >= nodes.len() {
+        return None;
+    }
+    let removed = nodes.remove(idx);
+
+    if let Some(next) = nodes.get_mut(idx) {
+        if let Some(line_break) = removed.leading.find('\n') {
+            if removed.trailing.ends_with('\n') {
+                next.leading = Cow::Owned(format!(
+                    "{}{}",
+                    &removed.leading[..=line_break],
+                    next.leading
+                ));
+            }
+        }
+    }
+
+    Some(removed)
+}
+
+/// The indentation on the last line of a piece of trivia.
+fn indentation(trivia: &str) -> &str {
+    let last_line = &trivia[trivia.rfind('\n').map_or(0, |i| i + 1)..];
+    let end = last_line
+        .find(|c: char| !c.is_whitespace())
+        .unwrap_or(last_line.len());
+    &last_line[..end]
+}
+
+fn write_name(name: &str) -> String {
+    let mut buf = String::with_capacity(name.len());
+    if format::is_bare_identifier(name) {
+        buf.push_str(name);
+    } else {
+        format::write_quoted(&mut buf, name).expect("writing to a String can't fail");
+    }
+
+    buf
+}
+
+fn write_value(value: &TypedValue<'_>) -> String {
+    let mut buf = String::new();
+    format::write_value(&mut buf, value, &FormatOptions::default())
+        .expect("writing to a String can't fail");
+    buf
+}
+
+/// Reads back an identifier or string literal.
+fn string_literal(literal: &str) -> Option<KdlString<'_>> {
+    match Token::lexer(literal).next()? {
+        Token::Identifier(s) | Token::StringWithNoEscapes(s) => Some(KdlString::Escapeless(s)),
+        Token::StringWithEscapes(s) => Some(KdlString::Escaped(s)),
+        _ => None,
+    }
+}
+
+/// Reads back a value literal, with it's type annotation if it has one.
+fn value_literal(literal: &str) -> Option<TypedValue<'_>> {
+    let mut tokens = Token::lexer(literal);
+    let mut ty = None;
+    let mut next = tokens.next()?;
+
+    if let Token::TyDescriptor(desc) = next {
+        ty = Some(desc);
+        next = tokens.next()?;
+    }
+
+    let val = match next {
+        Token::Integer(i) => KdlValue::Integer(i),
+        Token::StringWithEscapes(s) => KdlValue::String(KdlString::Escaped(s)),
+        Token::StringWithNoEscapes(s) => KdlValue::String(KdlString::Escapeless(s)),
+        Token::Float(f) => KdlValue::Float(f),
+        Token::True => KdlValue::Bool(true),
+        Token::False => KdlValue::Bool(false),
+        Token::Null => KdlValue::Null,
+        _ => return None,
+    };
+
+    Some(TypedValue { ty, val })
+}
+
+/// If a piece of trivia ends the current line (i.e. it contains a line comment), returns the offset just past the end of that line.
+fn line_end(trivia: &str) -> Option<usize> {
+    let mut offset = 0;
+    while let Some(idx) = trivia[offset..].find('/') {
+        offset += idx;
+        let rest = &trivia[offset..];
+        if rest.starts_with("//") {
+            return Some(rest.find('\n').map_or(trivia.len(), |end| offset + end + 1));
+        } else if rest.starts_with("/*") {
+            offset += rest.find("*/")? + 2;
+        } else {
+            offset += 1;
+        }
+    }
+
+    None
+}
+
+/// Builds up a [CstDocument] from spanned tokens. Trivia is whatever the lexer skipped between two tokens.
+struct Builder<'a> {
+    input: &'a str,
+    tokens: Peekable<SpannedIter<'a, Token<'a>>>,
+    /// End of the last consumed token.
+    pos: usize,
+}
+
+impl<'a> Builder<'a> {
+    fn peek(&mut self) -> Option<(Token<'a>, Range<usize>)> {
+        self.tokens.peek().cloned()
+    }
+
+    fn bump(&mut self) -> Option<(Token<'a>, Range<usize>)> {
+        let next = self.tokens.next()?;
+        self.pos = next.1.end;
+        Some(next)
+    }
+
+    /// Start of the next token, or the end of the input.
+    fn next_start(&mut self) -> usize {
+        self.tokens
+            .peek()
+            .map_or(self.input.len(), |(_, span)| span.start)
+    }
+
+    /// Parses nodes up until a closing bracket or the end of the input, returning them with the trivia after the last one.
+    fn nodes(&mut self) -> ParseResult<(Vec<CstNode<'a>>, &'a str)> {
+        let mut nodes = Vec::new();
+        let mut start = self.pos;
+
+        while let Some((token, _)) = self.peek() {
+            match token {
+                Token::Newline | Token::Semicolon => {
+                    self.bump();
+                }
+                Token::SlashDash => {
+                    self.bump();
+                    self.node(start)?;
+                }
+                Token::BlockClose => break,
+                _ => {
+                    let node = self.node(start)?;
+                    nodes.push(node);
+                    start = self.pos;
+                }
+            }
+        }
+
+        let end = self.next_start();
+        Ok((nodes, &self.input[start..end]))
+    }
+
+    fn node(&mut self, leading_start: usize) -> ParseResult<CstNode<'a>> {
+        let (mut name_token, mut name_span) = self.bump().ok_or(ParseError::UnexpectedEOF)?;
+        let mut ty_span = name_span.start..name_span.start;
+
+        if let Token::TyDescriptor(_) = name_token {
+            ty_span = name_span;
+            (name_token, name_span) = self.bump().ok_or(ParseError::UnexpectedEOF)?;
+
+            // the annotation has to be right up against the name
+            if name_span.start != ty_span.end {
+                return Err(ParseError::NotANode);
+            }
+        }
+
+        if !matches!(
+            name_token,
+            Token::Identifier(_) | Token::StringWithEscapes(_) | Token::StringWithNoEscapes(_)
+        ) {
+            return Err(ParseError::NotANode);
+        }
+
+        let mut node = CstNode {
+            leading: Cow::Borrowed(&self.input[leading_start..ty_span.start]),
+            ty: Cow::Borrowed(&self.input[ty_span]),
+            name: Cow::Borrowed(&self.input[name_span]),
+            entries: Vec::new(),
+            children: None,
+            trailing: Cow::Borrowed(""),
+        };
+
+        let mut entry_start = self.pos;
+        let mut continued = false;
+
+        loop {
+            let next_start = self.next_start();
+
+            if !continued {
+                if let Some(end) = line_end(&self.input[self.pos..next_start]) {
+                    self.pos += end;
+                    break;
+                }
+            }
+
+            let (token, span) = match self.peek() {
+                Some(next) => next,
+                None => break,
+            };
+
+            match token {
+                Token::Newline if continued => {
+                    self.bump();
+                    continued = false;
+                    continue;
+                }
+                Token::Newline | Token::BlockClose => break,
+                Token::Semicolon => {
+                    self.bump();
+                    break;
+                }
+                Token::Backslash => {
+                    self.bump();
+                    continued = true;
+                    continue;
+                }
+                Token::SlashDash => {
+                    self.bump();
+                    if let Some((Token::BlockOpen, _)) = self.peek() {
+                        self.bump();
+                        self.nodes()?;
+                        self.close_block()?;
+                    } else {
+                        self.entry()?;
+                    }
+                    continued = false;
+                    continue;
+                }
+                Token::BlockOpen => {
+                    self.bump();
+                    let (nodes, trailing) = self.nodes()?;
+                    self.close_block()?;
+
+                    node.children = Some(CstChildren {
+                        leading: Cow::Borrowed(&self.input[entry_start..span.start]),
+                        nodes,
+                        trailing: Cow::Borrowed(trailing),
+                    });
+
+                    let trailing_start = self.pos;
+                    let next_start = self.next_start();
+                    let trivia = &self.input[trailing_start..next_start];
+                    if let Some(end) = line_end(trivia) {
+                        self.pos += end;
+                    } else if let Some((Token::Semicolon, _)) = self.peek() {
+                        self.bump();
+                    }
+
+                    node.trailing = Cow::Borrowed(&self.input[trailing_start..self.pos]);
+                    return Ok(node);
+                }
+                _ => {
+                    if let Some(kind) = self.entry()? {
+                        node.entries.push(CstEntry {
+                            leading: Cow::Borrowed(&self.input[entry_start..span.start]),
+                            kind,
+                        });
+                    } else {
+                        // bare identifiers aren't values; the parser skips them, so they're left in the trivia
+                        continue;
+                    }
+                }
+            }
+
+            continued = false;
+            entry_start = self.pos;
+        }
+
+        // anything slashdashed at the end of the node stays with it
+        node.trailing = Cow::Borrowed(&self.input[entry_start..self.pos]);
+        Ok(node)
+    }
+
+    fn close_block(&mut self) -> ParseResult<()> {
+        match self.bump() {
+            Some((Token::BlockClose, _)) => Ok(()),
+            Some(_) => Err(ParseError::MismatchedNodeClosing),
+            None => Err(ParseError::UnexpectedEOF),
+        }
+    }
+
+    /// Parses an argument or property. Returns `None` for a lone bare identifier.
+    fn entry(&mut self) -> ParseResult<Option<CstEntryKind<'a>>> {
+        let (token, span) = self.bump().ok_or(ParseError::UnexpectedEOF)?;
+
+        match token {
+            Token::Identifier(_) | Token::StringWithEscapes(_) | Token::StringWithNoEscapes(_)
+                if matches!(self.peek(), Some((Token::Equals, _))) =>
+            {
+                self.bump();
+                let value_start = self.next_start();
+                self.value().map_err(|_| ParseError::IncompleteProperty)?;
+
+                Ok(Some(CstEntryKind::Property {
+                    key: Cow::Borrowed(&self.input[span.clone()]),
+                    equals: Cow::Borrowed(&self.input[span.end..value_start]),
+                    value: Cow::Borrowed(&self.input[value_start..self.pos]),
+                }))
+            }
+            Token::Identifier(_) => Ok(None),
+            Token::TyDescriptor(_) => {
+                self.value()
+                    .map_err(|_| ParseError::TypeDescriptorWithNoValue)?;
+                Ok(Some(CstEntryKind::Argument {
+                    value: Cow::Borrowed(&self.input[span.start..self.pos]),
+                }))
+            }
+            Token::Integer(_)
+            | Token::StringWithEscapes(_)
+            | Token::StringWithNoEscapes(_)
+            | Token::Float(_)
+            | Token::True
+            | Token::False
+            | Token::Null => Ok(Some(CstEntryKind::Argument {
+                value: Cow::Borrowed(&self.input[span]),
+            })),
+            _ => Err(ParseError::NotANode),
+        }
+    }
+
+    /// Consumes a value, with an optional type annotation.
+    fn value(&mut self) -> ParseResult<()> {
+        let mut next = self.bump().ok_or(ParseError::UnexpectedEOF)?.0;
+        if let Token::TyDescriptor(_) = next {
+            next = self.bump().ok_or(ParseError::UnexpectedEOF)?.0;
+        }
+
+        match next {
+            Token::Integer(_)
+            | Token::StringWithEscapes(_)
+            | Token::StringWithNoEscapes(_)
+            | Token::Float(_)
+            | Token::True
+            | Token::False
+            | Token::Null => Ok(()),
+            _ => Err(ParseError::IncompleteProperty),
+        }
+    }
+}
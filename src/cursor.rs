@@ -0,0 +1,223 @@
+use core::fmt;
+
+use crate::ast::*;
+use crate::lex::Token;
+use crate::parser::Parser;
+use crate::{KdlEvent, ParseError};
+
+/// A document that keeps every node in one buffer, depth-first, so a node's descendants are the nodes between it and it's end.
+pub trait Nodes {
+    /// The number of nodes, at any depth.
+    fn node_count(&self) -> usize;
+
+    fn parent(&self, idx: usize) -> Option<usize>;
+
+    /// One past the node's last descendant.
+    fn end(&self, idx: usize) -> usize;
+
+    fn has_name(&self, idx: usize, name: &str) -> bool;
+}
+
+/// A [Nodes] document that can be built from a parser's events with [read].
+pub trait Assemble<'a>: Nodes {
+    type Error: From<ParseError>;
+
+    /// Appends a node below `parent`, ending just past itself until [Assemble::set_end] says otherwise.
+    fn push(
+        &mut self,
+        ty: Option<&'a str>,
+        name: KdlString<'a>,
+        values: impl IntoIterator<Item = TypedValue<'a>>,
+        attrs: impl IntoIterator<Item = KdlProperty<'a>>,
+        parent: Option<usize>,
+    ) -> Result<(), Self::Error>;
+
+    fn set_end(&mut self, idx: usize, end: usize);
+}
+
+/// Appends every node a parser reads to a document.
+pub(crate) fn read<'a, D, T, const ENTRIES: usize, const DEPTH: usize>(
+    doc: &mut D,
+    parser: &mut Parser<'a, T, ENTRIES, DEPTH>,
+) -> Result<(), D::Error>
+where
+    D: Assemble<'a>,
+    T: Iterator<Item = Token<'a>>,
+{
+    // the innermost node whose children are being read
+    let mut open = None;
+
+    for event in parser.by_ref() {
+        match event? {
+            KdlEvent::NodeOpen {
+                ty,
+                name,
+                attrs,
+                values,
+                has_children,
+            } => {
+                let idx = doc.node_count();
+                doc.push(ty, name, values, attrs, open)?;
+
+                if has_children {
+                    open = Some(idx);
+                }
+            }
+            KdlEvent::BracketedNodeClose(_) => {
+                // a stray close ends the document, as in [crate::assembler::parse_document]
+                let closed = match open {
+                    Some(idx) => idx,
+                    None => break,
+                };
+
+                doc.set_end(closed, doc.node_count());
+                open = doc.parent(closed);
+            }
+            KdlEvent::NodeClose(_) => continue,
+        }
+    }
+
+    // close anything left open at the end of input
+    let end = doc.node_count();
+    while let Some(idx) = open {
+        doc.set_end(idx, end);
+        open = doc.parent(idx);
+    }
+
+    Ok(())
+}
+
+/// A node in a [tape](crate::tape::Tape) or a [fixed document](crate::fixed::FixedDocument). Cursors are `Copy`, and moving one around doesn't touch the document.
+pub struct Cursor<'d, D> {
+    pub(crate) doc: &'d D,
+    pub(crate) idx: usize,
+}
+
+impl<'d, D> Clone for Cursor<'d, D> {
+    fn clone(&self) -> Self {
+        *self
+    }
+}
+
+impl<'d, D> Copy for Cursor<'d, D> {}
+
+impl<'d, D> PartialEq for Cursor<'d, D> {
+    fn eq(&self, other: &Self) -> bool {
+        core::ptr::eq(self.doc, other.doc) && self.idx == other.idx
+    }
+}
+
+impl<'d, D: Nodes> Cursor<'d, D> {
+    /// A cursor at the `idx`th node, if there is one.
+    pub(crate) fn new(doc: &'d D, idx: usize) -> Option<Self> {
+        if idx < doc.node_count() {
+            Some(Cursor { doc, idx })
+        } else {
+            None
+        }
+    }
+
+    pub fn parent(&self) -> Option<Self> {
+        Cursor::new(self.doc, self.doc.parent(self.idx)?)
+    }
+
+    pub fn has_children(&self) -> bool {
+        self.doc.end(self.idx) > self.idx + 1
+    }
+
+    pub fn first_child(&self) -> Option<Self> {
+        if self.has_children() {
+            Cursor::new(self.doc, self.idx + 1)
+        } else {
+            None
+        }
+    }
+
+    /// The node after this one with the same parent.
+    pub fn next_sibling(&self) -> Option<Self> {
+        let next = self.doc.end(self.idx);
+        let siblings_end = match self.doc.parent(self.idx) {
+            Some(parent) => self.doc.end(parent),
+            None => self.doc.node_count(),
+        };
+
+        if next < siblings_end {
+            Cursor::new(self.doc, next)
+        } else {
+            None
+        }
+    }
+
+    pub fn children(&self) -> Siblings<'d, D> {
+        Siblings {
+            next: self.first_child(),
+        }
+    }
+
+    /// The first child with the given name.
+    pub fn get(&self, name: &str) -> Option<Self> {
+        self.children().find(|child| child.has_name(name))
+    }
+
+    /// Every child with the given name.
+    pub fn get_all<'r>(&self, name: &'r str) -> impl Iterator<Item = Self> + 'r
+    where
+        'd: 'r,
+    {
+        self.children().filter(move |child| child.has_name(name))
+    }
+
+    /// Every node below this one, depth-first, parents before their children.
+    pub fn descendants(&self) -> impl Iterator<Item = Self> {
+        let doc = self.doc;
+        (self.idx + 1..doc.end(self.idx)).map(move |idx| Cursor { doc, idx })
+    }
+
+    fn has_name(&self, name: &str) -> bool {
+        self.doc.has_name(self.idx, name)
+    }
+}
+
+/// Iterator over a node's children, or a document's top-level nodes.
+pub struct Siblings<'d, D> {
+    next: Option<Cursor<'d, D>>,
+}
+
+impl<'d, D> Clone for Siblings<'d, D> {
+    fn clone(&self) -> Self {
+        Siblings { next: self.next }
+    }
+}
+
+impl<'d, D> fmt::Debug for Siblings<'d, D>
+where
+    Cursor<'d, D>: fmt::Debug,
+{
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.debug_struct("Siblings")
+            .field("next", &self.next)
+            .finish()
+    }
+}
+
+impl<'d, D: Nodes> Iterator for Siblings<'d, D> {
+    type Item = Cursor<'d, D>;
+
+    fn next(&mut self) -> Option<Cursor<'d, D>> {
+        let node = self.next?;
+        self.next = node.next_sibling();
+        Some(node)
+    }
+}
+
+/// A document's top-level nodes.
+pub(crate) fn roots<D: Nodes>(doc: &D) -> Siblings<'_, D> {
+    Siblings {
+        next: Cursor::new(doc, 0),
+    }
+}
+
+/// Every node in a document, depth-first, parents before their children.
+pub(crate) fn descendants<D: Nodes>(doc: &D) -> impl Iterator<Item = Cursor<'_, D>> {
+    (0..doc.node_count()).map(move |idx| Cursor { doc, idx })
+}
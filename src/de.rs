@@ -0,0 +1,740 @@
+#[cfg(all(not(feature = "std"), feature = "alloc"))]
+use alloc::{borrow::Cow, string::String};
+
+#[cfg(feature = "std")]
+use std::borrow::Cow;
+
+use core::fmt;
+use serde::de::{self, DeserializeSeed, Visitor};
+use serde::forward_to_deserialize_any;
+
+use crate::ast::*;
+use crate::lex::Token;
+use crate::parser::Parser;
+use crate::{Container, KdlEvent, ParseError};
+
+/// A deserialization error.
+#[derive(Debug, Clone)]
+pub enum Error {
+    Parse(ParseError),
+    /// A node deserialized as a single value didn't have exactly one argument. Contains the node's name.
+    ExpectedSingleArgument(String),
+    /// A node deserialized as an enum didn't have a single variant in it's children block. Contains the node's name.
+    ExpectedSingleVariant(String),
+    Custom(String),
+}
+
+impl From<ParseError> for Error {
+    fn from(e: ParseError) -> Error {
+        Error::Parse(e)
+    }
+}
+
+impl fmt::Display for Error {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            Error::Parse(e) => write!(f, "parse error: {:?}", e),
+            Error::ExpectedSingleArgument(name) => {
+                write!(f, "expected node `{}` to have exactly one argument", name)
+            }
+            Error::ExpectedSingleVariant(name) => {
+                write!(f, "expected node `{}` to have exactly one child", name)
+            }
+            Error::Custom(msg) => f.write_str(msg),
+        }
+    }
+}
+
+impl de::StdError for Error {}
+
+impl de::Error for Error {
+    fn custom<T: fmt::Display>(msg: T) -> Error {
+        Error::Custom(msg.to_string())
+    }
+}
+
+/// Deserializes a document from a str.
+pub fn from_str<'de, D: de::Deserialize<'de>>(input: &'de str) -> Result<D, Error> {
+    D::deserialize(&mut Deserializer::new(Parser::from_str(input)))
+}
+
+/// A serde Deserializer reading straight from a [Parser]'s events, without assembling the document first.
+///
+/// The mapping from kdl to serde's data model:
+///
+/// - A document is a map from the names of it's top-level nodes to the nodes themselves, or a sequence of nodes.
+/// - A node with a single argument deserializes as that argument when a plain value is expected, so `port 8080` fills in a `port: u16` field.
+/// - When a sequence is expected, a node deserializes as it's arguments, or as the nodes in it's children block if it has no arguments.
+/// - Otherwise, a node is a map: properties map to fields, the arguments (if any) are a sequence under the key `-`, and children map to fields by their name.
+/// - Enums are either a string argument naming a unit variant, a type annotated argument (`(Seconds)30`), or a children block with a single node naming the variant.
+///
+/// Repeated nodes with the same name aren't merged; to deserialize a `Vec` of nodes, put them in a children block.
+///
+/// Strings without escapes are borrowed straight from the input.
+pub struct Deserializer<'de, T: Iterator<Item = Token<'de>>> {
+    parser: Parser<'de, T>,
+}
+
+/// The contents of a [KdlEvent::NodeOpen].
+struct Node<'de> {
+    name: KdlString<'de>,
+    attrs: Container<KdlProperty<'de>>,
+    values: Container<TypedValue<'de>>,
+    has_children: bool,
+}
+
+impl<'de, T: Iterator<Item = Token<'de>>> Deserializer<'de, T> {
+    pub fn new(parser: Parser<'de, T>) -> Deserializer<'de, T> {
+        Deserializer { parser }
+    }
+
+    /// Reads the next node in the current block, returning `None` once the block (or document) ends.
+    fn next_node(&mut self) -> Result<Option<Node<'de>>, Error> {
+        for event in self.parser.by_ref() {
+            match event? {
+                KdlEvent::NodeOpen {
+                    ty: _,
+                    name,
+                    attrs,
+                    values,
+                    has_children,
+                } => {
+                    return Ok(Some(Node {
+                        name,
+                        attrs,
+                        values,
+                        has_children,
+                    }))
+                }
+                KdlEvent::NodeClose(_) => continue,
+                KdlEvent::BracketedNodeClose(_) => return Ok(None),
+            }
+        }
+
+        Ok(None)
+    }
+
+    /// Skips over everything left in the current block.
+    fn skip_block(&mut self) -> Result<(), Error> {
+        while let Some(node) = self.next_node()? {
+            if node.has_children {
+                self.skip_block()?;
+            }
+        }
+
+        Ok(())
+    }
+}
+
+impl<'de, T: Iterator<Item = Token<'de>>> de::Deserializer<'de> for &mut Deserializer<'de, T> {
+    type Error = Error;
+
+    fn deserialize_any<V: Visitor<'de>>(self, visitor: V) -> Result<V::Value, Error> {
+        let mut access = NodeMap::block(self);
+        let value = visitor.visit_map(&mut access)?;
+        access.finish()?;
+        Ok(value)
+    }
+
+    fn deserialize_seq<V: Visitor<'de>>(self, visitor: V) -> Result<V::Value, Error> {
+        let mut access = Children {
+            de: self,
+            done: false,
+        };
+        let value = visitor.visit_seq(&mut access)?;
+        access.finish()?;
+        Ok(value)
+    }
+
+    fn deserialize_option<V: Visitor<'de>>(self, visitor: V) -> Result<V::Value, Error> {
+        visitor.visit_some(self)
+    }
+
+    fn deserialize_newtype_struct<V: Visitor<'de>>(
+        self,
+        _name: &'static str,
+        visitor: V,
+    ) -> Result<V::Value, Error> {
+        visitor.visit_newtype_struct(self)
+    }
+
+    forward_to_deserialize_any! {
+        bool i8 i16 i32 i64 i128 u8 u16 u32 u64 u128 f32 f64 char str string
+        bytes byte_buf unit unit_struct tuple tuple_struct
+        map struct enum identifier ignored_any
+    }
+}
+
+/// Deserializes a single node, consuming it's children from the parser as needed.
+struct NodeDeserializer<'a, 'de, T: Iterator<Item = Token<'de>>> {
+    de: &'a mut Deserializer<'de, T>,
+    node: Node<'de>,
+}
+
+impl<'a, 'de, T: Iterator<Item = Token<'de>>> NodeDeserializer<'a, 'de, T> {
+    fn finish(self) -> Result<(), Error> {
+        if self.node.has_children {
+            self.de.skip_block()?;
+        }
+
+        Ok(())
+    }
+
+    fn is_single_value(&self) -> bool {
+        self.node.values.len() == 1 && self.node.attrs.is_empty() && !self.node.has_children
+    }
+
+    fn single_value(self) -> Result<TypedValue<'de>, Error> {
+        if self.node.values.len() != 1 {
+            return Err(Error::ExpectedSingleArgument(self.node.name.to_string()));
+        }
+
+        let value = self.node.values[0];
+        self.finish()?;
+        Ok(value)
+    }
+}
+
+macro_rules! forward_to_single_value {
+    ($($method:ident)*) => {
+        $(
+            fn $method<V: Visitor<'de>>(self, visitor: V) -> Result<V::Value, Error> {
+                de::Deserializer::deserialize_any(ValueDeserializer(self.single_value()?), visitor)
+            }
+        )*
+    };
+}
+
+impl<'a, 'de, T: Iterator<Item = Token<'de>>> de::Deserializer<'de>
+    for NodeDeserializer<'a, 'de, T>
+{
+    type Error = Error;
+
+    fn deserialize_any<V: Visitor<'de>>(self, visitor: V) -> Result<V::Value, Error> {
+        if !self.node.attrs.is_empty() || self.node.has_children {
+            return self.deserialize_map(visitor);
+        }
+
+        match self.node.values.len() {
+            0 => visitor.visit_unit(),
+            1 => de::Deserializer::deserialize_any(ValueDeserializer(self.node.values[0]), visitor),
+            _ => self.deserialize_seq(visitor),
+        }
+    }
+
+    forward_to_single_value! {
+        deserialize_bool deserialize_i8 deserialize_i16 deserialize_i32 deserialize_i64
+        deserialize_i128 deserialize_u8 deserialize_u16 deserialize_u32 deserialize_u64
+        deserialize_u128 deserialize_f32 deserialize_f64 deserialize_char deserialize_str
+        deserialize_string deserialize_bytes deserialize_byte_buf deserialize_identifier
+    }
+
+    fn deserialize_option<V: Visitor<'de>>(self, visitor: V) -> Result<V::Value, Error> {
+        if self.is_single_value() && self.node.values[0].val == KdlValue::Null {
+            visitor.visit_none()
+        } else {
+            visitor.visit_some(self)
+        }
+    }
+
+    fn deserialize_unit<V: Visitor<'de>>(self, visitor: V) -> Result<V::Value, Error> {
+        self.finish()?;
+        visitor.visit_unit()
+    }
+
+    fn deserialize_unit_struct<V: Visitor<'de>>(
+        self,
+        _name: &'static str,
+        visitor: V,
+    ) -> Result<V::Value, Error> {
+        self.deserialize_unit(visitor)
+    }
+
+    fn deserialize_newtype_struct<V: Visitor<'de>>(
+        self,
+        _name: &'static str,
+        visitor: V,
+    ) -> Result<V::Value, Error> {
+        visitor.visit_newtype_struct(self)
+    }
+
+    fn deserialize_seq<V: Visitor<'de>>(self, visitor: V) -> Result<V::Value, Error> {
+        if self.node.values.is_empty() && self.node.has_children {
+            let mut access = Children {
+                de: self.de,
+                done: false,
+            };
+            let value = visitor.visit_seq(&mut access)?;
+            access.finish()?;
+            Ok(value)
+        } else {
+            if self.node.has_children {
+                self.de.skip_block()?;
+            }
+
+            visitor.visit_seq(Args {
+                values: self.node.values,
+                idx: 0,
+            })
+        }
+    }
+
+    fn deserialize_tuple<V: Visitor<'de>>(
+        self,
+        _len: usize,
+        visitor: V,
+    ) -> Result<V::Value, Error> {
+        self.deserialize_seq(visitor)
+    }
+
+    fn deserialize_tuple_struct<V: Visitor<'de>>(
+        self,
+        _name: &'static str,
+        _len: usize,
+        visitor: V,
+    ) -> Result<V::Value, Error> {
+        self.deserialize_seq(visitor)
+    }
+
+    fn deserialize_map<V: Visitor<'de>>(self, visitor: V) -> Result<V::Value, Error> {
+        let mut access = NodeMap {
+            children: self.node.has_children,
+            de: self.de,
+            attrs: self.node.attrs,
+            attr_idx: 0,
+            values: self.node.values,
+            pending: Pending::Nothing,
+        };
+        let value = visitor.visit_map(&mut access)?;
+        access.finish()?;
+        Ok(value)
+    }
+
+    fn deserialize_struct<V: Visitor<'de>>(
+        self,
+        _name: &'static str,
+        _fields: &'static [&'static str],
+        visitor: V,
+    ) -> Result<V::Value, Error> {
+        self.deserialize_map(visitor)
+    }
+
+    fn deserialize_enum<V: Visitor<'de>>(
+        self,
+        name: &'static str,
+        variants: &'static [&'static str],
+        visitor: V,
+    ) -> Result<V::Value, Error> {
+        if !self.node.has_children {
+            let value = self.single_value()?;
+            return de::Deserializer::deserialize_enum(
+                ValueDeserializer(value),
+                name,
+                variants,
+                visitor,
+            );
+        }
+
+        let parent = self.node.name.to_string();
+        let variant = self
+            .de
+            .next_node()?
+            .ok_or_else(|| Error::ExpectedSingleVariant(parent.clone()))?;
+
+        let value = visitor.visit_enum(NodeEnum {
+            de: &mut *self.de,
+            node: variant,
+        })?;
+
+        if self.de.next_node()?.is_some() {
+            return Err(Error::ExpectedSingleVariant(parent));
+        }
+
+        Ok(value)
+    }
+
+    fn deserialize_ignored_any<V: Visitor<'de>>(self, visitor: V) -> Result<V::Value, Error> {
+        self.deserialize_unit(visitor)
+    }
+}
+
+/// What a [NodeMap] will hand out as the next value.
+enum Pending<'de> {
+    Nothing,
+    Attr(TypedValue<'de>),
+    Args(Container<TypedValue<'de>>),
+    Child(Node<'de>),
+}
+
+/// A node's properties, arguments and children, as a map.
+struct NodeMap<'a, 'de, T: Iterator<Item = Token<'de>>> {
+    de: &'a mut Deserializer<'de, T>,
+    attrs: Container<KdlProperty<'de>>,
+    attr_idx: usize,
+    values: Container<TypedValue<'de>>,
+    /// Whether there are still children left to read.
+    children: bool,
+    pending: Pending<'de>,
+}
+
+impl<'a, 'de, T: Iterator<Item = Token<'de>>> NodeMap<'a, 'de, T> {
+    /// A map over the nodes of the current block.
+    fn block(de: &'a mut Deserializer<'de, T>) -> NodeMap<'a, 'de, T> {
+        NodeMap {
+            de,
+            attrs: Container::new(),
+            attr_idx: 0,
+            values: Container::new(),
+            children: true,
+            pending: Pending::Nothing,
+        }
+    }
+
+    fn finish(self) -> Result<(), Error> {
+        if self.children {
+            self.de.skip_block()?;
+        }
+
+        Ok(())
+    }
+}
+
+impl<'a, 'de, T: Iterator<Item = Token<'de>>> de::MapAccess<'de> for NodeMap<'a, 'de, T> {
+    type Error = Error;
+
+    fn next_key_seed<K: DeserializeSeed<'de>>(
+        &mut self,
+        seed: K,
+    ) -> Result<Option<K::Value>, Error> {
+        if let Some(attr) = self.attrs.get(self.attr_idx) {
+            self.attr_idx += 1;
+            self.pending = Pending::Attr(attr.value);
+            return seed.deserialize(KeyDeserializer(attr.key)).map(Some);
+        }
+
+        if !self.values.is_empty() {
+            self.pending = Pending::Args(core::mem::take(&mut self.values));
+            return seed
+                .deserialize(KeyDeserializer(KdlString::Escapeless("-")))
+                .map(Some);
+        }
+
+        if self.children {
+            if let Some(node) = self.de.next_node()? {
+                let key = seed.deserialize(KeyDeserializer(node.name))?;
+                self.pending = Pending::Child(node);
+                return Ok(Some(key));
+            }
+
+            self.children = false;
+        }
+
+        Ok(None)
+    }
+
+    fn next_value_seed<S: DeserializeSeed<'de>>(&mut self, seed: S) -> Result<S::Value, Error> {
+        match core::mem::replace(&mut self.pending, Pending::Nothing) {
+            Pending::Attr(value) => seed.deserialize(ValueDeserializer(value)),
+            Pending::Args(values) => seed.deserialize(Args { values, idx: 0 }),
+            Pending::Child(node) => seed.deserialize(NodeDeserializer {
+                de: &mut *self.de,
+                node,
+            }),
+            Pending::Nothing => Err(de::Error::custom("value requested before key")),
+        }
+    }
+}
+
+/// The nodes of the current block, as a sequence.
+struct Children<'a, 'de, T: Iterator<Item = Token<'de>>> {
+    de: &'a mut Deserializer<'de, T>,
+    done: bool,
+}
+
+impl<'a, 'de, T: Iterator<Item = Token<'de>>> Children<'a, 'de, T> {
+    fn finish(self) -> Result<(), Error> {
+        if !self.done {
+            self.de.skip_block()?;
+        }
+
+        Ok(())
+    }
+}
+
+impl<'a, 'de, T: Iterator<Item = Token<'de>>> de::SeqAccess<'de> for Children<'a, 'de, T> {
+    type Error = Error;
+
+    fn next_element_seed<S: DeserializeSeed<'de>>(
+        &mut self,
+        seed: S,
+    ) -> Result<Option<S::Value>, Error> {
+        if self.done {
+            return Ok(None);
+        }
+
+        match self.de.next_node()? {
+            Some(node) => seed
+                .deserialize(NodeDeserializer {
+                    de: &mut *self.de,
+                    node,
+                })
+                .map(Some),
+            None => {
+                self.done = true;
+                Ok(None)
+            }
+        }
+    }
+}
+
+/// A node's arguments, as a sequence.
+struct Args<'de> {
+    values: Container<TypedValue<'de>>,
+    idx: usize,
+}
+
+impl<'de> de::SeqAccess<'de> for Args<'de> {
+    type Error = Error;
+
+    fn next_element_seed<S: DeserializeSeed<'de>>(
+        &mut self,
+        seed: S,
+    ) -> Result<Option<S::Value>, Error> {
+        match self.values.get(self.idx) {
+            Some(value) => {
+                self.idx += 1;
+                seed.deserialize(ValueDeserializer(*value)).map(Some)
+            }
+            None => Ok(None),
+        }
+    }
+
+    fn size_hint(&self) -> Option<usize> {
+        Some(self.values.len() - self.idx)
+    }
+}
+
+impl<'de> de::Deserializer<'de> for Args<'de> {
+    type Error = Error;
+
+    fn deserialize_any<V: Visitor<'de>>(self, visitor: V) -> Result<V::Value, Error> {
+        visitor.visit_seq(self)
+    }
+
+    forward_to_deserialize_any! {
+        bool i8 i16 i32 i64 i128 u8 u16 u32 u64 u128 f32 f64 char str string
+        bytes byte_buf option unit unit_struct newtype_struct seq tuple
+        tuple_struct map struct enum identifier ignored_any
+    }
+}
+
+/// An enum spelled as a children block holding a single node, named after the variant.
+struct NodeEnum<'a, 'de, T: Iterator<Item = Token<'de>>> {
+    de: &'a mut Deserializer<'de, T>,
+    node: Node<'de>,
+}
+
+impl<'a, 'de, T: Iterator<Item = Token<'de>>> de::EnumAccess<'de> for NodeEnum<'a, 'de, T> {
+    type Error = Error;
+    type Variant = NodeDeserializer<'a, 'de, T>;
+
+    fn variant_seed<S: DeserializeSeed<'de>>(
+        self,
+        seed: S,
+    ) -> Result<(S::Value, Self::Variant), Error> {
+        let variant = seed.deserialize(KeyDeserializer(self.node.name))?;
+        Ok((
+            variant,
+            NodeDeserializer {
+                de: self.de,
+                node: self.node,
+            },
+        ))
+    }
+}
+
+impl<'a, 'de, T: Iterator<Item = Token<'de>>> de::VariantAccess<'de>
+    for NodeDeserializer<'a, 'de, T>
+{
+    type Error = Error;
+
+    fn unit_variant(self) -> Result<(), Error> {
+        self.finish()
+    }
+
+    fn newtype_variant_seed<S: DeserializeSeed<'de>>(self, seed: S) -> Result<S::Value, Error> {
+        seed.deserialize(self)
+    }
+
+    fn tuple_variant<V: Visitor<'de>>(self, _len: usize, visitor: V) -> Result<V::Value, Error> {
+        de::Deserializer::deserialize_seq(self, visitor)
+    }
+
+    fn struct_variant<V: Visitor<'de>>(
+        self,
+        _fields: &'static [&'static str],
+        visitor: V,
+    ) -> Result<V::Value, Error> {
+        de::Deserializer::deserialize_map(self, visitor)
+    }
+}
+
+/// Deserializes a single value. Type annotations name enum variants.
+struct ValueDeserializer<'de>(TypedValue<'de>);
+
+impl<'de> de::Deserializer<'de> for ValueDeserializer<'de> {
+    type Error = Error;
+
+    fn deserialize_any<V: Visitor<'de>>(self, visitor: V) -> Result<V::Value, Error> {
+        match self.0.val {
+            KdlValue::String(s) => KeyDeserializer(s).deserialize_any(visitor),
+            KdlValue::Integer(i) => visitor.visit_i64(i),
+            KdlValue::Float(f) => visitor.visit_f64(f),
+            KdlValue::Bool(b) => visitor.visit_bool(b),
+            KdlValue::Null => visitor.visit_unit(),
+        }
+    }
+
+    fn deserialize_option<V: Visitor<'de>>(self, visitor: V) -> Result<V::Value, Error> {
+        match self.0.val {
+            KdlValue::Null => visitor.visit_none(),
+            _ => visitor.visit_some(self),
+        }
+    }
+
+    fn deserialize_enum<V: Visitor<'de>>(
+        self,
+        _name: &'static str,
+        _variants: &'static [&'static str],
+        visitor: V,
+    ) -> Result<V::Value, Error> {
+        match self.0 {
+            TypedValue { ty: Some(ty), val } => visitor.visit_enum(ValueEnum {
+                variant: ty,
+                value: Some(TypedValue { ty: None, val }),
+            }),
+            TypedValue {
+                ty: None,
+                val: KdlValue::String(s),
+            } => visitor.visit_enum(KeyDeserializer(s)),
+            _ => self.deserialize_any(visitor),
+        }
+    }
+
+    fn deserialize_newtype_struct<V: Visitor<'de>>(
+        self,
+        _name: &'static str,
+        visitor: V,
+    ) -> Result<V::Value, Error> {
+        visitor.visit_newtype_struct(self)
+    }
+
+    forward_to_deserialize_any! {
+        bool i8 i16 i32 i64 i128 u8 u16 u32 u64 u128 f32 f64 char str string
+        bytes byte_buf unit unit_struct seq tuple
+        tuple_struct map struct identifier ignored_any
+    }
+}
+
+/// A type annotated value, as an enum variant holding the value.
+struct ValueEnum<'de> {
+    variant: &'de str,
+    value: Option<TypedValue<'de>>,
+}
+
+impl<'de> de::EnumAccess<'de> for ValueEnum<'de> {
+    type Error = Error;
+    type Variant = ValueEnum<'de>;
+
+    fn variant_seed<S: DeserializeSeed<'de>>(
+        self,
+        seed: S,
+    ) -> Result<(S::Value, Self::Variant), Error> {
+        let variant = seed.deserialize(KeyDeserializer(KdlString::Escapeless(self.variant)))?;
+        Ok((variant, self))
+    }
+}
+
+impl<'de> de::VariantAccess<'de> for ValueEnum<'de> {
+    type Error = Error;
+
+    fn unit_variant(self) -> Result<(), Error> {
+        Ok(())
+    }
+
+    fn newtype_variant_seed<S: DeserializeSeed<'de>>(self, seed: S) -> Result<S::Value, Error> {
+        match self.value {
+            Some(value) => seed.deserialize(ValueDeserializer(value)),
+            None => Err(de::Error::invalid_type(
+                de::Unexpected::UnitVariant,
+                &"newtype variant",
+            )),
+        }
+    }
+
+    fn tuple_variant<V: Visitor<'de>>(self, _len: usize, _visitor: V) -> Result<V::Value, Error> {
+        Err(de::Error::invalid_type(
+            de::Unexpected::NewtypeVariant,
+            &"tuple variant",
+        ))
+    }
+
+    fn struct_variant<V: Visitor<'de>>(
+        self,
+        _fields: &'static [&'static str],
+        _visitor: V,
+    ) -> Result<V::Value, Error> {
+        Err(de::Error::invalid_type(
+            de::Unexpected::NewtypeVariant,
+            &"struct variant",
+        ))
+    }
+}
+
+/// Deserializes a name or string, borrowing it if it has no escapes.
+struct KeyDeserializer<'de>(KdlString<'de>);
+
+impl<'de> de::Deserializer<'de> for KeyDeserializer<'de> {
+    type Error = Error;
+
+    fn deserialize_any<V: Visitor<'de>>(self, visitor: V) -> Result<V::Value, Error> {
+        match self.0.unescape()? {
+            Cow::Borrowed(s) => visitor.visit_borrowed_str(s),
+            Cow::Owned(s) => visitor.visit_string(s),
+        }
+    }
+
+    fn deserialize_enum<V: Visitor<'de>>(
+        self,
+        _name: &'static str,
+        _variants: &'static [&'static str],
+        visitor: V,
+    ) -> Result<V::Value, Error> {
+        visitor.visit_enum(self)
+    }
+
+    forward_to_deserialize_any! {
+        bool i8 i16 i32 i64 i128 u8 u16 u32 u64 u128 f32 f64 char str string
+        bytes byte_buf option unit unit_struct newtype_struct seq tuple
+        tuple_struct map struct identifier ignored_any
+    }
+}
+
+impl<'de> de::EnumAccess<'de> for KeyDeserializer<'de> {
+    type Error = Error;
+    type Variant = ValueEnum<'de>;
+
+    fn variant_seed<S: DeserializeSeed<'de>>(
+        self,
+        seed: S,
+    ) -> Result<(S::Value, Self::Variant), Error> {
+        let variant = seed.deserialize(self)?;
+        Ok((
+            variant,
+            ValueEnum {
+                variant: "",
+                value: None,
+            },
+        ))
+    }
+}
@@ -0,0 +1,412 @@
+#[cfg(all(not(feature = "std"), feature = "alloc"))]
+use alloc::{
+    borrow::Cow,
+    boxed::Box,
+    string::{String, ToString},
+    vec,
+    vec::Vec,
+};
+
+#[cfg(feature = "std")]
+use std::borrow::Cow;
+
+use core::fmt;
+use core::ops::Range;
+
+use crate::assembler::{self, KdlDocument, KdlNode, NameLocation};
+use crate::ast::*;
+use crate::lex::Token;
+use crate::parser::Parser;
+use crate::ParseError;
+
+#[cfg(feature = "derive")]
+pub use sleepyhead_kdl_derive::DecodeKdl;
+
+/// Decodes a type from an assembled node. Usually derived with `#[derive(DecodeKdl)]`, behind the `derive` feature.
+pub trait DecodeKdl<'a>: Sized {
+    fn decode_node(node: &KdlNode<'a>) -> Result<Self, DecodeError>;
+}
+
+/// Decodes a type from a single argument or property value.
+pub trait DecodeValue<'a>: Sized {
+    fn decode_value(value: &TypedValue<'a>) -> Result<Self, DecodeErrorKind>;
+}
+
+/// What went wrong while decoding.
+#[derive(Debug, Clone)]
+pub enum DecodeErrorKind {
+    Parse(ParseError),
+    MissingArgument(usize),
+    MissingProperty(String),
+    MissingChild(String),
+    /// A value had the wrong type; contains the expected type.
+    UnexpectedType(&'static str),
+    /// A value had the right type, but didn't fit; e.g. an integer out of range.
+    InvalidValue(String),
+}
+
+/// An owned value of any type, for arguments and properties that can hold more than one.
+#[derive(Debug, Clone, PartialEq)]
+pub enum Value {
+    String(String),
+    Integer(i64),
+    Float(f64),
+    Bool(bool),
+    Null,
+}
+
+/// An error while decoding, with the path to the node it happened at.
+#[derive(Debug, Clone)]
+pub struct DecodeError {
+    pub kind: DecodeErrorKind,
+    /// Names of the nodes leading to the one that failed, outermost first.
+    pub path: Vec<String>,
+    location: Option<NameLocation>,
+}
+
+impl DecodeError {
+    /// Builds an error for a node.
+    pub fn new(node: &KdlNode<'_>, kind: DecodeErrorKind) -> DecodeError {
+        DecodeError {
+            kind,
+            path: vec![node.name.to_string()],
+            location: NameLocation::of(node),
+        }
+    }
+
+    /// Adds a parent node to the error's path.
+    pub fn within(mut self, parent: &KdlNode<'_>) -> DecodeError {
+        if !parent.name.is_empty() {
+            self.path.insert(0, parent.name.to_string());
+        }
+
+        self
+    }
+
+    /// The byte range of the failing node's name within the source it was parsed from, if it can be found.
+    ///
+    /// Nodes don't carry spans, so this is best-effort: it's `None` if the name had escapes, if the node was built or cloned into owned strings, or if it wasn't parsed from `source`.
+    pub fn span_in(&self, source: &str) -> Option<Range<usize>> {
+        self.location?.span_in(source)
+    }
+}
+
+impl fmt::Display for DecodeErrorKind {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            DecodeErrorKind::Parse(e) => write!(f, "parse error: {:?}", e),
+            DecodeErrorKind::MissingArgument(idx) => write!(f, "missing argument #{}", idx),
+            DecodeErrorKind::MissingProperty(key) => write!(f, "missing property `{}`", key),
+            DecodeErrorKind::MissingChild(name) => write!(f, "missing child node `{}`", name),
+            DecodeErrorKind::UnexpectedType(expected) => write!(f, "expected {}", expected),
+            DecodeErrorKind::InvalidValue(msg) => write!(f, "invalid value: {}", msg),
+        }
+    }
+}
+
+impl fmt::Display for DecodeError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        for (idx, name) in self.path.iter().enumerate() {
+            if idx > 0 {
+                f.write_str(" > ")?;
+            }
+            f.write_str(name)?;
+        }
+
+        if !self.path.is_empty() {
+            f.write_str(": ")?;
+        }
+        write!(f, "{}", self.kind)
+    }
+}
+
+#[cfg(feature = "std")]
+impl std::error::Error for DecodeError {}
+
+impl<'a> KdlNode<'a> {
+    /// The first child with the given name, or an error naming it.
+    pub fn require(&self, name: &str) -> Result<&KdlNode<'a>, DecodeError> {
+        self.get(name)
+            .ok_or_else(|| missing(self, DecodeErrorKind::MissingChild(name.to_string())))
+    }
+
+    /// Follows a path of child names, e.g. `["server", "tls"]`; errors name the path up to where it stopped.
+    pub fn require_path(&self, path: &[&str]) -> Result<&KdlNode<'a>, DecodeError> {
+        let mut trail = vec![self];
+        for name in path {
+            let next = trail[trail.len() - 1].require(name).map_err(|e| {
+                trail[..trail.len() - 1]
+                    .iter()
+                    .rev()
+                    .fold(e, |e, parent| e.within(parent))
+            })?;
+            trail.push(next);
+        }
+
+        Ok(trail[trail.len() - 1])
+    }
+
+    /// Decodes the `idx`th argument, which has to be there.
+    pub fn require_arg<T: DecodeValue<'a>>(&self, idx: usize) -> Result<T, DecodeError> {
+        argument(self, idx)?.ok_or_else(|| missing(self, DecodeErrorKind::MissingArgument(idx)))
+    }
+
+    /// Decodes a property, which has to be there. If the key is repeated, the last one wins.
+    pub fn require_prop<T: DecodeValue<'a>>(&self, key: &str) -> Result<T, DecodeError> {
+        property(self, key)?
+            .ok_or_else(|| missing(self, DecodeErrorKind::MissingProperty(key.to_string())))
+    }
+}
+
+impl<'a> KdlDocument<'a> {
+    /// The first top-level node with the given name, or an error naming it.
+    pub fn require(&self, name: &str) -> Result<&KdlNode<'a>, DecodeError> {
+        self.get(name).ok_or_else(|| DecodeError {
+            kind: DecodeErrorKind::MissingChild(name.to_string()),
+            path: Vec::new(),
+            location: None,
+        })
+    }
+
+    /// Follows a path of node names from the top level, e.g. `["server", "tls"]`; errors name the path up to where it stopped. An empty path finds nothing.
+    pub fn require_path(&self, path: &[&str]) -> Result<&KdlNode<'a>, DecodeError> {
+        match path {
+            [] => Err(DecodeError {
+                kind: DecodeErrorKind::MissingChild(String::new()),
+                path: Vec::new(),
+                location: None,
+            }),
+            [first, rest @ ..] => self.require(first)?.require_path(rest),
+        }
+    }
+}
+
+/// Decodes a whole document. The document is treated as the children of a nameless root node, so `T`'s fields should all be children.
+/// The nodes are moved into the root rather than copied.
+pub fn decode_document<'a, T: DecodeKdl<'a>>(nodes: Vec<KdlNode<'a>>) -> Result<T, DecodeError> {
+    T::decode_node(&KdlNode {
+        ty: None,
+        name: Cow::Borrowed(""),
+        attrs: Vec::new(),
+        values: Vec::new(),
+        children: nodes,
+    })
+}
+
+/// Assembles a parser's events into a whole document, then decodes it.
+/// The full tree is built first; decoding doesn't start until the parser is done.
+pub fn decode_assembled<'a, T: Iterator<Item = Token<'a>>, D: DecodeKdl<'a>>(
+    parser: &mut Parser<'a, T>,
+) -> Result<D, DecodeError> {
+    let nodes = assembler::parse_document(parser).map_err(|e| DecodeError {
+        kind: DecodeErrorKind::Parse(e),
+        path: Vec::new(),
+        location: None,
+    })?;
+
+    decode_document(nodes)
+}
+
+/// Parses and decodes a whole document. Errors can often be located in `input` with [DecodeError::span_in].
+pub fn decode_str<'a, D: DecodeKdl<'a>>(input: &'a str) -> Result<D, DecodeError> {
+    decode_assembled(&mut Parser::from_str(input))
+}
+
+/// Decodes the `idx`th argument of a node, if it has one.
+pub fn argument<'a, T: DecodeValue<'a>>(
+    node: &KdlNode<'a>,
+    idx: usize,
+) -> Result<Option<T>, DecodeError> {
+    node.values
+        .get(idx)
+        .map(|value| T::decode_value(value).map_err(|kind| DecodeError::new(node, kind)))
+        .transpose()
+}
+
+/// Decodes all arguments of a node, starting at the `from`th one.
+pub fn arguments<'a, T: DecodeValue<'a>>(
+    node: &KdlNode<'a>,
+    from: usize,
+) -> Result<Vec<T>, DecodeError> {
+    node.values
+        .iter()
+        .skip(from)
+        .map(|value| T::decode_value(value).map_err(|kind| DecodeError::new(node, kind)))
+        .collect()
+}
+
+/// Decodes a node's property. If the key is repeated, the last one wins.
+pub fn property<'a, T: DecodeValue<'a>>(
+    node: &KdlNode<'a>,
+    key: &str,
+) -> Result<Option<T>, DecodeError> {
+    node.attrs
+        .iter()
+        .rev()
+        .find(|attr| attr.key == KdlString::Escapeless(key))
+        .map(|attr| T::decode_value(&attr.value).map_err(|kind| DecodeError::new(node, kind)))
+        .transpose()
+}
+
+/// Decodes the first child with the given name.
+pub fn child<'a, T: DecodeKdl<'a>>(
+    node: &KdlNode<'a>,
+    name: &str,
+) -> Result<Option<T>, DecodeError> {
+    node.children
+        .iter()
+        .find(|child| child.name == name)
+        .map(|child| T::decode_node(child).map_err(|e| e.within(node)))
+        .transpose()
+}
+
+/// Decodes every child with the given name, or every child if there's no name.
+pub fn children<'a, T: DecodeKdl<'a>>(
+    node: &KdlNode<'a>,
+    name: Option<&str>,
+) -> Result<Vec<T>, DecodeError> {
+    node.children
+        .iter()
+        .filter(|child| name.is_none() || name == Some(child.name.as_ref()))
+        .map(|child| T::decode_node(child).map_err(|e| e.within(node)))
+        .collect()
+}
+
+/// Decodes every child whose name isn't one of `claimed`; for a field that takes whatever children other fields don't.
+pub fn other_children<'a, T: DecodeKdl<'a>>(
+    node: &KdlNode<'a>,
+    claimed: &[&str],
+) -> Result<Vec<T>, DecodeError> {
+    node.children
+        .iter()
+        .filter(|child| !claimed.contains(&child.name.as_ref()))
+        .map(|child| T::decode_node(child).map_err(|e| e.within(node)))
+        .collect()
+}
+
+/// The node's type annotation, e.g. `seconds` in `(seconds)timeout 30`.
+pub fn type_name<'a>(node: &KdlNode<'a>) -> Option<&'a str> {
+    node.ty
+}
+
+/// Builds the error for a required field that wasn't there.
+pub fn missing(node: &KdlNode<'_>, kind: DecodeErrorKind) -> DecodeError {
+    DecodeError::new(node, kind)
+}
+
+macro_rules! decode_integers {
+    ($($ty:ty),*) => {
+        $(
+            impl<'a> DecodeValue<'a> for $ty {
+                fn decode_value(value: &TypedValue<'a>) -> Result<$ty, DecodeErrorKind> {
+                    match value.val {
+                        KdlValue::Integer(i) => <$ty>::try_from(i)
+                            .map_err(|_| DecodeErrorKind::InvalidValue(i.to_string())),
+                        _ => Err(DecodeErrorKind::UnexpectedType("an integer")),
+                    }
+                }
+            }
+        )*
+    };
+}
+
+decode_integers!(i8, i16, i32, i64, i128, isize, u8, u16, u32, u64, u128, usize);
+
+impl<'a> DecodeValue<'a> for f64 {
+    fn decode_value(value: &TypedValue<'a>) -> Result<f64, DecodeErrorKind> {
+        match value.val {
+            KdlValue::Float(f) => Ok(f),
+            KdlValue::Integer(i) => Ok(i as f64),
+            _ => Err(DecodeErrorKind::UnexpectedType("a number")),
+        }
+    }
+}
+
+impl<'a> DecodeValue<'a> for f32 {
+    fn decode_value(value: &TypedValue<'a>) -> Result<f32, DecodeErrorKind> {
+        f64::decode_value(value).map(|f| f as f32)
+    }
+}
+
+impl<'a> DecodeValue<'a> for bool {
+    fn decode_value(value: &TypedValue<'a>) -> Result<bool, DecodeErrorKind> {
+        value
+            .val
+            .as_bool()
+            .copied()
+            .ok_or(DecodeErrorKind::UnexpectedType("a bool"))
+    }
+}
+
+impl<'a> DecodeValue<'a> for Cow<'a, str> {
+    fn decode_value(value: &TypedValue<'a>) -> Result<Cow<'a, str>, DecodeErrorKind> {
+        match value.val {
+            KdlValue::String(s) => s.unescape().map_err(DecodeErrorKind::Parse),
+            _ => Err(DecodeErrorKind::UnexpectedType("a string")),
+        }
+    }
+}
+
+impl<'a> DecodeValue<'a> for String {
+    fn decode_value(value: &TypedValue<'a>) -> Result<String, DecodeErrorKind> {
+        Cow::decode_value(value).map(Cow::into_owned)
+    }
+}
+
+/// Borrows the string from the input; fails on strings with escapes, which can't be borrowed.
+impl<'a> DecodeValue<'a> for &'a str {
+    fn decode_value(value: &TypedValue<'a>) -> Result<&'a str, DecodeErrorKind> {
+        match value.val {
+            KdlValue::String(KdlString::Escapeless(s)) => Ok(s),
+            _ => Err(DecodeErrorKind::UnexpectedType("a string without escapes")),
+        }
+    }
+}
+
+impl<'a> DecodeValue<'a> for KdlValue<'a> {
+    fn decode_value(value: &TypedValue<'a>) -> Result<KdlValue<'a>, DecodeErrorKind> {
+        Ok(value.val)
+    }
+}
+
+impl<'a> DecodeValue<'a> for TypedValue<'a> {
+    fn decode_value(value: &TypedValue<'a>) -> Result<TypedValue<'a>, DecodeErrorKind> {
+        Ok(*value)
+    }
+}
+
+impl<'a> DecodeValue<'a> for Value {
+    fn decode_value(value: &TypedValue<'a>) -> Result<Value, DecodeErrorKind> {
+        Ok(match value.val {
+            KdlValue::String(s) => {
+                Value::String(s.unescape().map_err(DecodeErrorKind::Parse)?.into_owned())
+            }
+            KdlValue::Integer(i) => Value::Integer(i),
+            KdlValue::Float(f) => Value::Float(f),
+            KdlValue::Bool(b) => Value::Bool(b),
+            KdlValue::Null => Value::Null,
+        })
+    }
+}
+
+/// `null` decodes as `None`.
+impl<'a, T: DecodeValue<'a>> DecodeValue<'a> for Option<T> {
+    fn decode_value(value: &TypedValue<'a>) -> Result<Option<T>, DecodeErrorKind> {
+        match value.val {
+            KdlValue::Null => Ok(None),
+            _ => T::decode_value(value).map(Some),
+        }
+    }
+}
+
+impl<'a> DecodeKdl<'a> for KdlNode<'a> {
+    fn decode_node(node: &KdlNode<'a>) -> Result<KdlNode<'a>, DecodeError> {
+        Ok(node.clone())
+    }
+}
+
+impl<'a, T: DecodeKdl<'a>> DecodeKdl<'a> for Box<T> {
+    fn decode_node(node: &KdlNode<'a>) -> Result<Box<T>, DecodeError> {
+        T::decode_node(node).map(Box::new)
+    }
+}
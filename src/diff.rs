@@ -0,0 +1,426 @@
+#[cfg(all(not(feature = "std"), feature = "alloc"))]
+use alloc::{
+    collections::BTreeMap,
+    string::{String, ToString},
+    vec::Vec,
+};
+
+#[cfg(feature = "std")]
+use std::collections::BTreeMap;
+
+use core::fmt::{self, Write};
+
+use crate::assembler::KdlNode;
+use crate::ast::*;
+use crate::format::{self, FormatOptions};
+
+/// How the nodes in two lists of siblings are paired up to be compared.
+#[derive(Debug, Clone, PartialEq, Default)]
+pub enum Matching {
+    /// The nth node is paired with the nth node, whatever their names.
+    Position,
+    /// The nth node with a given name is paired with the nth node with that name.
+    #[default]
+    Name,
+    /// Nodes are paired by name and the value of a key property, e.g. `name=`; nodes without one fall back to being paired by name.
+    Key(String),
+}
+
+/// Options for [diff] and [diff_documents].
+#[derive(Debug, Clone, PartialEq, Default)]
+pub struct DiffOptions {
+    pub matching: Matching,
+}
+
+/// A step in the path to a node: it's name, and how many siblings with the same name come before it.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Step {
+    pub name: String,
+    pub index: usize,
+}
+
+/// What changed.
+#[derive(Debug, Clone, PartialEq)]
+pub enum ChangeKind<'a> {
+    /// A node was added; the change's path is to it's parent, and `position` is it's index among the new children.
+    AddedNode {
+        position: usize,
+        node: KdlNode<'a>,
+    },
+    RemovedNode(KdlNode<'a>),
+    /// Only happens when matching by position.
+    Renamed {
+        old: String,
+        new: String,
+    },
+    ChangedType {
+        old: Option<&'a str>,
+        new: Option<&'a str>,
+    },
+    AddedArg {
+        idx: usize,
+        value: TypedValue<'a>,
+    },
+    RemovedArg {
+        idx: usize,
+        value: TypedValue<'a>,
+    },
+    ChangedArg {
+        idx: usize,
+        old: TypedValue<'a>,
+        new: TypedValue<'a>,
+    },
+    AddedProp {
+        key: String,
+        value: TypedValue<'a>,
+    },
+    RemovedProp {
+        key: String,
+        value: TypedValue<'a>,
+    },
+    ChangedProp {
+        key: String,
+        old: TypedValue<'a>,
+        new: TypedValue<'a>,
+    },
+}
+
+/// A single change, with the path to the node it happened at. Steps are counted in the old document.
+#[derive(Debug, Clone, PartialEq)]
+pub struct Change<'a> {
+    pub path: Vec<Step>,
+    pub kind: ChangeKind<'a>,
+}
+
+/// Every change between two documents. It's `Display` impl renders it as a human-readable patch, one change per line:
+///
+/// ```text
+/// server: ~ argument 1: 8080 -> 9090
+/// server: + workers=8
+/// server > route[1]: - route "/about"
+/// ```
+#[derive(Debug, Clone, PartialEq, Default)]
+pub struct Diff<'a> {
+    pub changes: Vec<Change<'a>>,
+}
+
+impl<'a> Diff<'a> {
+    pub fn is_empty(&self) -> bool {
+        self.changes.is_empty()
+    }
+}
+
+/// Compares two nodes and everything below them. Changes to the nodes themselves have an empty path.
+pub fn diff<'a>(old: &KdlNode<'a>, new: &KdlNode<'a>, options: &DiffOptions) -> Diff<'a> {
+    let mut diff = Diff::default();
+    compare_nodes(old, new, &mut Vec::new(), options, &mut diff.changes);
+    diff
+}
+
+/// Compares two documents' top-level nodes, and everything below them.
+pub fn diff_documents<'a>(
+    old: &[KdlNode<'a>],
+    new: &[KdlNode<'a>],
+    options: &DiffOptions,
+) -> Diff<'a> {
+    let mut diff = Diff::default();
+    compare_lists(old, new, &mut Vec::new(), options, &mut diff.changes);
+    diff
+}
+
+fn compare_nodes<'a>(
+    old: &KdlNode<'a>,
+    new: &KdlNode<'a>,
+    path: &mut Vec<Step>,
+    options: &DiffOptions,
+    changes: &mut Vec<Change<'a>>,
+) {
+    let mut push = |kind| {
+        changes.push(Change {
+            path: path.clone(),
+            kind,
+        })
+    };
+
+    if old.name != new.name {
+        push(ChangeKind::Renamed {
+            old: old.name.to_string(),
+            new: new.name.to_string(),
+        });
+    }
+
+    if old.ty != new.ty {
+        push(ChangeKind::ChangedType {
+            old: old.ty,
+            new: new.ty,
+        });
+    }
+
+    for idx in 0..old.values.len().max(new.values.len()) {
+        match (old.values.get(idx), new.values.get(idx)) {
+            (Some(&old), Some(&new)) if !same_value(&old, &new) => {
+                push(ChangeKind::ChangedArg { idx, old, new })
+            }
+            (Some(&value), None) => push(ChangeKind::RemovedArg { idx, value }),
+            (None, Some(&value)) => push(ChangeKind::AddedArg { idx, value }),
+            _ => (),
+        }
+    }
+
+    let old_props = properties(old);
+    let new_props = properties(new);
+    for (key, old) in &old_props {
+        match new_props.iter().find(|(new_key, _)| new_key == key) {
+            Some((_, new)) if !same_value(old, new) => push(ChangeKind::ChangedProp {
+                key: key.clone(),
+                old: *old,
+                new: *new,
+            }),
+            Some(_) => (),
+            None => push(ChangeKind::RemovedProp {
+                key: key.clone(),
+                value: *old,
+            }),
+        }
+    }
+    for (key, value) in new_props {
+        if !old_props.iter().any(|(old_key, _)| *old_key == key) {
+            push(ChangeKind::AddedProp { key, value });
+        }
+    }
+
+    compare_lists(&old.children, &new.children, path, options, changes);
+}
+
+fn compare_lists<'a>(
+    old: &[KdlNode<'a>],
+    new: &[KdlNode<'a>],
+    path: &mut Vec<Step>,
+    options: &DiffOptions,
+    changes: &mut Vec<Change<'a>>,
+) {
+    let pairs = pair(old, new, &options.matching);
+    let mut matched = Vec::new();
+    matched.resize(new.len(), false);
+
+    for (idx, node) in old.iter().enumerate() {
+        path.push(Step {
+            name: node.name.to_string(),
+            index: old[..idx]
+                .iter()
+                .filter(|other| other.name == node.name)
+                .count(),
+        });
+
+        match pairs[idx] {
+            Some(other) => {
+                matched[other] = true;
+                compare_nodes(node, &new[other], path, options, changes);
+            }
+            None => changes.push(Change {
+                path: path.clone(),
+                kind: ChangeKind::RemovedNode(node.clone()),
+            }),
+        }
+
+        path.pop();
+    }
+
+    for (position, node) in new.iter().enumerate() {
+        if !matched[position] {
+            changes.push(Change {
+                path: path.clone(),
+                kind: ChangeKind::AddedNode {
+                    position,
+                    node: node.clone(),
+                },
+            });
+        }
+    }
+}
+
+/// Finds the node in `new` each node in `old` is paired with, if any.
+fn pair(old: &[KdlNode<'_>], new: &[KdlNode<'_>], matching: &Matching) -> Vec<Option<usize>> {
+    if let Matching::Position = matching {
+        return (0..old.len())
+            .map(|idx| (idx < new.len()).then_some(idx))
+            .collect();
+    }
+
+    let key = |node: &KdlNode<'_>| match matching {
+        Matching::Key(key) => node.prop(key).map(|value| {
+            let mut buf = String::new();
+            let _ = format::write_value(&mut buf, value, &FormatOptions::default());
+            buf
+        }),
+        _ => None,
+    };
+
+    // identities are numbered, so the second `route "/"` pairs with the second one
+    let identities = |nodes: &[KdlNode<'_>]| {
+        let mut seen = BTreeMap::new();
+        nodes
+            .iter()
+            .map(|node| {
+                let identity = (node.name.to_string(), key(node));
+                let count = seen.entry(identity.clone()).or_insert(0);
+                *count += 1;
+                (identity, *count)
+            })
+            .collect::<Vec<_>>()
+    };
+
+    let new_identities: BTreeMap<_, _> = identities(new)
+        .into_iter()
+        .enumerate()
+        .map(|(idx, identity)| (identity, idx))
+        .collect();
+
+    identities(old)
+        .iter()
+        .map(|identity| new_identities.get(identity).copied())
+        .collect()
+}
+
+/// A node's properties, with repeated keys collapsed so the last one wins.
+fn properties<'a>(node: &KdlNode<'a>) -> Vec<(String, TypedValue<'a>)> {
+    let mut props: Vec<(String, TypedValue<'a>)> = Vec::new();
+    for attr in &node.attrs {
+        let key = format::string_contents(&attr.key);
+        match props.iter_mut().find(|(other, _)| *other == key) {
+            Some((_, value)) => *value = attr.value,
+            None => props.push((key, attr.value)),
+        }
+    }
+
+    props
+}
+
+/// Values are only the same if their annotations are, too.
+fn same_value(old: &TypedValue<'_>, new: &TypedValue<'_>) -> bool {
+    old == new && old.ty == new.ty
+}
+
+impl fmt::Display for Step {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write_name(f, &self.name)?;
+
+        if self.index > 0 {
+            write!(f, "[{}]", self.index)?;
+        }
+
+        Ok(())
+    }
+}
+
+impl<'a> fmt::Display for Change<'a> {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        if self.path.is_empty() {
+            f.write_str("(root)")?;
+        }
+        write_path(f, &self.path)?;
+        f.write_str(": ")?;
+
+        match &self.kind {
+            ChangeKind::AddedNode { node, .. } => write_node(f, '+', node),
+            ChangeKind::RemovedNode(node) => write_node(f, '-', node),
+            ChangeKind::Renamed { old, new } => {
+                f.write_str("~ name: ")?;
+                write_name(f, old)?;
+                f.write_str(" -> ")?;
+                write_name(f, new)
+            }
+            ChangeKind::ChangedType { old, new } => write!(
+                f,
+                "~ type: {} -> {}",
+                old.unwrap_or("none"),
+                new.unwrap_or("none")
+            ),
+            ChangeKind::AddedArg { idx, value } => {
+                write!(f, "+ argument {}: ", idx)?;
+                write_value(f, value)
+            }
+            ChangeKind::RemovedArg { idx, value } => {
+                write!(f, "- argument {}: ", idx)?;
+                write_value(f, value)
+            }
+            ChangeKind::ChangedArg { idx, old, new } => {
+                write!(f, "~ argument {}: ", idx)?;
+                write_value(f, old)?;
+                f.write_str(" -> ")?;
+                write_value(f, new)
+            }
+            ChangeKind::AddedProp { key, value } => {
+                f.write_str("+ ")?;
+                write_prop(f, key, value)
+            }
+            ChangeKind::RemovedProp { key, value } => {
+                f.write_str("- ")?;
+                write_prop(f, key, value)
+            }
+            ChangeKind::ChangedProp { key, old, new } => {
+                f.write_str("~ ")?;
+                write_prop(f, key, old)?;
+                f.write_str(" -> ")?;
+                write_prop(f, key, new)
+            }
+        }
+    }
+}
+
+impl<'a> fmt::Display for Diff<'a> {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        for change in &self.changes {
+            writeln!(f, "{}", change)?;
+        }
+
+        Ok(())
+    }
+}
+
+/// Writes a path as steps separated by ` > `, e.g. `server > route[1]`.
+pub(crate) fn write_path<W: Write>(out: &mut W, path: &[Step]) -> fmt::Result {
+    for (idx, step) in path.iter().enumerate() {
+        if idx > 0 {
+            out.write_str(" > ")?;
+        }
+        write!(out, "{}", step)?;
+    }
+
+    Ok(())
+}
+
+fn write_name<W: Write>(out: &mut W, name: &str) -> fmt::Result {
+    if format::is_bare_identifier(name) {
+        out.write_str(name)
+    } else {
+        format::write_quoted(out, name)
+    }
+}
+
+fn write_value<W: Write>(out: &mut W, value: &TypedValue<'_>) -> fmt::Result {
+    format::write_value(out, value, &FormatOptions::default())
+}
+
+fn write_prop<W: Write>(out: &mut W, key: &str, value: &TypedValue<'_>) -> fmt::Result {
+    write_name(out, key)?;
+    out.write_char('=')?;
+    write_value(out, value)
+}
+
+/// Writes a whole node, marking every line after the first, too.
+fn write_node<W: Write>(out: &mut W, sign: char, node: &KdlNode<'_>) -> fmt::Result {
+    let formatted =
+        format::format_document(core::slice::from_ref(node), &FormatOptions::default())?;
+
+    for (idx, line) in formatted.lines().enumerate() {
+        if idx > 0 {
+            write!(out, "\n{} ", sign)?;
+        } else {
+            write!(out, "{} ", sign)?;
+        }
+        out.write_str(line)?;
+    }
+
+    Ok(())
+}
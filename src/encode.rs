@@ -0,0 +1,202 @@
+#[cfg(all(not(feature = "std"), feature = "alloc"))]
+use alloc::{borrow::Cow, string::String, vec::Vec};
+
+#[cfg(feature = "std")]
+use std::borrow::Cow;
+
+use core::fmt;
+
+use crate::assembler::KdlNode;
+use crate::ast::*;
+use crate::format::{self, FormatOptions};
+use crate::KdlEvent;
+
+#[cfg(feature = "derive")]
+pub use sleepyhead_kdl_derive::EncodeKdl;
+
+/// Encodes a type into a node. Usually derived with `#[derive(EncodeKdl)]`, behind the `derive` feature; the attributes are the same as for decoding.
+pub trait EncodeKdl {
+    /// Encodes into a node called `name`. Nodes borrow their strings from `self`.
+    fn encode_node<'a>(&'a self, name: &'a str) -> Result<KdlNode<'a>, EncodeError>;
+}
+
+/// Encodes a type into a single argument or property value.
+pub trait EncodeValue {
+    fn encode_value(&self) -> Result<TypedValue<'_>, EncodeError>;
+}
+
+/// An error while encoding.
+#[derive(Debug, Copy, Clone, PartialEq)]
+pub enum EncodeError {
+    /// kdl integers are 64-bit; this one didn't fit.
+    IntegerOutOfRange,
+    /// kdl can't represent NaN floats.
+    NotANumber,
+}
+
+impl fmt::Display for EncodeError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            EncodeError::IntegerOutOfRange => f.write_str("integer doesn't fit in an i64"),
+            EncodeError::NotANumber => f.write_str("NaN can't be written as kdl"),
+        }
+    }
+}
+
+#[cfg(feature = "std")]
+impl std::error::Error for EncodeError {}
+
+/// Encodes a value as a whole document; the top-level nodes are the children `T` encodes to.
+pub fn encode_document<T: EncodeKdl>(value: &T) -> Result<Vec<KdlNode<'_>>, EncodeError> {
+    Ok(value.encode_node("")?.children)
+}
+
+/// Encodes a value as a whole document and formats it.
+pub fn encode_str<T: EncodeKdl>(value: &T, options: &FormatOptions) -> Result<String, EncodeError> {
+    format::format_document(&encode_document(value)?, options).map_err(|_| EncodeError::NotANumber)
+}
+
+/// Feeds a node and all of it's children to `emit` as the events a [crate::parser::Parser] would produce for it.
+pub fn write_events<'a, F: FnMut(KdlEvent<'a>)>(node: &'a KdlNode<'a>, emit: &mut F) {
+    let name = KdlString::Escapeless(&node.name);
+
+    emit(KdlEvent::NodeOpen {
+        ty: node.ty,
+        name,
+        attrs: node.attrs.to_vec(),
+        values: node.values.to_vec(),
+        has_children: !node.children.is_empty(),
+    });
+
+    if node.children.is_empty() {
+        emit(KdlEvent::NodeClose(name));
+    } else {
+        for child in &node.children {
+            write_events(child, emit);
+        }
+        emit(KdlEvent::BracketedNodeClose(name));
+    }
+}
+
+/// An empty node called `name`.
+pub fn node(name: &str) -> KdlNode<'_> {
+    KdlNode {
+        ty: None,
+        name: Cow::Borrowed(name),
+        attrs: Vec::new(),
+        values: Vec::new(),
+        children: Vec::new(),
+    }
+}
+
+/// A property with the given key.
+pub fn property<'a>(key: &'a str, value: TypedValue<'a>) -> KdlProperty<'a> {
+    KdlProperty {
+        key: KdlString::Escapeless(key),
+        value,
+    }
+}
+
+fn untyped(val: KdlValue<'_>) -> TypedValue<'_> {
+    TypedValue { ty: None, val }
+}
+
+macro_rules! encode_integers {
+    ($($ty:ty),*) => {
+        $(
+            impl EncodeValue for $ty {
+                fn encode_value(&self) -> Result<TypedValue<'_>, EncodeError> {
+                    i64::try_from(*self)
+                        .map(|i| untyped(KdlValue::Integer(i)))
+                        .map_err(|_| EncodeError::IntegerOutOfRange)
+                }
+            }
+        )*
+    };
+}
+
+encode_integers!(i8, i16, i32, i64, i128, isize, u8, u16, u32, u64, u128, usize);
+
+impl EncodeValue for f64 {
+    fn encode_value(&self) -> Result<TypedValue<'_>, EncodeError> {
+        if self.is_nan() {
+            return Err(EncodeError::NotANumber);
+        }
+
+        Ok(untyped(KdlValue::Float(*self)))
+    }
+}
+
+impl EncodeValue for f32 {
+    fn encode_value(&self) -> Result<TypedValue<'_>, EncodeError> {
+        if self.is_nan() {
+            return Err(EncodeError::NotANumber);
+        }
+
+        Ok(untyped(KdlValue::Float(*self as f64)))
+    }
+}
+
+impl EncodeValue for bool {
+    fn encode_value(&self) -> Result<TypedValue<'_>, EncodeError> {
+        Ok(untyped(KdlValue::Bool(*self)))
+    }
+}
+
+impl EncodeValue for str {
+    fn encode_value(&self) -> Result<TypedValue<'_>, EncodeError> {
+        Ok(untyped(KdlValue::String(KdlString::Escapeless(self))))
+    }
+}
+
+impl EncodeValue for String {
+    fn encode_value(&self) -> Result<TypedValue<'_>, EncodeError> {
+        self.as_str().encode_value()
+    }
+}
+
+impl EncodeValue for Cow<'_, str> {
+    fn encode_value(&self) -> Result<TypedValue<'_>, EncodeError> {
+        self.as_ref().encode_value()
+    }
+}
+
+impl EncodeValue for KdlValue<'_> {
+    fn encode_value(&self) -> Result<TypedValue<'_>, EncodeError> {
+        Ok(untyped(*self))
+    }
+}
+
+impl EncodeValue for TypedValue<'_> {
+    fn encode_value(&self) -> Result<TypedValue<'_>, EncodeError> {
+        Ok(*self)
+    }
+}
+
+impl<T: EncodeValue + ?Sized> EncodeValue for &T {
+    fn encode_value(&self) -> Result<TypedValue<'_>, EncodeError> {
+        (**self).encode_value()
+    }
+}
+
+/// `None` encodes as `null`.
+impl<T: EncodeValue> EncodeValue for Option<T> {
+    fn encode_value(&self) -> Result<TypedValue<'_>, EncodeError> {
+        match self {
+            Some(value) => value.encode_value(),
+            None => Ok(untyped(KdlValue::Null)),
+        }
+    }
+}
+
+/// Encodes a copy of the node under the new name.
+impl EncodeKdl for KdlNode<'_> {
+    fn encode_node<'a>(&'a self, name: &'a str) -> Result<KdlNode<'a>, EncodeError> {
+        let mut node = self.clone();
+        if !name.is_empty() {
+            node.name = Cow::Borrowed(name);
+        }
+
+        Ok(node)
+    }
+}
@@ -0,0 +1,284 @@
+use core::fmt;
+use core::ops::Range;
+
+use crate::ast::*;
+use crate::cursor::{self, Assemble, Nodes};
+use crate::lex::Token;
+use crate::parser::Parser;
+use crate::ParseError;
+
+/// Marks a node without a parent.
+const NONE: usize = usize::MAX;
+
+/// An error assembling a [FixedDocument].
+#[derive(Debug, Copy, Clone)]
+pub enum FixedError {
+    Parse(ParseError),
+    /// The document has more nodes than the node pool can hold.
+    TooManyNodes,
+    /// The document has more arguments and properties than the entry pool can hold.
+    TooManyEntries,
+}
+
+impl From<ParseError> for FixedError {
+    fn from(e: ParseError) -> FixedError {
+        FixedError::Parse(e)
+    }
+}
+
+impl fmt::Display for FixedError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            FixedError::Parse(e) => write!(f, "parse error: {:?}", e),
+            FixedError::TooManyNodes => f.write_str("too many nodes for the node pool"),
+            FixedError::TooManyEntries => {
+                f.write_str("too many arguments and properties for the entry pool")
+            }
+        }
+    }
+}
+
+#[cfg(feature = "std")]
+impl std::error::Error for FixedError {}
+
+/// An argument or property, as stored in the entry pool.
+#[derive(Debug, Copy, Clone, PartialEq)]
+pub enum Entry<'a> {
+    Arg(TypedValue<'a>),
+    Prop(KdlProperty<'a>),
+}
+
+#[derive(Debug, Clone, PartialEq)]
+struct NodeData<'a> {
+    ty: Option<&'a str>,
+    name: KdlString<'a>,
+    parent: usize,
+    /// One past the node's last descendant.
+    end: usize,
+    /// The node's arguments, followed by it's properties.
+    entries: Range<usize>,
+    args: usize,
+}
+
+/// A document assembled into fixed-capacity pools, for use without an allocator: up to `NODES` nodes, and `ENTRIES` arguments and properties between them.
+///
+/// Pools can be big, so it's usually best to keep a document in a `static` or somewhere else long-lived and [read](FixedDocument::read) into it, rather than returning it by value.
+#[derive(Debug, Clone, PartialEq)]
+pub struct FixedDocument<'a, const NODES: usize, const ENTRIES: usize> {
+    nodes: heapless::Vec<NodeData<'a>, NODES>,
+    entries: heapless::Vec<Entry<'a>, ENTRIES>,
+}
+
+impl<'a, const NODES: usize, const ENTRIES: usize> Default for FixedDocument<'a, NODES, ENTRIES> {
+    fn default() -> Self {
+        FixedDocument::new()
+    }
+}
+
+impl<'a, const NODES: usize, const ENTRIES: usize> FixedDocument<'a, NODES, ENTRIES> {
+    /// An empty document.
+    pub const fn new() -> Self {
+        FixedDocument {
+            nodes: heapless::Vec::new(),
+            entries: heapless::Vec::new(),
+        }
+    }
+
+    /// Parses a document from a string.
+    pub fn parse(input: &'a str) -> Result<Self, FixedError> {
+        let mut doc = FixedDocument::new();
+        doc.read(&mut Parser::from_str(input))?;
+        Ok(doc)
+    }
+
+    /// Replaces the document's contents with a document read from a parser's events. If it doesn't fit, the document is left empty.
+    pub fn read<T: Iterator<Item = Token<'a>>, const CAPACITY: usize, const DEPTH: usize>(
+        &mut self,
+        parser: &mut Parser<'a, T, CAPACITY, DEPTH>,
+    ) -> Result<(), FixedError> {
+        self.clear();
+        let read = cursor::read(self, parser);
+        if read.is_err() {
+            self.clear();
+        }
+        read
+    }
+
+    fn push_entry(&mut self, entry: Entry<'a>) -> Result<(), FixedError> {
+        self.entries
+            .push(entry)
+            .map_err(|_| FixedError::TooManyEntries)
+    }
+
+    /// Empties the document, keeping it's pools.
+    pub fn clear(&mut self) {
+        // heapless' `Vec::clear` indexes past the end of it's slice as it truncates, and building a new document would put a whole one on the stack, so the pools are emptied one by one instead
+        while self.nodes.pop().is_some() {}
+        while self.entries.pop().is_some() {}
+    }
+
+    /// The number of nodes, at any depth.
+    pub fn len(&self) -> usize {
+        self.nodes.len()
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.nodes.is_empty()
+    }
+
+    /// The top-level nodes.
+    pub fn roots(&self) -> Siblings<'_, 'a, NODES, ENTRIES> {
+        cursor::roots(self)
+    }
+
+    /// The first top-level node with the given name.
+    pub fn get(&self, name: &str) -> Option<Cursor<'_, 'a, NODES, ENTRIES>> {
+        self.roots().find(|node| self.has_name(node.idx, name))
+    }
+
+    /// Every top-level node with the given name.
+    pub fn get_all<'r>(
+        &'r self,
+        name: &'r str,
+    ) -> impl Iterator<Item = Cursor<'r, 'a, NODES, ENTRIES>> {
+        self.roots()
+            .filter(move |node| self.has_name(node.idx, name))
+    }
+
+    /// Every node, depth-first, parents before their children.
+    pub fn descendants(&self) -> impl Iterator<Item = Cursor<'_, 'a, NODES, ENTRIES>> {
+        cursor::descendants(self)
+    }
+}
+
+impl<'a, const NODES: usize, const ENTRIES: usize> Nodes for FixedDocument<'a, NODES, ENTRIES> {
+    fn node_count(&self) -> usize {
+        self.nodes.len()
+    }
+
+    fn parent(&self, idx: usize) -> Option<usize> {
+        match self.nodes[idx].parent {
+            NONE => None,
+            parent => Some(parent),
+        }
+    }
+
+    fn end(&self, idx: usize) -> usize {
+        self.nodes[idx].end
+    }
+
+    fn has_name(&self, idx: usize, name: &str) -> bool {
+        self.nodes[idx].name == KdlString::Escapeless(name)
+    }
+}
+
+impl<'a, const NODES: usize, const ENTRIES: usize> Assemble<'a>
+    for FixedDocument<'a, NODES, ENTRIES>
+{
+    type Error = FixedError;
+
+    fn push(
+        &mut self,
+        ty: Option<&'a str>,
+        name: KdlString<'a>,
+        values: impl IntoIterator<Item = TypedValue<'a>>,
+        attrs: impl IntoIterator<Item = KdlProperty<'a>>,
+        parent: Option<usize>,
+    ) -> Result<(), FixedError> {
+        let idx = self.nodes.len();
+        let start = self.entries.len();
+
+        for value in values {
+            self.push_entry(Entry::Arg(value))?;
+        }
+        let args = self.entries.len() - start;
+        for attr in attrs {
+            self.push_entry(Entry::Prop(attr))?;
+        }
+
+        self.nodes
+            .push(NodeData {
+                ty,
+                name,
+                parent: parent.unwrap_or(NONE),
+                end: idx + 1,
+                entries: start..self.entries.len(),
+                args,
+            })
+            .map_err(|_| FixedError::TooManyNodes)
+    }
+
+    fn set_end(&mut self, idx: usize, end: usize) {
+        self.nodes[idx].end = end;
+    }
+}
+
+/// A node in a [FixedDocument]. Cursors are `Copy`, and moving one around doesn't touch the document.
+pub type Cursor<'d, 'a, const NODES: usize, const ENTRIES: usize> =
+    cursor::Cursor<'d, FixedDocument<'a, NODES, ENTRIES>>;
+
+/// Iterator over a node's children, or a document's top-level nodes.
+pub type Siblings<'d, 'a, const NODES: usize, const ENTRIES: usize> =
+    cursor::Siblings<'d, FixedDocument<'a, NODES, ENTRIES>>;
+
+impl<'d, 'a, const NODES: usize, const ENTRIES: usize> fmt::Debug
+    for Cursor<'d, 'a, NODES, ENTRIES>
+{
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.debug_struct("Cursor")
+            .field("idx", &self.idx)
+            .field("name", &self.name())
+            .finish()
+    }
+}
+
+impl<'d, 'a, const NODES: usize, const ENTRIES: usize> Cursor<'d, 'a, NODES, ENTRIES> {
+    fn data(&self) -> &'d NodeData<'a> {
+        &self.doc.nodes[self.idx]
+    }
+
+    /// The node's type annotation.
+    pub fn ty(&self) -> Option<&'a str> {
+        self.data().ty
+    }
+
+    /// The node's name, with any escapes still in it; compare it to a [KdlString] to compare it's unescaped text.
+    pub fn name(&self) -> KdlString<'a> {
+        self.data().name
+    }
+
+    /// The node's arguments and properties, arguments first.
+    pub fn entries(&self) -> &'d [Entry<'a>] {
+        &self.doc.entries[self.data().entries.clone()]
+    }
+
+    pub fn args(&self) -> impl Iterator<Item = &'d TypedValue<'a>> {
+        self.entries().iter().filter_map(|entry| match entry {
+            Entry::Arg(value) => Some(value),
+            Entry::Prop(_) => None,
+        })
+    }
+
+    pub fn props(&self) -> impl Iterator<Item = &'d KdlProperty<'a>> {
+        self.entries().iter().filter_map(|entry| match entry {
+            Entry::Prop(attr) => Some(attr),
+            Entry::Arg(_) => None,
+        })
+    }
+
+    /// The `idx`th argument.
+    pub fn arg(&self, idx: usize) -> Option<&'d TypedValue<'a>> {
+        match self.entries()[..self.data().args].get(idx)? {
+            Entry::Arg(value) => Some(value),
+            Entry::Prop(_) => None,
+        }
+    }
+
+    /// The value of a property. If the key is repeated, the last one wins.
+    pub fn prop(&self, key: &str) -> Option<&'d TypedValue<'a>> {
+        self.props()
+            .filter(|attr| attr.key == KdlString::Escapeless(key))
+            .last()
+            .map(|attr| &attr.value)
+    }
+}
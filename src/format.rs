@@ -0,0 +1,374 @@
+#[cfg(all(not(feature = "std"), feature = "alloc"))]
+use alloc::{string::String, vec::Vec};
+
+use core::fmt::{self, Write};
+use logos::Logos;
+
+use crate::assembler::KdlNode;
+use crate::ast::*;
+use crate::lex::Token;
+use crate::parser::Parser;
+use crate::{KdlEvent, ParseResult};
+
+/// How children blocks are indented.
+#[derive(Debug, Copy, Clone, PartialEq)]
+pub enum Indent {
+    Spaces(usize),
+    Tabs,
+}
+
+/// Order in which properties are written.
+#[derive(Debug, Copy, Clone, PartialEq)]
+pub enum PropertyOrder {
+    /// Keep properties in the order they appeared in the source.
+    Source,
+    /// Sort properties by key. The sort is stable, so duplicated keys keep their relative order and the last one still wins.
+    Sorted,
+}
+
+/// How node names and property keys are written.
+#[derive(Debug, Copy, Clone, PartialEq)]
+pub enum QuoteStyle {
+    /// Write names as bare identifiers whenever the lexer would read them back as one, quoting them otherwise.
+    PreferBare,
+    /// Always quote names.
+    AlwaysQuote,
+}
+
+/// How floats are written. Integers are always written in decimal, since their source radix isn't kept around.
+///
+/// kdl has no way of writing NaN, so it's an error with either style; infinities, which the lexer produces for exponents too big for an `f64`, are written as `1e999` so they read back the same.
+#[derive(Debug, Copy, Clone, PartialEq)]
+pub enum NumberStyle {
+    /// Shortest representation that round-trips, e.g. `1.5` or `1e100`.
+    Shortest,
+    /// Always use exponent notation, e.g. `1.5e0`.
+    Scientific,
+}
+
+/// How childless nodes are terminated.
+#[derive(Debug, Copy, Clone, PartialEq)]
+pub enum Terminator {
+    Newline,
+    Semicolon,
+}
+
+/// Options for the [Formatter].
+#[derive(Debug, Copy, Clone, PartialEq)]
+pub struct FormatOptions {
+    pub indent: Indent,
+    /// Lines longer than this are broken up with `\` line continuations. `None` never breaks lines.
+    pub max_width: Option<usize>,
+    pub property_order: PropertyOrder,
+    pub quote_style: QuoteStyle,
+    pub numbers: NumberStyle,
+    pub terminator: Terminator,
+}
+
+impl Default for FormatOptions {
+    fn default() -> FormatOptions {
+        FormatOptions {
+            indent: Indent::Spaces(4),
+            max_width: None,
+            property_order: PropertyOrder::Source,
+            quote_style: QuoteStyle::PreferBare,
+            numbers: NumberStyle::Shortest,
+            terminator: Terminator::Newline,
+        }
+    }
+}
+
+/// Writes canonical kdl, either from [KdlEvent]s or from assembled [KdlNode]s.
+pub struct Formatter<'o, W: Write> {
+    out: W,
+    options: &'o FormatOptions,
+    depth: usize,
+    /// Whether the last node opened a children block that hasn't been written yet; empty blocks are left out, as [Formatter::write_node] does.
+    open_block: bool,
+}
+
+impl<'o, W: Write> Formatter<'o, W> {
+    pub fn new(out: W, options: &'o FormatOptions) -> Formatter<'o, W> {
+        Formatter {
+            out,
+            options,
+            depth: 0,
+            open_block: false,
+        }
+    }
+
+    /// Writes a single event. Feeding this every event from a [Parser] writes out the whole document.
+    pub fn write_event(&mut self, event: &KdlEvent<'_>) -> fmt::Result {
+        if core::mem::take(&mut self.open_block) {
+            if let KdlEvent::BracketedNodeClose(_) = event {
+                self.depth -= 1;
+                return self.terminate();
+            }
+
+            self.out.write_str(" {\n")?;
+        }
+
+        match event {
+            KdlEvent::NodeOpen {
+                ty,
+                name,
+                attrs,
+                values,
+                has_children,
+            } => {
+                let name = self.node_name(*ty, name)?;
+                self.write_line(name, values, attrs)?;
+
+                if *has_children {
+                    self.open_block = true;
+                    self.depth += 1;
+                }
+
+                Ok(())
+            }
+            KdlEvent::NodeClose(_) => self.terminate(),
+            KdlEvent::BracketedNodeClose(_) => {
+                self.depth = self.depth.saturating_sub(1);
+                self.write_indent(self.depth)?;
+                self.out.write_str("}\n")
+            }
+        }
+    }
+
+    /// Writes an assembled node and all of it's children.
+    pub fn write_node(&mut self, node: &KdlNode<'_>) -> fmt::Result {
+        let name = self.node_name(node.ty, &KdlString::Escapeless(&node.name))?;
+        self.write_line(name, &node.values, &node.attrs)?;
+
+        if node.children.is_empty() {
+            return self.terminate();
+        }
+
+        self.out.write_str(" {\n")?;
+        self.depth += 1;
+        for child in &node.children {
+            self.write_node(child)?;
+        }
+        self.depth -= 1;
+
+        self.write_indent(self.depth)?;
+        self.out.write_str("}\n")
+    }
+
+    /// Consumes the formatter, returning the underlying writer.
+    pub fn into_inner(mut self) -> W {
+        if self.open_block {
+            // a block left open at the end of input; there's no way to report an error from here
+            let _ = self.out.write_str(" {\n");
+        }
+
+        self.out
+    }
+
+    fn terminate(&mut self) -> fmt::Result {
+        match self.options.terminator {
+            Terminator::Newline => self.out.write_str("\n"),
+            Terminator::Semicolon => self.out.write_str(";\n"),
+        }
+    }
+
+    fn write_line(
+        &mut self,
+        name: String,
+        values: &[TypedValue<'_>],
+        attrs: &[KdlProperty<'_>],
+    ) -> fmt::Result {
+        let mut entries = Vec::with_capacity(values.len() + attrs.len());
+
+        for value in values {
+            let mut entry = String::new();
+            write_value(&mut entry, value, self.options)?;
+            entries.push(entry);
+        }
+
+        let mut attrs: Vec<&KdlProperty<'_>> = attrs.iter().collect();
+
+        if self.options.property_order == PropertyOrder::Sorted {
+            attrs.sort_by_cached_key(|attr| string_contents(&attr.key));
+        }
+
+        for attr in attrs {
+            let mut entry = self.name(&attr.key)?;
+            entry.push('=');
+            write_value(&mut entry, &attr.value, self.options)?;
+            entries.push(entry);
+        }
+
+        self.write_indent(self.depth)?;
+        self.out.write_str(&name)?;
+
+        let indent_width = match self.options.indent {
+            Indent::Spaces(n) => n,
+            Indent::Tabs => 1,
+        };
+        let mut width = self.depth * indent_width + name.chars().count();
+
+        for entry in entries {
+            let entry_width = entry.chars().count();
+
+            match self.options.max_width {
+                Some(max) if width + 1 + entry_width > max => {
+                    self.out.write_str(" \\\n")?;
+                    self.write_indent(self.depth + 1)?;
+                    width = (self.depth + 1) * indent_width;
+                }
+                _ => {
+                    self.out.write_char(' ')?;
+                    width += 1;
+                }
+            }
+
+            self.out.write_str(&entry)?;
+            width += entry_width;
+        }
+
+        Ok(())
+    }
+
+    fn write_indent(&mut self, depth: usize) -> fmt::Result {
+        for _ in 0..depth {
+            match self.options.indent {
+                Indent::Spaces(n) => {
+                    for _ in 0..n {
+                        self.out.write_char(' ')?;
+                    }
+                }
+                Indent::Tabs => self.out.write_char('\t')?,
+            }
+        }
+
+        Ok(())
+    }
+
+    /// A node's name, with it's type annotation if it has one.
+    fn node_name(&self, ty: Option<&str>, name: &KdlString<'_>) -> Result<String, fmt::Error> {
+        let mut buf = String::new();
+        if let Some(ty) = ty {
+            write!(buf, "({})", ty)?;
+        }
+
+        buf.push_str(&self.name(name)?);
+        Ok(buf)
+    }
+
+    fn name(&self, name: &KdlString<'_>) -> Result<String, fmt::Error> {
+        let contents = string_contents(name);
+
+        if self.options.quote_style == QuoteStyle::PreferBare && is_bare_identifier(&contents) {
+            return Ok(contents);
+        }
+
+        let mut buf = String::with_capacity(contents.len() + 2);
+        write_string(&mut buf, name)?;
+        Ok(buf)
+    }
+}
+
+/// Formats a list of assembled nodes.
+///
+/// Fails if a value is a NaN float, which can't be written as kdl; see [NumberStyle].
+pub fn format_document(
+    nodes: &[KdlNode<'_>],
+    options: &FormatOptions,
+) -> Result<String, fmt::Error> {
+    let mut formatter = Formatter::new(String::new(), options);
+    for node in nodes {
+        formatter.write_node(node)?;
+    }
+
+    Ok(formatter.into_inner())
+}
+
+/// Parses a document and formats it straight from the event stream, without assembling it.
+pub fn format_str(input: &str, options: &FormatOptions) -> ParseResult<String> {
+    let mut formatter = Formatter::new(String::new(), options);
+    for event in Parser::from_str(input) {
+        formatter
+            .write_event(&event?)
+            .expect("writing to a String can't fail");
+    }
+
+    Ok(formatter.into_inner())
+}
+
+/// Checks whether a document is already formatted according to the options.
+pub fn check(input: &str, options: &FormatOptions) -> ParseResult<bool> {
+    Ok(format_str(input, options)? == input)
+}
+
+/// Returns whether the lexer would read `s` back as a single identifier.
+pub(crate) fn is_bare_identifier(s: &str) -> bool {
+    let mut lexer = Token::lexer(s);
+    matches!(lexer.next(), Some(Token::Identifier(ident)) if ident == s) && lexer.next().is_none()
+}
+
+/// Writes the value, with it's type annotation if it has one.
+pub(crate) fn write_value<W: Write>(
+    out: &mut W,
+    value: &TypedValue<'_>,
+    options: &FormatOptions,
+) -> fmt::Result {
+    if let Some(ty) = value.ty {
+        write!(out, "({})", ty)?;
+    }
+
+    match value.val {
+        KdlValue::String(s) => write_string(out, &s),
+        KdlValue::Integer(i) => write!(out, "{}", i),
+        KdlValue::Float(f) if f.is_nan() => Err(fmt::Error),
+        KdlValue::Float(f) if f.is_infinite() => {
+            out.write_str(if f < 0.0 { "-1e999" } else { "1e999" })
+        }
+        KdlValue::Float(f) => match options.numbers {
+            NumberStyle::Shortest => write!(out, "{:?}", f),
+            NumberStyle::Scientific => write!(out, "{:e}", f),
+        },
+        KdlValue::Bool(b) => write!(out, "{}", b),
+        KdlValue::Null => out.write_str("null"),
+    }
+}
+
+/// Writes a quoted string, escaping quotes, backslashes and control characters.
+pub(crate) fn write_quoted<W: Write>(out: &mut W, s: &str) -> fmt::Result {
+    out.write_char('"')?;
+    for c in s.chars() {
+        match c {
+            '"' => out.write_str("\\\"")?,
+            '\\' => out.write_str("\\\\")?,
+            '\n' => out.write_str("\\n")?,
+            '\r' => out.write_str("\\r")?,
+            '\t' => out.write_str("\\t")?,
+            '\u{0008}' => out.write_str("\\b")?,
+            '\u{000C}' => out.write_str("\\f")?,
+            c => out.write_char(c)?,
+        }
+    }
+    out.write_char('"')
+}
+
+/// Writes a [KdlString] as a quoted string. Strings with invalid escapes are written exactly as they were in the source.
+pub(crate) fn write_string<W: Write>(out: &mut W, s: &KdlString<'_>) -> fmt::Result {
+    match s {
+        KdlString::Escapeless(s) => write_quoted(out, s),
+        KdlString::Escaped(raw) => match s.unescape() {
+            Ok(unescaped) => write_quoted(out, &unescaped),
+            Err(_) => write!(out, "\"{}\"", raw),
+        },
+    }
+}
+
+/// The contents of a string with escapes processed, falling back to the source text on invalid escapes.
+pub(crate) fn string_contents(s: &KdlString<'_>) -> String {
+    match s {
+        KdlString::Escapeless(s) => String::from(*s),
+        KdlString::Escaped(raw) => s
+            .unescape()
+            .map(String::from)
+            .unwrap_or_else(|_| String::from(*raw)),
+    }
+}
@@ -0,0 +1,274 @@
+use std::collections::HashMap;
+use std::fmt;
+use std::fs;
+use std::io;
+use std::path::{Component, Path, PathBuf};
+
+use crate::assembler::{self, KdlDocument, KdlNode};
+use crate::ast::*;
+use crate::parser::Parser;
+use crate::ParseError;
+
+/// Loads included files.
+pub trait Loader {
+    fn load(&mut self, path: &Path) -> io::Result<String>;
+}
+
+/// Loads files from the file system.
+#[derive(Debug, Copy, Clone, Default)]
+pub struct FsLoader;
+
+impl Loader for FsLoader {
+    fn load(&mut self, path: &Path) -> io::Result<String> {
+        fs::read_to_string(path)
+    }
+}
+
+/// Loads files from memory, e.g. in tests.
+impl Loader for HashMap<PathBuf, String> {
+    fn load(&mut self, path: &Path) -> io::Result<String> {
+        self.get(path)
+            .cloned()
+            .ok_or_else(|| io::Error::new(io::ErrorKind::NotFound, "no such file"))
+    }
+}
+
+/// Options for expanding includes.
+#[derive(Debug, Clone, PartialEq)]
+pub struct IncludeOptions {
+    /// The name of include nodes.
+    pub directive: String,
+}
+
+impl Default for IncludeOptions {
+    fn default() -> IncludeOptions {
+        IncludeOptions {
+            directive: "include".into(),
+        }
+    }
+}
+
+/// What went wrong while expanding includes.
+#[derive(Debug)]
+pub enum IncludeErrorKind {
+    Io(io::Error),
+    Parse(ParseError),
+    /// A file that ends up including itself; contains the path it was included by again.
+    Cycle(PathBuf),
+    /// An include node without a single string argument.
+    InvalidDirective,
+}
+
+/// An error expanding includes, with the chain of files that led to it, outermost first.
+#[derive(Debug)]
+pub struct IncludeError {
+    pub kind: IncludeErrorKind,
+    pub chain: Vec<PathBuf>,
+}
+
+impl fmt::Display for IncludeErrorKind {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            IncludeErrorKind::Io(e) => write!(f, "{}", e),
+            IncludeErrorKind::Parse(e) => write!(f, "parse error: {:?}", e),
+            IncludeErrorKind::Cycle(path) => {
+                write!(f, "`{}` is already being included", path.display())
+            }
+            IncludeErrorKind::InvalidDirective => {
+                f.write_str("include nodes need a single path argument")
+            }
+        }
+    }
+}
+
+impl fmt::Display for IncludeError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        for (idx, path) in self.chain.iter().enumerate() {
+            if idx > 0 {
+                f.write_str(" -> ")?;
+            }
+            write!(f, "{}", path.display())?;
+        }
+
+        write!(f, ": {}", self.kind)
+    }
+}
+
+impl std::error::Error for IncludeError {}
+
+/// Every file reachable from a root through includes. Since nodes borrow from their source, files are loaded up front, and assembled into a single document with [Sources::document].
+#[derive(Debug, Clone)]
+pub struct Sources {
+    files: HashMap<PathBuf, String>,
+    root: PathBuf,
+    directive: String,
+}
+
+impl Sources {
+    /// Loads a file and everything it includes. Included paths are relative to the file including them.
+    pub fn load<L: Loader>(
+        root: impl AsRef<Path>,
+        loader: &mut L,
+        options: &IncludeOptions,
+    ) -> Result<Sources, IncludeError> {
+        let mut sources = Sources {
+            files: HashMap::new(),
+            root: normalize(root.as_ref()),
+            directive: options.directive.clone(),
+        };
+
+        let root = sources.root.clone();
+        sources.load_file(root, loader, &mut Vec::new())?;
+        Ok(sources)
+    }
+
+    /// The loaded files' paths.
+    pub fn paths(&self) -> impl Iterator<Item = &Path> {
+        self.files.keys().map(PathBuf::as_path)
+    }
+
+    /// Assembles the root file, replacing each include node with the top-level nodes of the file it includes.
+    pub fn document(&self) -> Result<KdlDocument<'_>, IncludeError> {
+        let mut chain = vec![self.root.clone()];
+        let nodes = self.assemble(&mut chain)?;
+        Ok(KdlDocument { nodes })
+    }
+
+    fn load_file<L: Loader>(
+        &mut self,
+        path: PathBuf,
+        loader: &mut L,
+        chain: &mut Vec<PathBuf>,
+    ) -> Result<(), IncludeError> {
+        if chain.contains(&path) {
+            return Err(IncludeError {
+                kind: IncludeErrorKind::Cycle(path),
+                chain: chain.clone(),
+            });
+        }
+
+        // a file that's already been loaded has had everything it includes loaded, too
+        if self.files.contains_key(&path) {
+            return Ok(());
+        }
+
+        chain.push(path.clone());
+
+        let source = loader
+            .load(&path)
+            .map_err(|e| error(chain, IncludeErrorKind::Io(e)))?;
+        let mut includes = Vec::new();
+        let nodes = parse(&source).map_err(|kind| error(chain, kind))?;
+        self.find_includes(&nodes, &path, &mut includes)
+            .map_err(|kind| error(chain, kind))?;
+        drop(nodes);
+
+        self.files.insert(path, source);
+        for include in includes {
+            self.load_file(include, loader, chain)?;
+        }
+
+        chain.pop();
+        Ok(())
+    }
+
+    fn find_includes(
+        &self,
+        nodes: &[KdlNode<'_>],
+        from: &Path,
+        out: &mut Vec<PathBuf>,
+    ) -> Result<(), IncludeErrorKind> {
+        for node in nodes {
+            if node.name == self.directive {
+                out.push(self.include_path(node, from)?);
+            } else {
+                self.find_includes(&node.children, from, out)?;
+            }
+        }
+
+        Ok(())
+    }
+
+    fn include_path(&self, node: &KdlNode<'_>, from: &Path) -> Result<PathBuf, IncludeErrorKind> {
+        match node.values.as_slice() {
+            [TypedValue {
+                val: KdlValue::String(path),
+                ..
+            }] => {
+                let path = path.unescape().map_err(IncludeErrorKind::Parse)?;
+                let dir = from.parent().unwrap_or_else(|| Path::new(""));
+                Ok(normalize(&dir.join(path.as_ref())))
+            }
+            _ => Err(IncludeErrorKind::InvalidDirective),
+        }
+    }
+
+    fn assemble(&self, chain: &mut Vec<PathBuf>) -> Result<Vec<KdlNode<'_>>, IncludeError> {
+        let path = chain[chain.len() - 1].clone();
+        let nodes = parse(&self.files[&path]).map_err(|kind| error(chain, kind))?;
+        self.expand(nodes, &path, chain)
+    }
+
+    fn expand<'s>(
+        &'s self,
+        nodes: Vec<KdlNode<'s>>,
+        from: &Path,
+        chain: &mut Vec<PathBuf>,
+    ) -> Result<Vec<KdlNode<'s>>, IncludeError> {
+        let mut out = Vec::with_capacity(nodes.len());
+
+        for mut node in nodes {
+            if node.name == self.directive {
+                let path = self
+                    .include_path(&node, from)
+                    .map_err(|kind| error(chain, kind))?;
+
+                chain.push(path);
+                out.extend(self.assemble(chain)?);
+                chain.pop();
+            } else {
+                node.children = self.expand(node.children, from, chain)?;
+                out.push(node);
+            }
+        }
+
+        Ok(out)
+    }
+}
+
+/// Loads a file from the file system, along with everything it includes.
+pub fn load_file(path: impl AsRef<Path>) -> Result<Sources, IncludeError> {
+    Sources::load(path, &mut FsLoader, &IncludeOptions::default())
+}
+
+fn parse(source: &str) -> Result<Vec<KdlNode<'_>>, IncludeErrorKind> {
+    assembler::parse_document(&mut Parser::from_str(source)).map_err(IncludeErrorKind::Parse)
+}
+
+fn error(chain: &[PathBuf], kind: IncludeErrorKind) -> IncludeError {
+    IncludeError {
+        kind,
+        chain: chain.to_vec(),
+    }
+}
+
+/// Resolves `.` and `..` in a path without touching the file system, so in-memory loaders see the same paths.
+fn normalize(path: &Path) -> PathBuf {
+    let mut out = PathBuf::new();
+
+    for component in path.components() {
+        match component {
+            Component::CurDir => (),
+            Component::ParentDir => {
+                if matches!(out.components().next_back(), Some(Component::Normal(_))) {
+                    out.pop();
+                } else {
+                    out.push("..");
+                }
+            }
+            other => out.push(other),
+        }
+    }
+
+    out
+}
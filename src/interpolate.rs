@@ -0,0 +1,299 @@
+#[cfg(all(not(feature = "std"), feature = "alloc"))]
+use alloc::{
+    collections::BTreeMap,
+    string::{String, ToString},
+    vec::Vec,
+};
+
+#[cfg(feature = "std")]
+use std::collections::{BTreeMap, HashMap};
+
+use core::fmt;
+
+use crate::assembler::{KdlDocument, KdlNode};
+use crate::ast::*;
+use crate::format;
+use crate::ParseError;
+
+/// Where variables are looked up.
+pub trait Variables {
+    fn get(&self, name: &str) -> Option<String>;
+}
+
+/// The process' environment variables.
+#[cfg(feature = "std")]
+#[derive(Debug, Copy, Clone, Default)]
+pub struct Env;
+
+#[cfg(feature = "std")]
+impl Variables for Env {
+    fn get(&self, name: &str) -> Option<String> {
+        std::env::var(name).ok()
+    }
+}
+
+#[cfg(feature = "std")]
+impl Variables for HashMap<String, String> {
+    fn get(&self, name: &str) -> Option<String> {
+        HashMap::get(self, name).cloned()
+    }
+}
+
+impl Variables for BTreeMap<String, String> {
+    fn get(&self, name: &str) -> Option<String> {
+        BTreeMap::get(self, name).cloned()
+    }
+}
+
+impl<F: Fn(&str) -> Option<String>> Variables for F {
+    fn get(&self, name: &str) -> Option<String> {
+        self(name)
+    }
+}
+
+/// An argument or property of a node; properties are counted in [KdlNode::attrs], so repeated keys each have their own.
+#[derive(Debug, Copy, Clone, PartialEq, Eq, PartialOrd, Ord)]
+pub enum Slot {
+    Arg(usize),
+    Prop(usize),
+}
+
+/// What went wrong while interpolating.
+#[derive(Debug, Clone)]
+pub enum InterpolationErrorKind {
+    Parse(ParseError),
+    /// A variable without a value or a default.
+    Undefined(String),
+    /// A `${` without a closing `}`.
+    Unterminated,
+}
+
+/// The argument or property a value is in.
+#[derive(Debug, Clone, PartialEq)]
+pub enum Entry {
+    Argument(usize),
+    Property(String),
+}
+
+/// An error interpolating a value, with the path to it's node and the argument or property it's in.
+#[derive(Debug, Clone)]
+pub struct InterpolationError {
+    pub kind: InterpolationErrorKind,
+    /// Names of the nodes leading to the value, outermost first.
+    pub path: Vec<String>,
+    pub entry: Entry,
+}
+
+impl fmt::Display for InterpolationErrorKind {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            InterpolationErrorKind::Parse(e) => write!(f, "parse error: {:?}", e),
+            InterpolationErrorKind::Undefined(name) => write!(f, "undefined variable `{}`", name),
+            InterpolationErrorKind::Unterminated => f.write_str("unterminated `${`"),
+        }
+    }
+}
+
+impl fmt::Display for InterpolationError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.write_str(&self.path.join(" > "))?;
+
+        match &self.entry {
+            Entry::Argument(idx) => write!(f, ": argument {}", idx)?,
+            Entry::Property(key) => write!(f, ": property `{}`", key)?,
+        }
+
+        write!(f, ": {}", self.kind)
+    }
+}
+
+#[cfg(feature = "std")]
+impl std::error::Error for InterpolationError {}
+
+/// The interpolated values of a document, kept apart from it so the original strings are still there.
+#[derive(Debug, Clone, PartialEq, Default)]
+pub struct Expansions {
+    values: BTreeMap<(Vec<usize>, Slot), String>,
+}
+
+impl Expansions {
+    /// The interpolated value of an argument or property of the node at a path of indices, as in [KdlDocument::node_at]; `None` if it wasn't interpolated.
+    pub fn get(&self, path: &[usize], slot: Slot) -> Option<&str> {
+        self.values.get(&(path.to_vec(), slot)).map(String::as_str)
+    }
+
+    pub fn len(&self) -> usize {
+        self.values.len()
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.values.is_empty()
+    }
+
+    /// Copies a document with it's interpolated values swapped in, borrowing them from here. `(env)` annotations are dropped from the values they expanded.
+    pub fn apply<'r>(&'r self, doc: &KdlDocument<'r>) -> KdlDocument<'r> {
+        let mut path = Vec::new();
+        KdlDocument {
+            nodes: self.apply_nodes(&doc.nodes, &mut path),
+        }
+    }
+
+    fn apply_nodes<'r>(&'r self, nodes: &[KdlNode<'r>], path: &mut Vec<usize>) -> Vec<KdlNode<'r>> {
+        nodes
+            .iter()
+            .enumerate()
+            .map(|(idx, node)| {
+                path.push(idx);
+
+                let mut node = node.clone();
+                for (idx, value) in node.values.iter_mut().enumerate() {
+                    self.apply_value(path, Slot::Arg(idx), value);
+                }
+                for (idx, attr) in node.attrs.iter_mut().enumerate() {
+                    self.apply_value(path, Slot::Prop(idx), &mut attr.value);
+                }
+                node.children = self.apply_nodes(&node.children, path);
+
+                path.pop();
+                node
+            })
+            .collect()
+    }
+
+    fn apply_value<'r>(&'r self, path: &[usize], slot: Slot, value: &mut TypedValue<'r>) {
+        if let Some(expanded) = self.get(path, slot) {
+            *value = TypedValue {
+                ty: value.ty.filter(|ty| *ty != "env"),
+                val: KdlValue::String(KdlString::Escapeless(expanded)),
+            };
+        }
+    }
+}
+
+/// Interpolates every string argument and property value in a document:
+///
+/// - `${NAME}` in a string is replaced with the variable's value, or with `default` in `${NAME:-default}` if it isn't set.
+/// - `$$` is a literal `$`, as is a `$` that isn't followed by `{`.
+/// - a string annotated `(env)`, like `(env)"DB_URL"`, is replaced with the variable it names, which can have a default too: `(env)"PORT:-8080"`.
+pub fn interpolate<V: Variables + ?Sized>(
+    doc: &KdlDocument<'_>,
+    vars: &V,
+) -> Result<Expansions, InterpolationError> {
+    let mut expansions = Expansions::default();
+    interpolate_nodes(&doc.nodes, vars, &mut Vec::new(), &mut expansions)?;
+    Ok(expansions)
+}
+
+fn interpolate_nodes<V: Variables + ?Sized>(
+    nodes: &[KdlNode<'_>],
+    vars: &V,
+    path: &mut Vec<usize>,
+    expansions: &mut Expansions,
+) -> Result<(), InterpolationError> {
+    for (idx, node) in nodes.iter().enumerate() {
+        path.push(idx);
+
+        let slots = node
+            .values
+            .iter()
+            .enumerate()
+            .map(|(idx, value)| (Slot::Arg(idx), value))
+            .chain(
+                node.attrs
+                    .iter()
+                    .enumerate()
+                    .map(|(idx, attr)| (Slot::Prop(idx), &attr.value)),
+            );
+
+        for (slot, value) in slots {
+            let expanded = expand_value(value, vars).map_err(|kind| InterpolationError {
+                kind,
+                path: Vec::new(),
+                entry: match slot {
+                    Slot::Arg(idx) => Entry::Argument(idx),
+                    Slot::Prop(idx) => {
+                        Entry::Property(format::string_contents(&node.attrs[idx].key))
+                    }
+                },
+            });
+
+            match expanded {
+                Ok(Some(expanded)) => {
+                    expansions.values.insert((path.clone(), slot), expanded);
+                }
+                Ok(None) => (),
+                Err(e) => return Err(within(e, node)),
+            }
+        }
+
+        interpolate_nodes(&node.children, vars, path, expansions).map_err(|e| within(e, node))?;
+        path.pop();
+    }
+
+    Ok(())
+}
+
+fn within(mut e: InterpolationError, node: &KdlNode<'_>) -> InterpolationError {
+    e.path.insert(0, node.name.to_string());
+    e
+}
+
+/// Expands a value, if it has anything to expand.
+fn expand_value<V: Variables + ?Sized>(
+    value: &TypedValue<'_>,
+    vars: &V,
+) -> Result<Option<String>, InterpolationErrorKind> {
+    let s = match value.val {
+        KdlValue::String(s) => s.unescape().map_err(InterpolationErrorKind::Parse)?,
+        _ => return Ok(None),
+    };
+
+    if value.ty == Some("env") {
+        lookup(&s, vars).map(Some)
+    } else if s.contains('$') {
+        expand(&s, vars).map(Some)
+    } else {
+        Ok(None)
+    }
+}
+
+/// Looks up `NAME` or `NAME:-default`.
+fn lookup<V: Variables + ?Sized>(
+    reference: &str,
+    vars: &V,
+) -> Result<String, InterpolationErrorKind> {
+    let (name, default) = match reference.split_once(":-") {
+        Some((name, default)) => (name, Some(default)),
+        None => (reference, None),
+    };
+
+    vars.get(name)
+        .or_else(|| default.map(String::from))
+        .ok_or_else(|| InterpolationErrorKind::Undefined(name.to_string()))
+}
+
+fn expand<V: Variables + ?Sized>(s: &str, vars: &V) -> Result<String, InterpolationErrorKind> {
+    let mut out = String::with_capacity(s.len());
+    let mut rest = s;
+
+    while let Some(pos) = rest.find('$') {
+        out.push_str(&rest[..pos]);
+        rest = &rest[pos + 1..];
+
+        if let Some(after) = rest.strip_prefix('$') {
+            out.push('$');
+            rest = after;
+        } else if let Some(after) = rest.strip_prefix('{') {
+            let end = after
+                .find('}')
+                .ok_or(InterpolationErrorKind::Unterminated)?;
+            out.push_str(&lookup(&after[..end], vars)?);
+            rest = &after[end + 1..];
+        } else {
+            out.push('$');
+        }
+    }
+
+    out.push_str(rest);
+    Ok(out)
+}
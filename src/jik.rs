@@ -0,0 +1,271 @@
+use std::borrow::Cow;
+use std::fmt;
+
+use serde_json::{Map, Number, Value};
+
+use crate::assembler::{self, KdlNode};
+use crate::ast::*;
+use crate::format::{self, FormatOptions};
+use crate::parser::Parser;
+use crate::ParseError;
+
+/// What went wrong while reading JiK.
+#[derive(Debug, Clone)]
+pub enum JikErrorKind {
+    Parse(ParseError),
+    /// A JiK document has exactly one top-level node; contains how many there were.
+    RootCount(usize),
+    /// An `(array)` node with properties, or with children not named `-`.
+    NotAnArray,
+    /// An `(object)` node with arguments.
+    NotAnObject,
+    /// A node with both array items and object members, e.g. arguments and properties.
+    Mixed,
+    /// A float JSON can't hold, like infinity.
+    NonFiniteFloat(f64),
+}
+
+/// An error while reading JiK, with the path to the node it happened at.
+#[derive(Debug, Clone)]
+pub struct JikError {
+    pub kind: JikErrorKind,
+    /// Names of the nodes leading to the one that failed, outermost first. Empty for the document itself.
+    pub path: Vec<String>,
+}
+
+impl JikError {
+    fn new(node: &KdlNode<'_>, kind: JikErrorKind) -> JikError {
+        JikError {
+            kind,
+            path: vec![node.name.to_string()],
+        }
+    }
+
+    fn within(mut self, parent: &KdlNode<'_>) -> JikError {
+        self.path.insert(0, parent.name.to_string());
+        self
+    }
+}
+
+impl fmt::Display for JikErrorKind {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            JikErrorKind::Parse(e) => write!(f, "parse error: {:?}", e),
+            JikErrorKind::RootCount(found) => {
+                write!(f, "expected exactly one top-level node, found {}", found)
+            }
+            JikErrorKind::NotAnArray => f.write_str("`(array)` node has object members"),
+            JikErrorKind::NotAnObject => f.write_str("`(object)` node has arguments"),
+            JikErrorKind::Mixed => f.write_str("node has both array items and object members"),
+            JikErrorKind::NonFiniteFloat(x) => write!(f, "{} can't be represented in JSON", x),
+        }
+    }
+}
+
+impl fmt::Display for JikError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        if self.path.is_empty() {
+            write!(f, "document: {}", self.kind)
+        } else {
+            write!(f, "{}: {}", self.path.join(" > "), self.kind)
+        }
+    }
+}
+
+impl std::error::Error for JikError {}
+
+/// How a node reads as JSON.
+#[derive(Debug, Clone, Copy, PartialEq)]
+enum Shape {
+    Literal,
+    Array,
+    Object,
+}
+
+/// Works out a node's shape, following the JiK rules:
+/// - `(array)` and `(object)` annotations decide it outright.
+/// - a single argument, and nothing else, is a literal.
+/// - arguments and children named `-` are array items.
+/// - properties and other children are object members; an empty node is an empty object.
+fn shape(node: &KdlNode<'_>) -> Result<Shape, JikErrorKind> {
+    let items = node.children.iter().all(|child| child.name == "-");
+
+    match node.ty {
+        Some("array") if node.attrs.is_empty() && items => return Ok(Shape::Array),
+        Some("array") => return Err(JikErrorKind::NotAnArray),
+        Some("object") if node.values.is_empty() => return Ok(Shape::Object),
+        Some("object") => return Err(JikErrorKind::NotAnObject),
+        _ => (),
+    }
+
+    let has_values = !node.values.is_empty();
+    let has_props = !node.attrs.is_empty();
+    let has_children = !node.children.is_empty();
+
+    if has_values && has_props {
+        Err(JikErrorKind::Mixed)
+    } else if node.values.len() == 1 && !has_props && !has_children {
+        Ok(Shape::Literal)
+    } else if has_values && !items {
+        Err(JikErrorKind::Mixed)
+    } else if has_values || (has_children && !has_props && items) {
+        Ok(Shape::Array)
+    } else {
+        Ok(Shape::Object)
+    }
+}
+
+/// Reads a node as JSON. The node's own name doesn't matter; it's children's names are object keys.
+pub fn to_json(node: &KdlNode<'_>) -> Result<Value, JikError> {
+    let shape = shape(node).map_err(|kind| JikError::new(node, kind))?;
+
+    match shape {
+        Shape::Literal => value(&node.values[0].val).map_err(|kind| JikError::new(node, kind)),
+        Shape::Array => {
+            let mut items = Vec::with_capacity(node.values.len() + node.children.len());
+            for item in &node.values {
+                items.push(value(&item.val).map_err(|kind| JikError::new(node, kind))?);
+            }
+            for child in &node.children {
+                items.push(to_json(child).map_err(|e| e.within(node))?);
+            }
+
+            Ok(Value::Array(items))
+        }
+        Shape::Object => {
+            // repeated keys work like they do in JSON; the last one wins
+            let mut members = Map::new();
+            for attr in &node.attrs {
+                let key = attr
+                    .key
+                    .unescape()
+                    .map_err(|e| JikError::new(node, JikErrorKind::Parse(e)))?;
+                let member = value(&attr.value.val).map_err(|kind| JikError::new(node, kind))?;
+                members.insert(key.into_owned(), member);
+            }
+            for child in &node.children {
+                let member = to_json(child).map_err(|e| e.within(node))?;
+                members.insert(child.name.to_string(), member);
+            }
+
+            Ok(Value::Object(members))
+        }
+    }
+}
+
+/// Reads a whole JiK document, which has a single top-level node, as JSON.
+pub fn document_to_json(nodes: &[KdlNode<'_>]) -> Result<Value, JikError> {
+    match nodes {
+        [root] => to_json(root),
+        _ => Err(JikError {
+            kind: JikErrorKind::RootCount(nodes.len()),
+            path: Vec::new(),
+        }),
+    }
+}
+
+/// Parses a JiK document and reads it as JSON.
+pub fn str_to_json(input: &str) -> Result<Value, JikError> {
+    let nodes = assembler::parse_document(&mut Parser::from_str(input)).map_err(|e| JikError {
+        kind: JikErrorKind::Parse(e),
+        path: Vec::new(),
+    })?;
+
+    document_to_json(&nodes)
+}
+
+/// Writes JSON as a JiK node called `-`, borrowing it's strings.
+///
+/// Scalars in objects become properties and everything else becomes children, and arrays of scalars become arguments.
+/// Nodes are only annotated with `(array)` or `(object)` where they'd read back differently without it, e.g. empty or single-element arrays.
+/// Integers outside of `i64` become floats.
+pub fn from_json(value: &Value) -> KdlNode<'_> {
+    node_from_json("-", value)
+}
+
+/// Writes JSON as a formatted JiK document.
+pub fn json_to_string(value: &Value, options: &FormatOptions) -> String {
+    format::format_document(&[from_json(value)], options).expect("JSON numbers are never NaN")
+}
+
+fn node_from_json<'a>(name: &'a str, value: &'a Value) -> KdlNode<'a> {
+    let mut node = KdlNode {
+        ty: None,
+        name: Cow::Borrowed(name),
+        attrs: Vec::new(),
+        values: Vec::new(),
+        children: Vec::new(),
+    };
+
+    let expected = match value {
+        Value::Array(items) => {
+            if items.iter().all(is_scalar) {
+                node.values = items.iter().map(scalar).collect();
+            } else {
+                node.children = items.iter().map(|item| node_from_json("-", item)).collect();
+            }
+
+            Shape::Array
+        }
+        Value::Object(members) => {
+            for (key, member) in members {
+                if is_scalar(member) {
+                    node.attrs.push(KdlProperty {
+                        key: KdlString::Escapeless(key),
+                        value: scalar(member),
+                    });
+                } else {
+                    node.children.push(node_from_json(key, member));
+                }
+            }
+
+            Shape::Object
+        }
+        _ => {
+            node.values.push(scalar(value));
+            Shape::Literal
+        }
+    };
+
+    if shape(&node).ok() != Some(expected) {
+        node.ty = match expected {
+            Shape::Array => Some("array"),
+            _ => Some("object"),
+        };
+    }
+
+    node
+}
+
+fn is_scalar(value: &Value) -> bool {
+    !matches!(value, Value::Array(_) | Value::Object(_))
+}
+
+fn scalar(value: &Value) -> TypedValue<'_> {
+    let val = match value {
+        Value::Bool(b) => KdlValue::Bool(*b),
+        Value::Number(n) => match n.as_i64() {
+            Some(i) => KdlValue::Integer(i),
+            // `as_f64` only fails with serde_json's arbitrary precision numbers, for ones an f64 can't hold
+            None => n.as_f64().map_or(KdlValue::Null, KdlValue::Float),
+        },
+        Value::String(s) => KdlValue::String(KdlString::Escapeless(s)),
+        _ => KdlValue::Null,
+    };
+
+    TypedValue { ty: None, val }
+}
+
+fn value(val: &KdlValue<'_>) -> Result<Value, JikErrorKind> {
+    Ok(match *val {
+        KdlValue::String(s) => {
+            Value::String(s.unescape().map_err(JikErrorKind::Parse)?.into_owned())
+        }
+        KdlValue::Integer(i) => Value::from(i),
+        KdlValue::Float(f) => Number::from_f64(f)
+            .map(Value::Number)
+            .ok_or(JikErrorKind::NonFiniteFloat(f))?,
+        KdlValue::Bool(b) => Value::Bool(b),
+        KdlValue::Null => Value::Null,
+    })
+}
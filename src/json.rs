@@ -0,0 +1,263 @@
+#[cfg(all(not(feature = "std"), feature = "alloc"))]
+use alloc::string::String;
+
+use core::fmt::{self, Write};
+
+use crate::ast::*;
+use crate::lex::Token;
+use crate::parser::Parser;
+use crate::{KdlEvent, ParseError};
+
+/// An error while transcoding; either from the parser or from the output.
+#[derive(Debug)]
+pub enum TranscodeError {
+    Parse(ParseError),
+    Write(fmt::Error),
+    /// An error from the io sink given to [transcode_to_writer].
+    #[cfg(feature = "std")]
+    Io(std::io::Error),
+}
+
+impl fmt::Display for TranscodeError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            TranscodeError::Parse(e) => write!(f, "parse error: {:?}", e),
+            TranscodeError::Write(e) => write!(f, "{}", e),
+            #[cfg(feature = "std")]
+            TranscodeError::Io(e) => write!(f, "io error: {}", e),
+        }
+    }
+}
+
+#[cfg(feature = "std")]
+impl std::error::Error for TranscodeError {}
+
+/// Writes JSON from [KdlEvent]s as they come, without assembling nodes; it only keeps track of how deep it is, so memory use doesn't grow with the document.
+///
+/// A document becomes an array of nodes, and each node becomes an object:
+///
+/// ```json
+/// {"name": "node", "type": "annotation", "values": [1, "two"], "props": {"key": true}, "children": []}
+/// ```
+///
+/// `type` is only there for annotated nodes. Annotated values become `{"type": "...", "value": ...}` objects. If a property is repeated, only the last one is written.
+pub struct JsonWriter<W: Write> {
+    out: W,
+    depth: usize,
+    started: bool,
+    /// Whether the next node follows a sibling, and needs a comma.
+    comma: bool,
+}
+
+impl<W: Write> JsonWriter<W> {
+    pub fn new(out: W) -> JsonWriter<W> {
+        JsonWriter {
+            out,
+            depth: 0,
+            started: false,
+            comma: false,
+        }
+    }
+
+    /// Writes a single event. Feeding this every event from a [Parser], then calling [JsonWriter::finish], writes out the whole document.
+    pub fn write_event(&mut self, event: &KdlEvent<'_>) -> Result<(), TranscodeError> {
+        self.start()?;
+
+        match event {
+            KdlEvent::NodeOpen {
+                ty,
+                name,
+                attrs,
+                values,
+                has_children,
+            } => {
+                if self.comma {
+                    self.out.write_char(',')?;
+                }
+
+                self.out.write_str("{\"name\":")?;
+                self.write_string(name)?;
+
+                if let Some(ty) = ty {
+                    self.out.write_str(",\"type\":")?;
+                    write_quoted(&mut self.out, ty)?;
+                }
+
+                self.out.write_str(",\"values\":[")?;
+                for (idx, value) in values.iter().enumerate() {
+                    if idx > 0 {
+                        self.out.write_char(',')?;
+                    }
+                    self.write_value(value)?;
+                }
+
+                self.out.write_str("],\"props\":{")?;
+                let mut first = true;
+                for (idx, attr) in attrs.iter().enumerate() {
+                    // the last of a repeated key wins
+                    if attrs[idx + 1..].iter().any(|later| later.key == attr.key) {
+                        continue;
+                    }
+
+                    if !first {
+                        self.out.write_char(',')?;
+                    }
+                    first = false;
+
+                    self.write_string(&attr.key)?;
+                    self.out.write_char(':')?;
+                    self.write_value(&attr.value)?;
+                }
+
+                if *has_children {
+                    self.out.write_str("},\"children\":[")?;
+                    self.depth += 1;
+                    self.comma = false;
+                } else {
+                    self.out.write_str("},\"children\":[]}")?;
+                    self.comma = true;
+                }
+            }
+            KdlEvent::NodeClose(_) => (),
+            KdlEvent::BracketedNodeClose(_) => {
+                self.out.write_str("]}")?;
+                self.depth = self.depth.saturating_sub(1);
+                self.comma = true;
+            }
+        }
+
+        Ok(())
+    }
+
+    /// Closes the document, returning the writer.
+    pub fn finish(mut self) -> Result<W, TranscodeError> {
+        self.start()?;
+
+        // close anything still open, in case the events stopped early
+        for _ in 0..self.depth {
+            self.out.write_str("]}")?;
+        }
+        self.out.write_char(']')?;
+
+        Ok(self.out)
+    }
+
+    fn start(&mut self) -> fmt::Result {
+        if !self.started {
+            self.started = true;
+            self.out.write_char('[')?;
+        }
+
+        Ok(())
+    }
+
+    fn write_string(&mut self, s: &KdlString<'_>) -> Result<(), TranscodeError> {
+        let s = s.unescape().map_err(TranscodeError::Parse)?;
+        write_quoted(&mut self.out, &s)?;
+        Ok(())
+    }
+
+    fn write_value(&mut self, value: &TypedValue<'_>) -> Result<(), TranscodeError> {
+        if let Some(ty) = value.ty {
+            self.out.write_str("{\"type\":")?;
+            write_quoted(&mut self.out, ty)?;
+            self.out.write_str(",\"value\":")?;
+        }
+
+        match value.val {
+            KdlValue::String(s) => self.write_string(&s)?,
+            KdlValue::Integer(i) => write!(self.out, "{}", i)?,
+            // JSON has no infinities or NaN
+            KdlValue::Float(f) if !f.is_finite() => self.out.write_str("null")?,
+            KdlValue::Float(f) => write!(self.out, "{:?}", f)?,
+            KdlValue::Bool(b) => write!(self.out, "{}", b)?,
+            KdlValue::Null => self.out.write_str("null")?,
+        }
+
+        if value.ty.is_some() {
+            self.out.write_char('}')?;
+        }
+
+        Ok(())
+    }
+}
+
+impl From<fmt::Error> for TranscodeError {
+    fn from(e: fmt::Error) -> TranscodeError {
+        TranscodeError::Write(e)
+    }
+}
+
+/// Transcodes everything a parser reads into JSON.
+pub fn transcode<'a, T: Iterator<Item = Token<'a>>, W: Write>(
+    parser: Parser<'a, T>,
+    out: W,
+) -> Result<W, TranscodeError> {
+    let mut writer = JsonWriter::new(out);
+    for event in parser {
+        writer.write_event(&event.map_err(TranscodeError::Parse)?)?;
+    }
+
+    writer.finish()
+}
+
+/// Transcodes everything a parser reads into JSON, straight into an io sink like a file, so nothing is buffered in between. Writes are small, so unbuffered sinks are best wrapped in a [std::io::BufWriter].
+#[cfg(feature = "std")]
+pub fn transcode_to_writer<'a, T: Iterator<Item = Token<'a>>, W: std::io::Write>(
+    parser: Parser<'a, T>,
+    out: W,
+) -> Result<W, TranscodeError> {
+    let mut sink = IoSink {
+        inner: out,
+        error: None,
+    };
+
+    match transcode(parser, &mut sink) {
+        Ok(_) => Ok(sink.inner),
+        Err(TranscodeError::Write(e)) => Err(sink
+            .error
+            .map_or(TranscodeError::Write(e), TranscodeError::Io)),
+        Err(e) => Err(e),
+    }
+}
+
+/// Adapts an io sink for the writer, holding on to the io error that a [fmt::Error] stands in for.
+#[cfg(feature = "std")]
+struct IoSink<W> {
+    inner: W,
+    error: Option<std::io::Error>,
+}
+
+#[cfg(feature = "std")]
+impl<W: std::io::Write> Write for IoSink<W> {
+    fn write_str(&mut self, s: &str) -> fmt::Result {
+        self.inner.write_all(s.as_bytes()).map_err(|e| {
+            self.error = Some(e);
+            fmt::Error
+        })
+    }
+}
+
+/// Transcodes a document into a JSON string.
+pub fn to_json_string(input: &str) -> Result<String, TranscodeError> {
+    transcode(Parser::from_str(input), String::new())
+}
+
+/// Writes a JSON string, escaping quotes, backslashes and control characters.
+fn write_quoted<W: Write>(out: &mut W, s: &str) -> fmt::Result {
+    out.write_char('"')?;
+
+    for c in s.chars() {
+        match c {
+            '"' => out.write_str("\\\"")?,
+            '\\' => out.write_str("\\\\")?,
+            '\n' => out.write_str("\\n")?,
+            '\r' => out.write_str("\\r")?,
+            '\t' => out.write_str("\\t")?,
+            c if (c as u32) < 0x20 => write!(out, "\\u{:04x}", c as u32)?,
+            c => out.write_char(c)?,
+        }
+    }
+
+    out.write_char('"')
+}
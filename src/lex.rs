@@ -46,7 +46,7 @@ pub enum Token<'input> {
     Integer(i64),
     #[regex(
         r#"\([^0-9\x00-\x20/\\(){}<>;\[\]=,"]+[^\x00-\x20/\\(){}<>;\[\]=,"]*\)"#,
-        parsers::parse_str
+        parsers::parse_ty_descriptor
     )]
     TyDescriptor(&'input str),
     #[regex(r#"[^0-9\x00-\x20/\\(){}<>;\[\]=,"\u000D\u000A\u0085\u000C\u2028\u2029\u0009\u0020\u00A0\u1680\u2000\u2001\u2002\u2003\u2004\u2005\u2006\u2007\u2008\u2009\u200A\u202F\u205F\u3000]+[^\x00-\x20/\\(){}<>;\[\]=,"\u000D\u000A\u0085\u000C\u2028\u2029\u0009\u0020\u00A0\u1680\u2000\u2001\u2002\u2003\u2004\u2005\u2006\u2007\u2008\u2009\u200A\u202F\u205F\u3000]*"#, callback = |lex| lex.slice())]
@@ -160,6 +160,16 @@ pub(crate) mod parsers {
         &slice[1..slice.len() - 1]
     }
 
+    /// A type descriptor has to sit right against what it annotates, so whitespace or a comment after it is an error.
+    pub(crate) fn parse_ty_descriptor<'input>(
+        lexer: &mut Lexer<'input, Token<'input>>,
+    ) -> Option<&'input str> {
+        match lexer.remainder().chars().next() {
+            Some(c) if c.is_whitespace() || c == '/' || c == '\\' => None,
+            _ => Some(parse_str(lexer)),
+        }
+    }
+
     pub(crate) fn multiline_comment<'input>(
         lexer: &mut Lexer<'input, Token<'input>>,
     ) -> logos::Skip {
@@ -4,17 +4,17 @@
 #[cfg(all(not(feature = "std"), feature = "alloc"))]
 extern crate alloc;
 
-/// Alias for a container; either alloc::Vec, std::Vec, or heapless::Vec
+/// Alias for a container; either alloc::Vec, std::Vec, or heapless::Vec with a capacity of `N`. `N` is ignored when there's an allocator.
 #[cfg(feature = "std")]
-pub type Container<A> = std::vec::Vec<A>;
+pub type Container<A, const N: usize = 128> = std::vec::Vec<A>;
 
-/// Alias for a container; either alloc::Vec, std::Vec, or heapless::Vec
+/// Alias for a container; either alloc::Vec, std::Vec, or heapless::Vec with a capacity of `N`. `N` is ignored when there's an allocator.
 #[cfg(all(feature = "alloc", not(feature = "std")))]
-pub type Container<A> = alloc::vec::Vec<A>;
+pub type Container<A, const N: usize = 128> = alloc::vec::Vec<A>;
 
-/// Alias for a container; either alloc::Vec, std::Vec, or heapless::Vec
+/// Alias for a container; either alloc::Vec, std::Vec, or heapless::Vec with a capacity of `N`. `N` is ignored when there's an allocator.
 #[cfg(all(not(feature = "alloc"), not(feature = "std")))]
-pub type Container<A> = heapless::Vec<A, 128>;
+pub type Container<A, const N: usize = 128> = heapless::Vec<A, N>;
 
 /// A parser error.
 #[derive(Debug, Copy, Clone)]
@@ -26,33 +26,99 @@ pub enum ParseError {
     UnexpectedEOF,
     BadUnicodeEscape,
     TypeDescriptorWithNoValue,
+    /// Nodes nested deeper than the parser's or the assembler's limit.
+    TooDeeplyNested,
+    /// A node with more arguments or properties than the parser's containers can hold.
+    TooManyEntries,
 }
 
 /// Result alias.
 pub type ParseResult<T> = core::result::Result<T, ParseError>;
 
+/// anchors and references, for reusing nodes within a document
+#[cfg(any(feature = "alloc", feature = "std"))]
+pub mod anchor;
 /// utils to assemble a series of events into [KdlNode]s
 #[cfg(any(feature = "alloc", feature = "std"))]
 pub mod assembler;
 /// AST types; [ast::KdlValue] and [ast::KdlString]
 pub mod ast;
+/// generating Rust types from a KDL Schema, e.g. in a build script
+#[cfg(feature = "schema")]
+pub mod codegen;
+/// format-preserving concrete syntax tree, for editing documents without reformatting them
+#[cfg(any(feature = "alloc", feature = "std"))]
+pub mod cst;
+/// cursors over documents kept depth-first in flat buffers, shared by [tape] and [fixed]
+mod cursor;
+/// serde Deserializer over the event stream
+#[cfg(all(feature = "serde", any(feature = "alloc", feature = "std")))]
+pub mod de;
+/// decoding typed structs from [assembler::KdlNode]s, with a derive macro behind the `derive` feature
+#[cfg(any(feature = "alloc", feature = "std"))]
+pub mod decode;
+/// structural diffs between [assembler::KdlNode] trees
+#[cfg(any(feature = "alloc", feature = "std"))]
+pub mod diff;
+/// encoding typed structs into [assembler::KdlNode]s, with a derive macro behind the `derive` feature
+#[cfg(any(feature = "alloc", feature = "std"))]
+pub mod encode;
+/// fixed-capacity documents, assembled into caller-provided pools without an allocator
+pub mod fixed;
+/// configurable formatter, writing canonical kdl from events or [assembler::KdlNode]s
+#[cfg(any(feature = "alloc", feature = "std"))]
+pub mod format;
+/// expanding `include` nodes into the documents they point to
+#[cfg(feature = "std")]
+pub mod include;
+/// interpolating variables, like `${HOME}` and `(env)"DB_URL"`, into string values
+#[cfg(any(feature = "alloc", feature = "std"))]
+pub mod interpolate;
+/// JSON-in-KDL (JiK), converting between [assembler::KdlNode]s and `serde_json` values
+#[cfg(feature = "jik")]
+pub mod jik;
+/// streaming transcoding of kdl events into JSON, without assembling nodes
+#[cfg(any(feature = "alloc", feature = "std"))]
+pub mod json;
 /// default kdl lexer
 pub mod lex;
+/// layered merging of [assembler::KdlDocument]s, e.g. configuration overlays, keeping track of where values came from
+#[cfg(any(feature = "alloc", feature = "std"))]
+pub mod merge;
 /// the kdl parser!
 pub mod parser;
+/// patches for [assembler::KdlDocument]s, written in kdl
+#[cfg(any(feature = "alloc", feature = "std"))]
+pub mod patch;
+/// KQL queries, over [assembler::KdlNode]s or streamed over parser events
+#[cfg(any(feature = "alloc", feature = "std"))]
+pub mod query;
+/// KDL Schema validation
+#[cfg(feature = "schema")]
+pub mod schema;
+/// serde Serializer producing kdl
+#[cfg(all(feature = "serde", any(feature = "alloc", feature = "std")))]
+pub mod ser;
+/// arena-backed documents, keeping every node in a few contiguous buffers and navigating them with cursors
+#[cfg(any(feature = "alloc", feature = "std"))]
+pub mod tape;
 /// utils for processing string escapes
 pub mod unescape;
+/// XML-in-KDL (XiK), transcoding between XML and kdl as a stream
+#[cfg(feature = "xik")]
+pub mod xik;
 
 use ast::*;
 
-/// An event emitted during parsing; either the opening or closing of a node.
+/// An event emitted during parsing; either the opening or closing of a node. `ENTRIES` is the capacity of it's containers, see [Container].
 #[derive(Debug, Clone)]
-pub enum KdlEvent<'input> {
-    /// Start of a node; contains it's name, properties/attributes, values, and whether ot not it has children.
+pub enum KdlEvent<'input, const ENTRIES: usize = 128> {
+    /// Start of a node; contains it's type annotation, name, properties/attributes, values, and whether or not it has children.
     NodeOpen {
+        ty: Option<&'input str>,
         name: KdlString<'input>,
-        attrs: Container<KdlProperty<'input>>,
-        values: Container<TypedValue<'input>>,
+        attrs: Container<KdlProperty<'input>, ENTRIES>,
+        values: Container<TypedValue<'input>, ENTRIES>,
         has_children: bool,
     },
     /// End of a childless node.
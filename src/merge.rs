@@ -0,0 +1,234 @@
+#[cfg(all(not(feature = "std"), feature = "alloc"))]
+use alloc::{collections::BTreeMap, string::String, vec::Vec};
+
+#[cfg(feature = "std")]
+use std::collections::BTreeMap;
+
+use crate::assembler::{KdlDocument, KdlNode};
+use crate::format;
+
+/// How a node from an overlay is combined with the matching node underneath it.
+#[derive(Debug, Copy, Clone, PartialEq, Eq)]
+pub enum Strategy {
+    /// The overlay's node replaces the one underneath entirely. Annotated `(replace)`.
+    Replace,
+    /// Properties are merged, the last one winning; arguments are replaced if the overlay's node has any; and children are merged in turn, each with it's own strategy. Annotated `(merge)`.
+    DeepMerge,
+    /// The overlay's node is added after the ones underneath, rather than combined with one. Annotated `(append)`.
+    Append,
+    /// Only properties are merged, the last one winning; the arguments and children underneath are kept. Annotated `(merge-props)`.
+    MergeProps,
+}
+
+impl Strategy {
+    /// The strategy a type annotation selects, if any.
+    pub fn from_annotation(ty: &str) -> Option<Strategy> {
+        match ty {
+            "replace" => Some(Strategy::Replace),
+            "merge" => Some(Strategy::DeepMerge),
+            "append" => Some(Strategy::Append),
+            "merge-props" => Some(Strategy::MergeProps),
+            _ => None,
+        }
+    }
+}
+
+/// Options for [merge].
+#[derive(Debug, Clone, PartialEq)]
+pub struct MergeOptions {
+    /// The strategy for nodes without one of their own.
+    pub default: Strategy,
+    /// Strategies for nodes with a given name. A strategy annotation on the overlay's node takes precedence.
+    pub by_name: BTreeMap<String, Strategy>,
+}
+
+impl Default for MergeOptions {
+    fn default() -> MergeOptions {
+        MergeOptions {
+            default: Strategy::DeepMerge,
+            by_name: BTreeMap::new(),
+        }
+    }
+}
+
+/// Which layer each part of a merged node came from, as an index into the layers given to [merge].
+#[derive(Debug, Clone, PartialEq)]
+pub struct Origin {
+    /// The layer the node itself was first added by.
+    pub layer: usize,
+    /// The layer each argument came from.
+    pub args: Vec<usize>,
+    /// The layer each property came from, by key.
+    pub props: BTreeMap<String, usize>,
+    pub children: Vec<Origin>,
+}
+
+impl Origin {
+    fn new(node: &KdlNode<'_>, layer: usize) -> Origin {
+        let mut origin = Origin {
+            layer,
+            args: Vec::new(),
+            props: BTreeMap::new(),
+            children: node
+                .children
+                .iter()
+                .map(|child| Origin::new(child, layer))
+                .collect(),
+        };
+        origin.args.resize(node.values.len(), layer);
+        origin.merge_props(node, layer);
+
+        origin
+    }
+
+    /// The layer an argument came from.
+    pub fn arg(&self, idx: usize) -> Option<usize> {
+        self.args.get(idx).copied()
+    }
+
+    /// The layer a property came from.
+    pub fn prop(&self, key: &str) -> Option<usize> {
+        self.props.get(key).copied()
+    }
+
+    fn merge_props(&mut self, node: &KdlNode<'_>, layer: usize) {
+        for attr in &node.attrs {
+            self.props.insert(format::string_contents(&attr.key), layer);
+        }
+    }
+}
+
+/// The result of merging layers: the merged document, and where everything in it came from.
+#[derive(Debug, Clone, PartialEq)]
+pub struct Merged<'a> {
+    pub document: KdlDocument<'a>,
+    /// The origin of each top-level node.
+    pub origins: Vec<Origin>,
+}
+
+impl<'a> Merged<'a> {
+    /// The origin of the node at a path of indices, as in [KdlDocument::node_at].
+    pub fn origin(&self, path: &[usize]) -> Option<&Origin> {
+        let (first, rest) = path.split_first()?;
+        rest.iter()
+            .try_fold(self.origins.get(*first)?, |origin, idx| {
+                origin.children.get(*idx)
+            })
+    }
+}
+
+/// Merges layers of documents, each one on top of the ones before it; e.g. `[base, prod, local]`.
+///
+/// Nodes are matched up by name: the nth node with a name in an overlay is combined with the nth one with that name underneath, using it's strategy. Nodes with nothing to combine with are added.
+/// Only nodes from earlier layers are underneath; ones an overlay adds are never combined with later nodes of the same overlay.
+/// Strategy annotations are removed from the merged nodes.
+pub fn merge<'a>(layers: &[KdlDocument<'a>], options: &MergeOptions) -> Merged<'a> {
+    let mut merged = Merged {
+        document: KdlDocument::default(),
+        origins: Vec::new(),
+    };
+
+    for (layer, doc) in layers.iter().enumerate() {
+        merge_children(
+            &mut merged.document.nodes,
+            &mut merged.origins,
+            &doc.nodes,
+            layer,
+            options,
+        );
+    }
+
+    merged
+}
+
+fn merge_children<'a>(
+    base: &mut Vec<KdlNode<'a>>,
+    origins: &mut Vec<Origin>,
+    overlay: &[KdlNode<'a>],
+    layer: usize,
+    options: &MergeOptions,
+) {
+    // nodes added by this overlay are pushed after these, and aren't matched against
+    let underneath = base.len();
+
+    for (idx, node) in overlay.iter().enumerate() {
+        let annotated = node.ty.and_then(Strategy::from_annotation);
+        let strategy = annotated
+            .or_else(|| options.by_name.get(node.name.as_ref()).copied())
+            .unwrap_or(options.default);
+
+        let ty = if annotated.is_some() { None } else { node.ty };
+
+        // the nth node with this name pairs with the nth one underneath
+        let occurrence = overlay[..idx]
+            .iter()
+            .filter(|other| other.name == node.name)
+            .count();
+        let existing = base[..underneath]
+            .iter()
+            .enumerate()
+            .filter(|(_, other)| other.name == node.name)
+            .nth(occurrence)
+            .map(|(pos, _)| pos);
+
+        match (strategy, existing) {
+            (Strategy::Append, _) | (_, None) => {
+                origins.push(Origin::new(node, layer));
+                base.push(without_strategies(node));
+            }
+            (Strategy::Replace, Some(pos)) => {
+                origins[pos] = Origin::new(node, layer);
+                base[pos] = without_strategies(node);
+            }
+            (Strategy::MergeProps, Some(pos)) => {
+                merge_props(&mut base[pos], &mut origins[pos], node, layer);
+            }
+            (Strategy::DeepMerge, Some(pos)) => {
+                let (target, origin) = (&mut base[pos], &mut origins[pos]);
+                merge_props(target, origin, node, layer);
+
+                if ty.is_some() {
+                    target.ty = ty;
+                }
+
+                if !node.values.is_empty() {
+                    target.values = node.values.clone();
+                    origin.args.clear();
+                    origin.args.resize(target.values.len(), layer);
+                }
+
+                merge_children(
+                    &mut target.children,
+                    &mut origin.children,
+                    &node.children,
+                    layer,
+                    options,
+                );
+            }
+        }
+    }
+}
+
+fn merge_props<'a>(
+    target: &mut KdlNode<'a>,
+    origin: &mut Origin,
+    node: &KdlNode<'a>,
+    layer: usize,
+) {
+    for attr in &node.attrs {
+        target.set_prop(attr.key, attr.value);
+    }
+
+    origin.merge_props(node, layer);
+}
+
+/// Copies a node, removing strategy annotations from it and all it's children.
+fn without_strategies<'a>(node: &KdlNode<'a>) -> KdlNode<'a> {
+    KdlNode {
+        ty: node.ty.filter(|ty| Strategy::from_annotation(ty).is_none()),
+        name: node.name.clone(),
+        attrs: node.attrs.clone(),
+        values: node.values.clone(),
+        children: node.children.iter().map(without_strategies).collect(),
+    }
+}
@@ -26,6 +26,19 @@ macro_rules! peek {
 }
 
 macro_rules! next_if {
+    // before the catch-all pattern below, which would otherwise bind `KdlValues` as a name and match anything
+    ($parser:expr, KdlValues) => {
+        next_if!(
+            $parser,
+            Token::Integer(_)
+                | Token::StringWithEscapes(_)
+                | Token::StringWithNoEscapes(_)
+                | Token::Float(_)
+                | Token::True
+                | Token::False
+                | Token::Null
+        )
+    };
     ($parser:expr, $token_kind:pat) => {
         #[allow(unreachable_patterns)]
         {
@@ -47,18 +60,6 @@ macro_rules! next_if {
                 _ => unreachable!(),
             })
     };
-    ($parser:expr, KdlValues) => {
-        next_if!(
-            $parser,
-            Token::Integer(_)
-                | Token::StringWithEscapes(_)
-                | Token::StringWithNoEscapes(_)
-                | Token::Float(_)
-                | Token::True
-                | Token::False
-                | Token::Null
-        )
-    };
     (ret IdentOrStr; $parser:expr) => {
         $parser
             .inner
@@ -103,10 +104,19 @@ macro_rules! slash_dash {
 }
 
 /// KDL parser! Acts as an iterator over [KdlEvent]s.
-pub struct Parser<'input, T: Iterator<Item = Token<'input>>> {
+///
+/// `ENTRIES` is how many arguments, and how many properties, a node can have, and `DEPTH` is how deeply nodes can be nested.
+/// Both only apply without an allocator (see [Container]), where going over them is a [ParseError::TooManyEntries] or [ParseError::TooDeeplyNested];
+/// with one, nodes can have as many entries and nest as deeply as memory allows.
+pub struct Parser<
+    'input,
+    T: Iterator<Item = Token<'input>>,
+    const ENTRIES: usize = 128,
+    const DEPTH: usize = 256,
+> {
     inner: core::iter::Peekable<T>,
-    nodes_to_close: heapless::Deque<KdlString<'input>, 256>,
-    bracketed_nodes_to_close: heapless::Deque<KdlString<'input>, 256>,
+    nodes_to_close: Container<KdlString<'input>, DEPTH>,
+    bracketed_nodes_to_close: Container<KdlString<'input>, DEPTH>,
 }
 
 impl<'input> Parser<'input, logos::Lexer<'input, Token<'input>>> {
@@ -114,8 +124,8 @@ impl<'input> Parser<'input, logos::Lexer<'input, Token<'input>>> {
     pub fn from_str(to_parse: &'input str) -> Parser<'input, logos::Lexer<'input, Token<'input>>> {
         Parser {
             inner: Token::lexer(to_parse).peekable(),
-            nodes_to_close: heapless::Deque::new(),
-            bracketed_nodes_to_close: heapless::Deque::new(),
+            nodes_to_close: Container::new(),
+            bracketed_nodes_to_close: Container::new(),
         }
     }
 }
@@ -123,19 +133,40 @@ impl<'input> Parser<'input, logos::Lexer<'input, Token<'input>>> {
 impl<'input, T: Iterator<Item = Token<'input>>> Parser<'input, T> {
     /// Build a parser from a lexer / some source of tokens.
     pub fn new(inner: T) -> Parser<'input, T> {
+        Parser::sized(inner)
+    }
+}
+
+impl<'input, const ENTRIES: usize, const DEPTH: usize>
+    Parser<'input, logos::Lexer<'input, Token<'input>>, ENTRIES, DEPTH>
+{
+    /// Builds a parser from an str, using the default lexer, with the `ENTRIES` and `DEPTH` given as const generics rather than the defaults; e.g. `Parser::<_, 16, 8>::from_str_sized(input)`.
+    pub fn from_str_sized(
+        to_parse: &'input str,
+    ) -> Parser<'input, logos::Lexer<'input, Token<'input>>, ENTRIES, DEPTH> {
+        Parser::sized(Token::lexer(to_parse))
+    }
+}
+
+impl<'input, T: Iterator<Item = Token<'input>>, const ENTRIES: usize, const DEPTH: usize>
+    Parser<'input, T, ENTRIES, DEPTH>
+{
+    /// Build a parser from a lexer / some source of tokens, with the `ENTRIES` and `DEPTH` given as const generics rather than the defaults.
+    pub fn sized(inner: T) -> Parser<'input, T, ENTRIES, DEPTH> {
         Parser {
             inner: inner.peekable(),
-            nodes_to_close: heapless::Deque::new(),
-            bracketed_nodes_to_close: heapless::Deque::new(),
+            nodes_to_close: Container::new(),
+            bracketed_nodes_to_close: Container::new(),
         }
     }
 
-    #[allow(non_snake_case, unused_variables, unused_must_use)]
-    fn node_open(&mut self) -> ParseResult<KdlEvent<'input>> {
+    #[allow(non_snake_case, unused_variables)]
+    fn node_open(&mut self) -> ParseResult<KdlEvent<'input, ENTRIES>> {
+        let ty = next_if!(ret ty; self, Token::TyDescriptor(_), Token::TyDescriptor(ty));
         let name = next_if!(ret IdentOrStr; self).ok_or(ParseError::NotANode)?;
 
-        let mut attrs: Container<KdlProperty<'input>> = Container::new();
-        let mut values: Container<TypedValue<'input>> = Container::new();
+        let mut attrs: Container<KdlProperty<'input>, ENTRIES> = Container::new();
+        let mut values: Container<TypedValue<'input>, ENTRIES> = Container::new();
         let mut has_children = false;
         let mut slash_dashed = false;
 
@@ -144,12 +175,7 @@ impl<'input, T: Iterator<Item = Token<'input>>> Parser<'input, T> {
                 Token::BlockOpen => {
                     self.inner.next();
                     if slash_dashed {
-                        'inner: while let Some(next_one) = self.inner.next() {
-                            if next_one == Token::BlockClose {
-                                break 'inner;
-                            }
-                        }
-
+                        self.skip_block();
                     } else {
                         next_if!(self, Token::Newline);
                         has_children = true;
@@ -188,14 +214,17 @@ impl<'input, T: Iterator<Item = Token<'input>>> Parser<'input, T> {
                         let property = self.property(ident)?;
 
                         slash_dash!(slash_dashed, {
-                            attrs.push(property);
+                            push::<_, ENTRIES>(&mut attrs, property)?;
                         });
                     } else if !is_ident {
                         slash_dash!(slash_dashed, {
-                            values.push(TypedValue {
-                                ty: None,
-                                val: KdlValue::String(ident),
-                            });
+                            push::<_, ENTRIES>(
+                                &mut values,
+                                TypedValue {
+                                    ty: None,
+                                    val: KdlValue::String(ident),
+                                },
+                            )?;
                         });
                     }
                 }
@@ -209,20 +238,26 @@ impl<'input, T: Iterator<Item = Token<'input>>> Parser<'input, T> {
                         next_if!(self, KdlValues).ok_or(ParseError::TypeDescriptorWithNoValue)?;
 
                     slash_dash!(slash_dashed, {
-                        values.push(TypedValue {
-                            ty: Some(name),
-                            val: token_to_value!(val),
-                        });
+                        push::<_, ENTRIES>(
+                            &mut values,
+                            TypedValue {
+                                ty: Some(name),
+                                val: token_to_value!(val),
+                            },
+                        )?;
                     });
                 }
                 Token::Integer(_) | Token::Float(_) | Token::True | Token::False | Token::Null => {
                     let val = self.inner.next().unwrap();
 
                     slash_dash!(slash_dashed, {
-                        values.push(TypedValue {
-                            ty: None,
-                            val: token_to_value!(val),
-                        });
+                        push::<_, ENTRIES>(
+                            &mut values,
+                            TypedValue {
+                                ty: None,
+                                val: token_to_value!(val),
+                            },
+                        )?;
                     });
                 }
                 Token::SlashDash => {
@@ -235,12 +270,14 @@ impl<'input, T: Iterator<Item = Token<'input>>> Parser<'input, T> {
         }
 
         if !has_children {
-            self.nodes_to_close.push_back(name);
+            push::<_, DEPTH>(&mut self.nodes_to_close, name)
         } else {
-            self.bracketed_nodes_to_close.push_back(name);
+            push::<_, DEPTH>(&mut self.bracketed_nodes_to_close, name)
         }
+        .map_err(|_| ParseError::TooDeeplyNested)?;
 
         Ok(KdlEvent::NodeOpen {
+            ty,
             name,
             values,
             attrs,
@@ -248,6 +285,36 @@ impl<'input, T: Iterator<Item = Token<'input>>> Parser<'input, T> {
         })
     }
 
+    /// Skips the rest of a children block, including any blocks nested in it.
+    fn skip_block(&mut self) {
+        let mut depth = 1usize;
+        for token in self.inner.by_ref() {
+            match token {
+                Token::BlockOpen => depth += 1,
+                Token::BlockClose => depth -= 1,
+                _ => (),
+            }
+
+            if depth == 0 {
+                break;
+            }
+        }
+    }
+
+    /// Skips a slash-dashed node, along with it's children; no events are emitted for it.
+    fn skip_node(&mut self) -> ParseResult<()> {
+        if let KdlEvent::NodeOpen { has_children, .. } = self.node_open()? {
+            if has_children {
+                self.bracketed_nodes_to_close.pop();
+                self.skip_block();
+            } else {
+                self.nodes_to_close.pop();
+            }
+        }
+
+        Ok(())
+    }
+
     #[allow(unused_variables, non_snake_case)]
     fn property(&mut self, ident: KdlString<'input>) -> ParseResult<KdlProperty<'input>> {
         self.inner.next(); // the only invocation of this checks if we have an Equals, so it's safe to just assume that!
@@ -279,8 +346,10 @@ impl<'input, T: Iterator<Item = Token<'input>>> Parser<'input, T> {
     }
 }
 
-impl<'input, T: Iterator<Item = Token<'input>>> Iterator for Parser<'input, T> {
-    type Item = ParseResult<KdlEvent<'input>>;
+impl<'input, T: Iterator<Item = Token<'input>>, const ENTRIES: usize, const DEPTH: usize> Iterator
+    for Parser<'input, T, ENTRIES, DEPTH>
+{
+    type Item = ParseResult<KdlEvent<'input, ENTRIES>>;
 
     fn next(&mut self) -> Option<Self::Item> {
         while let Some(next) = self.inner.peek() {
@@ -288,7 +357,7 @@ impl<'input, T: Iterator<Item = Token<'input>>> Iterator for Parser<'input, T> {
                 Token::BlockClose => {
                     self.inner.next();
 
-                    if let Some(to_close) = self.bracketed_nodes_to_close.pop_back() {
+                    if let Some(to_close) = self.bracketed_nodes_to_close.pop() {
                         return Some(Ok(KdlEvent::BracketedNodeClose(to_close)));
                     } else {
                         return Some(Err(ParseError::MismatchedNodeClosing));
@@ -297,7 +366,7 @@ impl<'input, T: Iterator<Item = Token<'input>>> Iterator for Parser<'input, T> {
                 Token::Semicolon => {
                     self.inner.next();
 
-                    if let Some(to_close) = self.nodes_to_close.pop_back() {
+                    if let Some(to_close) = self.nodes_to_close.pop() {
                         return Some(Ok(KdlEvent::NodeClose(to_close)));
                     } else {
                         return Some(Err(ParseError::MismatchedNodeClosing));
@@ -306,7 +375,7 @@ impl<'input, T: Iterator<Item = Token<'input>>> Iterator for Parser<'input, T> {
                 Token::Newline => {
                     self.inner.next();
 
-                    if let Some(to_close) = self.nodes_to_close.pop_back() {
+                    if let Some(to_close) = self.nodes_to_close.pop() {
                         return Some(Ok(KdlEvent::NodeClose(to_close)));
                     } else {
                         continue;
@@ -315,7 +384,7 @@ impl<'input, T: Iterator<Item = Token<'input>>> Iterator for Parser<'input, T> {
                 Token::SlashDash => {
                     self.inner.next();
 
-                    if let Err(e) = self.node_open() {
+                    if let Err(e) = self.skip_node() {
                         return Some(Err(e));
                     } else {
                         continue;
@@ -326,7 +395,23 @@ impl<'input, T: Iterator<Item = Token<'input>>> Iterator for Parser<'input, T> {
         }
 
         self.nodes_to_close
-            .pop_back()
+            .pop()
             .map(|to_close| Ok(KdlEvent::NodeClose(to_close)))
     }
 }
+
+/// Pushes onto a container, failing with [ParseError::TooManyEntries] if it's a full heapless::Vec.
+fn push<A, const N: usize>(container: &mut Container<A, N>, item: A) -> ParseResult<()> {
+    #[cfg(any(feature = "alloc", feature = "std"))]
+    {
+        container.push(item);
+        Ok(())
+    }
+
+    #[cfg(all(not(feature = "alloc"), not(feature = "std")))]
+    {
+        container
+            .push(item)
+            .map_err(|_| ParseError::TooManyEntries)
+    }
+}
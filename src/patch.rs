@@ -0,0 +1,548 @@
+#[cfg(all(not(feature = "std"), feature = "alloc"))]
+use alloc::{
+    borrow::Cow,
+    string::{String, ToString},
+    vec,
+    vec::Vec,
+};
+
+#[cfg(feature = "std")]
+use std::borrow::Cow;
+
+use core::fmt::{self, Write};
+use core::ops::Range;
+
+use crate::assembler::{self, KdlDocument, KdlNode, NameLocation};
+use crate::ast::*;
+use crate::diff::{self, Step};
+use crate::format::{self, FormatOptions};
+use crate::parser::Parser;
+use crate::query::{Query, QueryError};
+use crate::ParseError;
+
+/// Which nodes an operation applies to.
+#[derive(Debug, Clone, PartialEq)]
+pub enum Target {
+    /// A path of steps from the top level, as written by [diff::Diff], e.g. `server > route[1]`. An empty path is the document itself.
+    Path(Vec<Step>),
+    /// Every node matching a KQL query.
+    Select(String),
+}
+
+/// A change to make to each targeted node.
+#[derive(Debug, Clone, PartialEq)]
+pub enum Operation<'a> {
+    /// Sets a property, as [KdlNode::set_prop] does.
+    SetProp {
+        key: KdlString<'a>,
+        value: TypedValue<'a>,
+    },
+    RemoveProp {
+        key: KdlString<'a>,
+    },
+    ReplaceArg {
+        idx: usize,
+        value: TypedValue<'a>,
+    },
+    InsertArg {
+        idx: usize,
+        value: TypedValue<'a>,
+    },
+    RemoveArg {
+        idx: usize,
+    },
+    /// Inserts nodes before the `idx`th child, or after all of them if there's no index.
+    InsertChild {
+        idx: Option<usize>,
+        nodes: Vec<KdlNode<'a>>,
+    },
+    RemoveNode,
+    Rename(Cow<'a, str>),
+    SetType(Option<&'a str>),
+}
+
+/// An operation, and the nodes it applies to.
+#[derive(Debug, Clone)]
+pub struct PatchOp<'a> {
+    pub target: Target,
+    pub operation: Operation<'a>,
+    /// Where the operation's node name was, if it was parsed.
+    location: Option<NameLocation>,
+}
+
+impl<'a> PatchOp<'a> {
+    pub fn new(target: Target, operation: Operation<'a>) -> PatchOp<'a> {
+        PatchOp {
+            target,
+            operation,
+            location: None,
+        }
+    }
+}
+
+/// Operations are equal whatever they were parsed from.
+impl<'a> PartialEq for PatchOp<'a> {
+    fn eq(&self, other: &Self) -> bool {
+        self.target == other.target && self.operation == other.operation
+    }
+}
+
+/// A list of operations, applied in order. Patches are themselves kdl documents, with a node per operation; the first argument is the target, a path or a `(kql)` query:
+///
+/// ```kdl
+/// set-prop "server" "workers" 8
+/// remove-prop "server" "timeout"
+/// replace-arg "server" 1 9090
+/// insert-arg "server" 0 "0.0.0.0"
+/// remove-arg "server" 2
+/// insert-child "server" 0 {
+///     route "/health"
+/// }
+/// remove-node (kql)"server > route[val() = \"/old\"]"
+/// rename "server > tls" "ssl"
+/// set-type "server" "http"
+/// ```
+///
+/// `insert-child` appends it's children when there's no index, and with an empty path, inserts top-level nodes. `set-type` removes the annotation when given `null`.
+#[derive(Debug, Clone, PartialEq, Default)]
+pub struct Patch<'a> {
+    pub ops: Vec<PatchOp<'a>>,
+}
+
+/// What went wrong while reading or applying a patch.
+#[derive(Debug, Clone)]
+pub enum PatchErrorKind {
+    Parse(ParseError),
+    Query(QueryError),
+    UnknownOperation(String),
+    /// An operation with the wrong arguments; contains how it's used.
+    Usage(&'static str),
+    /// A path that isn't well-formed.
+    InvalidPath(String),
+    /// A path that doesn't lead to a node, or a query that didn't match any.
+    NoMatch,
+    /// An operation that needs a node, targeting the document itself.
+    NotANode,
+    MissingArgument(usize),
+    MissingProperty(String),
+    /// An index past the end of the arguments or children.
+    OutOfRange(usize),
+}
+
+/// An error reading or applying a patch, with the operation it happened at.
+#[derive(Debug, Clone)]
+pub struct PatchError {
+    pub kind: PatchErrorKind,
+    /// The index of the failing operation; `None` if the patch couldn't be parsed at all.
+    pub operation: Option<usize>,
+    location: Option<NameLocation>,
+}
+
+impl PatchError {
+    /// The byte range of the failing operation's name within the patch it was parsed from, if it can be found; see [crate::decode::DecodeError::span_in].
+    pub fn span_in(&self, source: &str) -> Option<Range<usize>> {
+        self.location?.span_in(source)
+    }
+}
+
+impl fmt::Display for PatchErrorKind {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            PatchErrorKind::Parse(e) => write!(f, "parse error: {:?}", e),
+            PatchErrorKind::Query(e) => write!(f, "invalid query: {}", e),
+            PatchErrorKind::UnknownOperation(name) => write!(f, "unknown operation `{}`", name),
+            PatchErrorKind::Usage(usage) => write!(f, "expected `{}`", usage),
+            PatchErrorKind::InvalidPath(path) => write!(f, "invalid path `{}`", path),
+            PatchErrorKind::NoMatch => f.write_str("no node matches the target"),
+            PatchErrorKind::NotANode => {
+                f.write_str("the document itself can't be changed this way")
+            }
+            PatchErrorKind::MissingArgument(idx) => write!(f, "missing argument #{}", idx),
+            PatchErrorKind::MissingProperty(key) => write!(f, "missing property `{}`", key),
+            PatchErrorKind::OutOfRange(idx) => write!(f, "index {} is out of range", idx),
+        }
+    }
+}
+
+impl fmt::Display for PatchError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        if let Some(operation) = self.operation {
+            write!(f, "operation #{}: ", operation)?;
+        }
+
+        write!(f, "{}", self.kind)
+    }
+}
+
+#[cfg(feature = "std")]
+impl std::error::Error for PatchError {}
+
+impl<'a> Patch<'a> {
+    /// Parses a patch. Errors can be located in `input` with [PatchError::span_in].
+    pub fn parse(input: &'a str) -> Result<Patch<'a>, PatchError> {
+        let nodes =
+            assembler::parse_document(&mut Parser::from_str(input)).map_err(|e| PatchError {
+                kind: PatchErrorKind::Parse(e),
+                operation: None,
+                location: None,
+            })?;
+
+        Patch::from_nodes(&nodes)
+    }
+
+    /// Reads a patch from assembled nodes, one per operation.
+    pub fn from_nodes(nodes: &[KdlNode<'a>]) -> Result<Patch<'a>, PatchError> {
+        let ops = nodes
+            .iter()
+            .enumerate()
+            .map(|(idx, node)| {
+                read_op(node).map_err(|kind| PatchError {
+                    kind,
+                    operation: Some(idx),
+                    location: NameLocation::of(node),
+                })
+            })
+            .collect::<Result<_, _>>()?;
+
+        Ok(Patch { ops })
+    }
+
+    /// Applies every operation in order. If any of them fails, the document is left as it was.
+    pub fn apply(&self, doc: &mut KdlDocument<'a>) -> Result<(), PatchError> {
+        let mut patched = doc.clone();
+
+        for (idx, op) in self.ops.iter().enumerate() {
+            apply_op(op, &mut patched).map_err(|kind| PatchError {
+                kind,
+                operation: Some(idx),
+                location: op.location,
+            })?;
+        }
+
+        *doc = patched;
+        Ok(())
+    }
+}
+
+/// Parses a patch and applies it to a document.
+pub fn apply<'a>(doc: &mut KdlDocument<'a>, patch: &'a str) -> Result<(), PatchError> {
+    Patch::parse(patch)?.apply(doc)
+}
+
+fn read_op<'a>(node: &KdlNode<'a>) -> Result<PatchOp<'a>, PatchErrorKind> {
+    // the usage of the operation, and how many arguments it takes after the target
+    let (usage, count): (&'static str, &[usize]) = match node.name.as_ref() {
+        "set-prop" => ("set-prop <target> <key> <value>", &[2]),
+        "remove-prop" => ("remove-prop <target> <key>", &[1]),
+        "replace-arg" => ("replace-arg <target> <index> <value>", &[2]),
+        "insert-arg" => ("insert-arg <target> <index> <value>", &[2]),
+        "remove-arg" => ("remove-arg <target> <index>", &[1]),
+        "insert-child" => ("insert-child <target> [index] { children }", &[0, 1]),
+        "remove-node" => ("remove-node <target>", &[0]),
+        "rename" => ("rename <target> <name>", &[1]),
+        "set-type" => ("set-type <target> <type or null>", &[1]),
+        name => return Err(PatchErrorKind::UnknownOperation(name.to_string())),
+    };
+
+    let usage = PatchErrorKind::Usage(usage);
+    if node.values.is_empty()
+        || !count.contains(&(node.values.len() - 1))
+        || !node.attrs.is_empty()
+        || (!node.children.is_empty() && node.name != "insert-child")
+    {
+        return Err(usage);
+    }
+
+    let target = match (node.values[0].ty, node.values[0].val) {
+        (Some("kql"), KdlValue::String(query)) => {
+            let query = query.unescape().map_err(PatchErrorKind::Parse)?;
+            Query::parse(&query).map_err(PatchErrorKind::Query)?;
+            Target::Select(query.into_owned())
+        }
+        (None, KdlValue::String(path)) => {
+            let path = path.unescape().map_err(PatchErrorKind::Parse)?;
+            Target::Path(parse_path(&path).ok_or_else(|| PatchErrorKind::InvalidPath(path.into()))?)
+        }
+        _ => return Err(usage),
+    };
+
+    let string = |idx: usize| match node.values[idx].val {
+        KdlValue::String(s) => Ok(s),
+        _ => Err(usage.clone()),
+    };
+    let index = |idx: usize| match node.values[idx].val {
+        KdlValue::Integer(i) => usize::try_from(i).map_err(|_| usage.clone()),
+        _ => Err(usage.clone()),
+    };
+
+    let operation = match node.name.as_ref() {
+        "set-prop" => Operation::SetProp {
+            key: string(1)?,
+            value: node.values[2],
+        },
+        "remove-prop" => Operation::RemoveProp { key: string(1)? },
+        "replace-arg" => Operation::ReplaceArg {
+            idx: index(1)?,
+            value: node.values[2],
+        },
+        "insert-arg" => Operation::InsertArg {
+            idx: index(1)?,
+            value: node.values[2],
+        },
+        "remove-arg" => Operation::RemoveArg { idx: index(1)? },
+        "insert-child" => Operation::InsertChild {
+            idx: (node.values.len() > 1).then(|| index(1)).transpose()?,
+            nodes: node.children.clone(),
+        },
+        "remove-node" => Operation::RemoveNode,
+        "rename" => Operation::Rename(string(1)?.unescape().map_err(PatchErrorKind::Parse)?),
+        _ => Operation::SetType(match node.values[1].val {
+            KdlValue::Null => None,
+            KdlValue::String(KdlString::Escapeless(ty)) => Some(ty),
+            _ => return Err(usage),
+        }),
+    };
+
+    Ok(PatchOp {
+        target,
+        operation,
+        location: NameLocation::of(node),
+    })
+}
+
+fn apply_op<'a>(op: &PatchOp<'a>, doc: &mut KdlDocument<'a>) -> Result<(), PatchErrorKind> {
+    let paths = match &op.target {
+        Target::Path(steps) => vec![resolve(&doc.nodes, steps).ok_or(PatchErrorKind::NoMatch)?],
+        Target::Select(query) => Query::parse(query)
+            .map_err(PatchErrorKind::Query)?
+            .select_paths(&doc.nodes),
+    };
+
+    if paths.is_empty() {
+        return Err(PatchErrorKind::NoMatch);
+    }
+
+    // later nodes first, so changing one doesn't move the ones before it
+    for path in paths.iter().rev() {
+        apply_at(&op.operation, doc, path)?;
+    }
+
+    Ok(())
+}
+
+/// Follows a path of steps to a path of indices.
+fn resolve(mut nodes: &[KdlNode<'_>], steps: &[Step]) -> Option<Vec<usize>> {
+    let mut path = Vec::with_capacity(steps.len());
+
+    for step in steps {
+        let (idx, node) = nodes
+            .iter()
+            .enumerate()
+            .filter(|(_, node)| node.name == step.name)
+            .nth(step.index)?;
+
+        path.push(idx);
+        nodes = &node.children;
+    }
+
+    Some(path)
+}
+
+fn apply_at<'a>(
+    operation: &Operation<'a>,
+    doc: &mut KdlDocument<'a>,
+    path: &[usize],
+) -> Result<(), PatchErrorKind> {
+    if let Operation::InsertChild { idx, nodes } = operation {
+        let children = match path {
+            [] => &mut doc.nodes,
+            _ => {
+                &mut doc
+                    .node_at_mut(path)
+                    .ok_or(PatchErrorKind::NoMatch)?
+                    .children
+            }
+        };
+
+        let idx = idx.unwrap_or(children.len());
+        if idx > children.len() {
+            return Err(PatchErrorKind::OutOfRange(idx));
+        }
+
+        children.splice(idx..idx, nodes.iter().cloned());
+        return Ok(());
+    }
+
+    let (last, parent) = path.split_last().ok_or(PatchErrorKind::NotANode)?;
+    if let Operation::RemoveNode = operation {
+        let removed = match parent {
+            [] => doc.remove_node(*last),
+            _ => doc
+                .node_at_mut(parent)
+                .and_then(|parent| parent.remove_child(*last)),
+        };
+
+        return removed.map(drop).ok_or(PatchErrorKind::NoMatch);
+    }
+
+    let node = doc.node_at_mut(path).ok_or(PatchErrorKind::NoMatch)?;
+    match operation {
+        Operation::SetProp { key, value } => {
+            node.set_prop(*key, *value);
+        }
+        Operation::RemoveProp { key } => {
+            node.remove_prop(*key)
+                .ok_or_else(|| PatchErrorKind::MissingProperty(format::string_contents(key)))?;
+        }
+        Operation::ReplaceArg { idx, value } => {
+            node.set_arg(*idx, *value)
+                .ok_or(PatchErrorKind::MissingArgument(*idx))?;
+        }
+        Operation::InsertArg { idx, value } => {
+            if *idx > node.values.len() {
+                return Err(PatchErrorKind::OutOfRange(*idx));
+            }
+            node.insert_arg(*idx, *value);
+        }
+        Operation::RemoveArg { idx } => {
+            node.remove_arg(*idx)
+                .ok_or(PatchErrorKind::MissingArgument(*idx))?;
+        }
+        Operation::Rename(name) => node.rename(name.clone()),
+        Operation::SetType(ty) => node.ty = *ty,
+        Operation::InsertChild { .. } | Operation::RemoveNode => unreachable!(),
+    }
+
+    Ok(())
+}
+
+/// Parses a path like `server > "my route"[1]`.
+fn parse_path(path: &str) -> Option<Vec<Step>> {
+    let mut steps = Vec::new();
+    let mut rest = path.trim_start();
+
+    while !rest.is_empty() {
+        if !steps.is_empty() {
+            rest = rest.strip_prefix('>')?.trim_start();
+        }
+
+        let name;
+        if let Some(quoted) = rest.strip_prefix('"') {
+            // find the closing quote, skipping escaped characters
+            let mut escaped = false;
+            let end = quoted.find(|c| {
+                let close = c == '"' && !escaped;
+                escaped = c == '\\' && !escaped;
+                close
+            })?;
+
+            name = KdlString::Escaped(&quoted[..end])
+                .unescape()
+                .ok()?
+                .into_owned();
+            rest = &quoted[end + 1..];
+        } else {
+            let end = rest
+                .find(|c: char| c.is_whitespace() || c == '[' || c == '>')
+                .unwrap_or(rest.len());
+            if end == 0 {
+                return None;
+            }
+
+            name = rest[..end].to_string();
+            rest = &rest[end..];
+        }
+
+        let mut index = 0;
+        if let Some(bracketed) = rest.strip_prefix('[') {
+            let end = bracketed.find(']')?;
+            index = bracketed[..end].trim().parse().ok()?;
+            rest = &bracketed[end + 1..];
+        }
+
+        steps.push(Step { name, index });
+        rest = rest.trim_start();
+    }
+
+    Some(steps)
+}
+
+impl fmt::Display for Target {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        let mut buf = String::new();
+        match self {
+            Target::Path(steps) => diff::write_path(&mut buf, steps)?,
+            Target::Select(query) => {
+                f.write_str("(kql)")?;
+                buf.push_str(query);
+            }
+        }
+
+        format::write_quoted(f, &buf)
+    }
+}
+
+/// Writes the patch back out as a kdl document.
+impl<'a> fmt::Display for Patch<'a> {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        let options = FormatOptions::default();
+
+        for op in &self.ops {
+            let name = match op.operation {
+                Operation::SetProp { .. } => "set-prop",
+                Operation::RemoveProp { .. } => "remove-prop",
+                Operation::ReplaceArg { .. } => "replace-arg",
+                Operation::InsertArg { .. } => "insert-arg",
+                Operation::RemoveArg { .. } => "remove-arg",
+                Operation::InsertChild { .. } => "insert-child",
+                Operation::RemoveNode => "remove-node",
+                Operation::Rename(_) => "rename",
+                Operation::SetType(_) => "set-type",
+            };
+            write!(f, "{} {}", name, op.target)?;
+
+            match &op.operation {
+                Operation::SetProp { key, value } => {
+                    f.write_char(' ')?;
+                    format::write_string(f, key)?;
+                    f.write_char(' ')?;
+                    format::write_value(f, value, &options)?;
+                }
+                Operation::RemoveProp { key } => {
+                    f.write_char(' ')?;
+                    format::write_string(f, key)?;
+                }
+                Operation::ReplaceArg { idx, value } | Operation::InsertArg { idx, value } => {
+                    write!(f, " {} ", idx)?;
+                    format::write_value(f, value, &options)?;
+                }
+                Operation::RemoveArg { idx } => write!(f, " {}", idx)?,
+                Operation::InsertChild { idx, nodes } => {
+                    if let Some(idx) = idx {
+                        write!(f, " {}", idx)?;
+                    }
+
+                    f.write_str(" {\n")?;
+                    for line in format::format_document(nodes, &options)?.lines() {
+                        writeln!(f, "    {}", line)?;
+                    }
+                    f.write_char('}')?;
+                }
+                Operation::RemoveNode => (),
+                Operation::Rename(name) => {
+                    f.write_char(' ')?;
+                    format::write_quoted(f, name)?;
+                }
+                Operation::SetType(Some(ty)) => {
+                    f.write_char(' ')?;
+                    format::write_quoted(f, ty)?;
+                }
+                Operation::SetType(None) => f.write_str(" null")?,
+            }
+
+            f.write_char('\n')?;
+        }
+
+        Ok(())
+    }
+}
@@ -0,0 +1,886 @@
+#[cfg(all(not(feature = "std"), feature = "alloc"))]
+use alloc::{borrow::ToOwned, string::String, vec, vec::Vec};
+
+use core::fmt;
+use core::str::FromStr;
+
+use crate::assembler::{self, KdlNode};
+use crate::ast::*;
+use crate::lex::Token;
+use crate::parser::Parser;
+use crate::{KdlEvent, ParseResult};
+
+/// A compiled KQL query, e.g. `top() > server[port > 1024]`.
+///
+/// Supported syntax:
+/// - combinators: `a > b` (child), `a b` (descendant), `a + b` (next sibling), `a ~ b` (any later sibling), and `a, b` (either).
+/// - `top()`, only at the start of a selector, for the top level of the document.
+/// - node names, bare or quoted, optionally preceded by a `(type)`.
+/// - `[]` for any node, or `[accessor]` to check the accessor exists, or `[accessor op value]` to compare it.
+/// - accessors: `val()`/`val(n)` for arguments, `prop(key)` or just `key` for properties, `name()`, and `tag()`.
+/// - operators: `=`, `!=`, `>`, `<`, `>=`, `<=`, `^=` (starts with), `$=` (ends with), and `*=` (contains).
+/// - values: strings, numbers, `true`, `false`, `null`, or `(type)` to compare a value's type annotation.
+#[derive(Debug, Clone, PartialEq)]
+pub struct Query {
+    selectors: Vec<Selector>,
+}
+
+/// An error compiling a query, at a byte offset into it.
+#[derive(Debug, Clone, PartialEq)]
+pub struct QueryError {
+    pub position: usize,
+    pub kind: QueryErrorKind,
+}
+
+#[derive(Debug, Clone, PartialEq)]
+pub enum QueryErrorKind {
+    UnexpectedEnd,
+    UnexpectedChar(char),
+    UnterminatedString,
+    BadEscape,
+    BadNumber,
+    UnknownAccessor(String),
+    /// `top()` anywhere other than the start of a selector.
+    MisplacedTop,
+    /// A selector with nothing in it, e.g. the second half of `a, `.
+    EmptySelector,
+    /// A sibling combinator in a query being streamed; siblings aren't kept around while streaming.
+    NotStreamable,
+}
+
+impl fmt::Display for QueryError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match &self.kind {
+            QueryErrorKind::UnexpectedEnd => f.write_str("unexpected end of query")?,
+            QueryErrorKind::UnexpectedChar(c) => write!(f, "unexpected `{}`", c)?,
+            QueryErrorKind::UnterminatedString => f.write_str("unterminated string")?,
+            QueryErrorKind::BadEscape => f.write_str("invalid escape in string")?,
+            QueryErrorKind::BadNumber => f.write_str("invalid number")?,
+            QueryErrorKind::UnknownAccessor(name) => write!(f, "unknown accessor `{}()`", name)?,
+            QueryErrorKind::MisplacedTop => {
+                f.write_str("`top()` can only be at the start of a selector")?
+            }
+            QueryErrorKind::EmptySelector => f.write_str("empty selector")?,
+            QueryErrorKind::NotStreamable => {
+                f.write_str("sibling combinators can't be used while streaming")?
+            }
+        }
+
+        write!(f, " at {}", self.position)
+    }
+}
+
+#[cfg(feature = "std")]
+impl std::error::Error for QueryError {}
+
+#[derive(Debug, Copy, Clone, PartialEq)]
+pub(crate) enum Combinator {
+    Child,
+    Descendant,
+    Next,
+    Sibling,
+}
+
+#[derive(Debug, Clone, PartialEq)]
+pub(crate) struct Selector {
+    pub(crate) compounds: Vec<Compound>,
+    /// `combinators[i]` sits between `compounds[i]` and `compounds[i + 1]`.
+    pub(crate) combinators: Vec<Combinator>,
+    /// Where each combinator was in the query, for errors.
+    offsets: Vec<usize>,
+}
+
+/// Everything a single node has to match, e.g. `(ty)name[a][b = 1]`.
+#[derive(Debug, Clone, PartialEq, Default)]
+pub(crate) struct Compound {
+    /// Matches the document root rather than a node.
+    pub(crate) top: bool,
+    ty: Option<String>,
+    name: Option<String>,
+    matchers: Vec<Matcher>,
+}
+
+#[derive(Debug, Clone, PartialEq)]
+enum Accessor {
+    Val(usize),
+    Prop(String),
+    Name,
+    Tag,
+}
+
+#[derive(Debug, Copy, Clone, PartialEq)]
+enum Op {
+    Eq,
+    NotEq,
+    Gt,
+    Lt,
+    GtEq,
+    LtEq,
+    StartsWith,
+    EndsWith,
+    Contains,
+}
+
+#[derive(Debug, Clone, PartialEq)]
+enum Literal {
+    String(String),
+    Integer(i64),
+    Float(f64),
+    Bool(bool),
+    Null,
+    Type(String),
+}
+
+/// A single `[...]`. An empty one has no accessor and matches anything.
+#[derive(Debug, Clone, PartialEq)]
+struct Matcher {
+    accessor: Option<Accessor>,
+    comparison: Option<(Op, Literal)>,
+}
+
+/// What an accessor found on a node.
+enum Found<'n> {
+    Value(&'n TypedValue<'n>),
+    Str(&'n str),
+}
+
+impl Query {
+    /// Compiles a query.
+    pub fn parse(query: &str) -> Result<Query, QueryError> {
+        QueryParser { src: query, pos: 0 }.query()
+    }
+
+    /// Returns every node in the document matching the query, in document order.
+    pub fn select<'n, 'a>(&self, nodes: &'n [KdlNode<'a>]) -> Vec<&'n KdlNode<'a>> {
+        let mut out = Vec::new();
+        self.walk(nodes, &mut Vec::new(), &mut |node, _| out.push(node));
+        out
+    }
+
+    /// Returns the path of indices to every node matching the query, in document order; see [assembler::KdlDocument::node_at].
+    pub fn select_paths(&self, nodes: &[KdlNode<'_>]) -> Vec<Vec<usize>> {
+        let mut out = Vec::new();
+        self.walk(nodes, &mut Vec::new(), &mut |_, stack| {
+            out.push(stack.iter().map(|(_, idx)| *idx).collect())
+        });
+        out
+    }
+
+    /// Returns the first node in the document matching the query.
+    pub fn first<'n, 'a>(&self, nodes: &'n [KdlNode<'a>]) -> Option<&'n KdlNode<'a>> {
+        self.select(nodes).into_iter().next()
+    }
+
+    /// Runs the query over a parser's events, without building the tree, yielding each matching node with it's children.
+    /// Only the ancestors of the current node are kept around, so sibling combinators (`+` and `~`) aren't supported.
+    /// Matches inside a matching node are part of it's subtree, and aren't yielded again.
+    pub fn stream<'a, T: Iterator<Item = Token<'a>>>(
+        &self,
+        parser: Parser<'a, T>,
+    ) -> Result<QueryStream<'_, 'a, T>, QueryError> {
+        for selector in &self.selectors {
+            for (combinator, offset) in selector.combinators.iter().zip(&selector.offsets) {
+                if matches!(combinator, Combinator::Next | Combinator::Sibling) {
+                    return Err(QueryError {
+                        position: *offset,
+                        kind: QueryErrorKind::NotStreamable,
+                    });
+                }
+            }
+        }
+
+        // the document root is where selectors starting with `top()` have matched their first compound.
+        let root: Vec<(usize, usize)> = self
+            .selectors
+            .iter()
+            .enumerate()
+            .filter(|(_, selector)| selector.compounds[0].top)
+            .map(|(idx, _)| (idx, 0))
+            .collect();
+
+        Ok(QueryStream {
+            query: self,
+            parser,
+            open: vec![Open {
+                matched: root.clone(),
+                reachable: root,
+            }],
+        })
+    }
+
+    fn walk<'n, 'a>(
+        &self,
+        siblings: &'n [KdlNode<'a>],
+        stack: &mut Vec<(&'n [KdlNode<'a>], usize)>,
+        found: &mut impl FnMut(&'n KdlNode<'a>, &[(&'n [KdlNode<'a>], usize)]),
+    ) {
+        for (idx, node) in siblings.iter().enumerate() {
+            stack.push((siblings, idx));
+
+            if self
+                .selectors
+                .iter()
+                .any(|selector| selector.matches_tree(selector.compounds.len() - 1, stack))
+            {
+                found(node, stack);
+            }
+
+            self.walk(&node.children, stack, found);
+            stack.pop();
+        }
+    }
+}
+
+impl FromStr for Query {
+    type Err = QueryError;
+
+    fn from_str(query: &str) -> Result<Query, QueryError> {
+        Query::parse(query)
+    }
+}
+
+/// Compiles a query and runs it against a document.
+pub fn select<'n, 'a>(
+    nodes: &'n [KdlNode<'a>],
+    query: &str,
+) -> Result<Vec<&'n KdlNode<'a>>, QueryError> {
+    Ok(Query::parse(query)?.select(nodes))
+}
+
+/// Matching nodes from a [Query] run over a parser's events; see [Query::stream].
+pub struct QueryStream<'q, 'a, T: Iterator<Item = Token<'a>>> {
+    query: &'q Query,
+    parser: Parser<'a, T>,
+    /// State for each node whose children block is currently open, outermost first.
+    open: Vec<Open>,
+}
+
+/// Which compounds of which selectors have matched, as `(selector, compound)` pairs.
+struct Open {
+    /// Matched by this node itself; it's children can continue these with `>`.
+    matched: Vec<(usize, usize)>,
+    /// Matched by this node or any of it's ancestors; it's descendants can continue these with ` `.
+    reachable: Vec<(usize, usize)>,
+}
+
+impl<'q, 'a, T: Iterator<Item = Token<'a>>> QueryStream<'q, 'a, T> {
+    /// Consumes the stream, returning the parser.
+    pub fn into_inner(self) -> Parser<'a, T> {
+        self.parser
+    }
+
+    fn matched(
+        &self,
+        ty: Option<&str>,
+        name: &str,
+        values: &[TypedValue<'_>],
+        attrs: &[KdlProperty<'_>],
+    ) -> Vec<(usize, usize)> {
+        let parent = self.open.last().expect("the root is never closed");
+        let mut matched = Vec::new();
+
+        for (s, selector) in self.query.selectors.iter().enumerate() {
+            for (k, compound) in selector.compounds.iter().enumerate() {
+                let continues = match k.checked_sub(1) {
+                    None => true,
+                    Some(prev) => match selector.combinators[prev] {
+                        Combinator::Child => parent.matched.contains(&(s, prev)),
+                        _ => parent.reachable.contains(&(s, prev)),
+                    },
+                };
+
+                if continues && compound.matches_parts(ty, name, values, attrs) {
+                    matched.push((s, k));
+                }
+            }
+        }
+
+        matched
+    }
+}
+
+impl<'q, 'a, T: Iterator<Item = Token<'a>>> Iterator for QueryStream<'q, 'a, T> {
+    type Item = ParseResult<KdlNode<'a>>;
+
+    fn next(&mut self) -> Option<Self::Item> {
+        loop {
+            let (ty, name, attrs, values, has_children) = match self.parser.next()? {
+                Err(e) => return Some(Err(e)),
+                Ok(KdlEvent::NodeOpen {
+                    ty,
+                    name,
+                    attrs,
+                    values,
+                    has_children,
+                }) => (ty, name, attrs, values, has_children),
+                Ok(KdlEvent::NodeClose(_)) => continue,
+                Ok(KdlEvent::BracketedNodeClose(_)) => {
+                    if self.open.len() > 1 {
+                        self.open.pop();
+                    }
+                    continue;
+                }
+            };
+
+            let name = match name.unescape() {
+                Ok(name) => name,
+                Err(e) => return Some(Err(e)),
+            };
+
+            let matched = self.matched(ty, &name, &values, &attrs);
+            let is_match = matched
+                .iter()
+                .any(|&(s, k)| k == self.query.selectors[s].compounds.len() - 1);
+
+            if is_match {
+                let mut node = KdlNode {
+                    ty,
+                    name,
+                    attrs,
+                    values,
+                    children: Vec::new(),
+                };
+
+                if has_children {
+                    if let Err(e) = assembler::add_children(&mut self.parser, &mut node.children) {
+                        return Some(Err(e));
+                    }
+                }
+
+                return Some(Ok(node));
+            }
+
+            if has_children {
+                let parent = self.open.last().expect("the root is never closed");
+                let mut reachable = parent.reachable.clone();
+                reachable.extend(matched.iter().copied());
+
+                self.open.push(Open { matched, reachable });
+            }
+        }
+    }
+}
+
+impl Selector {
+    /// Checks compound `k` against the node at the top of `stack`, then the rest of the selector against it's surroundings.
+    /// An empty stack is the document root.
+    fn matches_tree(&self, k: usize, stack: &[(&[KdlNode<'_>], usize)]) -> bool {
+        let compound = &self.compounds[k];
+
+        let (siblings, idx) = match stack.last() {
+            Some(&position) => position,
+            None => return compound.top,
+        };
+
+        if !compound.matches_node(&siblings[idx]) {
+            return false;
+        }
+
+        if k == 0 {
+            return true;
+        }
+
+        let parents = &stack[..stack.len() - 1];
+        match self.combinators[k - 1] {
+            Combinator::Child => self.matches_tree(k - 1, parents),
+            Combinator::Descendant => {
+                (0..stack.len()).any(|depth| self.matches_tree(k - 1, &stack[..depth]))
+            }
+            Combinator::Next => idx > 0 && self.matches_sibling(k - 1, stack, idx - 1),
+            Combinator::Sibling => (0..idx).any(|prev| self.matches_sibling(k - 1, stack, prev)),
+        }
+    }
+
+    fn matches_sibling(&self, k: usize, stack: &[(&[KdlNode<'_>], usize)], idx: usize) -> bool {
+        let mut moved = stack.to_vec();
+        let last = moved.len() - 1;
+        moved[last].1 = idx;
+        self.matches_tree(k, &moved)
+    }
+}
+
+impl Compound {
+    pub(crate) fn matches_node(&self, node: &KdlNode<'_>) -> bool {
+        self.matches_parts(node.ty, &node.name, &node.values, &node.attrs)
+    }
+
+    /// Matches the parts of a node, so unassembled nodes can be matched too.
+    pub(crate) fn matches_parts(
+        &self,
+        ty: Option<&str>,
+        name: &str,
+        values: &[TypedValue<'_>],
+        attrs: &[KdlProperty<'_>],
+    ) -> bool {
+        if self.top {
+            return false;
+        }
+
+        if let Some(expected) = &self.name {
+            if expected != name {
+                return false;
+            }
+        }
+
+        if let Some(expected) = &self.ty {
+            if ty != Some(expected.as_str()) {
+                return false;
+            }
+        }
+
+        self.matchers
+            .iter()
+            .all(|matcher| matcher.matches(ty, name, values, attrs))
+    }
+}
+
+impl Matcher {
+    fn matches(
+        &self,
+        ty: Option<&str>,
+        name: &str,
+        values: &[TypedValue<'_>],
+        attrs: &[KdlProperty<'_>],
+    ) -> bool {
+        let accessor = match &self.accessor {
+            Some(accessor) => accessor,
+            None => return true,
+        };
+
+        let found = match accessor {
+            Accessor::Val(idx) => values.get(*idx).map(Found::Value),
+            Accessor::Prop(key) => attrs
+                .iter()
+                .rev()
+                .find(|attr| attr.key == KdlString::Escapeless(key))
+                .map(|attr| Found::Value(&attr.value)),
+            Accessor::Name => Some(Found::Str(name)),
+            Accessor::Tag => ty.map(Found::Str),
+        };
+
+        match (found, &self.comparison) {
+            (None, _) => false,
+            (Some(_), None) => true,
+            (Some(found), Some((op, literal))) => compare(&found, *op, literal),
+        }
+    }
+}
+
+fn compare(found: &Found<'_>, op: Op, literal: &Literal) -> bool {
+    let value = match found {
+        Found::Str(s) => KdlValue::String(KdlString::Escapeless(s)),
+        Found::Value(value) => {
+            if let Literal::Type(ty) = literal {
+                return match op {
+                    Op::Eq => value.ty == Some(ty.as_str()),
+                    Op::NotEq => value.ty != Some(ty.as_str()),
+                    _ => false,
+                };
+            }
+
+            value.val
+        }
+    };
+
+    match (value, literal) {
+        (KdlValue::String(s), Literal::String(expected) | Literal::Type(expected)) => {
+            let s = match s.unescape() {
+                Ok(s) => s,
+                Err(_) => return false,
+            };
+
+            match op {
+                Op::Eq => s == expected.as_str(),
+                Op::NotEq => s != expected.as_str(),
+                Op::StartsWith => s.starts_with(expected.as_str()),
+                Op::EndsWith => s.ends_with(expected.as_str()),
+                Op::Contains => s.contains(expected.as_str()),
+                _ => false,
+            }
+        }
+        (KdlValue::Integer(a), Literal::Integer(b)) => compare_ord(a.partial_cmp(b), op),
+        (KdlValue::Integer(a), Literal::Float(b)) => compare_ord((a as f64).partial_cmp(b), op),
+        (KdlValue::Float(a), Literal::Integer(b)) => compare_ord(a.partial_cmp(&(*b as f64)), op),
+        (KdlValue::Float(a), Literal::Float(b)) => compare_ord(a.partial_cmp(b), op),
+        (KdlValue::Bool(a), Literal::Bool(b)) => match op {
+            Op::Eq => a == *b,
+            Op::NotEq => a != *b,
+            _ => false,
+        },
+        (KdlValue::Null, Literal::Null) => op == Op::Eq,
+        // values of different types are never equal
+        _ => op == Op::NotEq,
+    }
+}
+
+fn compare_ord(ordering: Option<core::cmp::Ordering>, op: Op) -> bool {
+    use core::cmp::Ordering::*;
+
+    match (ordering, op) {
+        (None, Op::NotEq) => true,
+        (None, _) => false,
+        (Some(ordering), Op::Eq) => ordering == Equal,
+        (Some(ordering), Op::NotEq) => ordering != Equal,
+        (Some(ordering), Op::Gt) => ordering == Greater,
+        (Some(ordering), Op::Lt) => ordering == Less,
+        (Some(ordering), Op::GtEq) => ordering != Less,
+        (Some(ordering), Op::LtEq) => ordering != Greater,
+        _ => false,
+    }
+}
+
+struct QueryParser<'q> {
+    src: &'q str,
+    pos: usize,
+}
+
+impl<'q> QueryParser<'q> {
+    fn query(mut self) -> Result<Query, QueryError> {
+        let mut selectors = vec![self.selector()?];
+
+        while self.eat(',') {
+            selectors.push(self.selector()?);
+        }
+
+        match self.peek() {
+            None => Ok(Query { selectors }),
+            Some(c) => Err(self.error(QueryErrorKind::UnexpectedChar(c))),
+        }
+    }
+
+    fn selector(&mut self) -> Result<Selector, QueryError> {
+        self.skip_whitespace();
+        let start = self.pos;
+
+        let mut selector = Selector {
+            compounds: vec![self.compound()?],
+            combinators: Vec::new(),
+            offsets: Vec::new(),
+        };
+
+        loop {
+            let had_whitespace = self.skip_whitespace();
+            let offset = self.pos;
+
+            let combinator = match self.peek() {
+                None | Some(',') => break,
+                Some('>') => Combinator::Child,
+                Some('+') => Combinator::Next,
+                Some('~') => Combinator::Sibling,
+                Some(_) if had_whitespace => Combinator::Descendant,
+                Some(c) => return Err(self.error(QueryErrorKind::UnexpectedChar(c))),
+            };
+
+            if combinator != Combinator::Descendant {
+                self.bump();
+                self.skip_whitespace();
+            }
+
+            let compound_start = self.pos;
+            let compound = self.compound()?;
+            if compound.top {
+                return Err(QueryError {
+                    position: compound_start,
+                    kind: QueryErrorKind::MisplacedTop,
+                });
+            }
+
+            selector.combinators.push(combinator);
+            selector.offsets.push(offset);
+            selector.compounds.push(compound);
+        }
+
+        // `top()` on it's own selects the top level.
+        if selector.compounds.len() == 1 && selector.compounds[0].top {
+            selector.combinators.push(Combinator::Child);
+            selector.offsets.push(self.pos);
+            selector.compounds.push(Compound::default());
+        }
+
+        if selector.compounds[0].top
+            && matches!(
+                selector.combinators[0],
+                Combinator::Next | Combinator::Sibling
+            )
+        {
+            return Err(QueryError {
+                position: start,
+                kind: QueryErrorKind::MisplacedTop,
+            });
+        }
+
+        Ok(selector)
+    }
+
+    fn compound(&mut self) -> Result<Compound, QueryError> {
+        let mut compound = Compound::default();
+
+        if self.src[self.pos..].starts_with("top()") {
+            self.pos += "top()".len();
+            compound.top = true;
+            return Ok(compound);
+        }
+
+        if self.eat('(') {
+            compound.ty = Some(self.identifier()?);
+            self.expect(')')?;
+        }
+
+        match self.peek() {
+            Some('"') => compound.name = Some(self.string()?),
+            Some(c) if is_identifier_char(c) => compound.name = Some(self.identifier()?),
+            _ => (),
+        }
+
+        while self.eat('[') {
+            compound.matchers.push(self.matcher()?);
+        }
+
+        if compound.ty.is_none() && compound.name.is_none() && compound.matchers.is_empty() {
+            return Err(match self.peek() {
+                None | Some(',') => self.error(QueryErrorKind::EmptySelector),
+                Some(c) => self.error(QueryErrorKind::UnexpectedChar(c)),
+            });
+        }
+
+        Ok(compound)
+    }
+
+    fn matcher(&mut self) -> Result<Matcher, QueryError> {
+        self.skip_whitespace();
+
+        if self.eat(']') {
+            return Ok(Matcher {
+                accessor: None,
+                comparison: None,
+            });
+        }
+
+        let accessor = self.accessor()?;
+        self.skip_whitespace();
+
+        let comparison = if self.eat(']') {
+            None
+        } else {
+            let op = self.op()?;
+            self.skip_whitespace();
+            let literal = self.literal()?;
+            self.skip_whitespace();
+            self.expect(']')?;
+            Some((op, literal))
+        };
+
+        Ok(Matcher {
+            accessor: Some(accessor),
+            comparison,
+        })
+    }
+
+    fn accessor(&mut self) -> Result<Accessor, QueryError> {
+        let start = self.pos;
+
+        let name = match self.peek() {
+            Some('"') => return Ok(Accessor::Prop(self.string()?)),
+            _ => self.identifier()?,
+        };
+
+        if !self.eat('(') {
+            return Ok(Accessor::Prop(name));
+        }
+
+        self.skip_whitespace();
+        let accessor = match name.as_str() {
+            "val" => match self.peek() {
+                Some(c) if c.is_ascii_digit() => {
+                    let digits = self.take_while(|c| c.is_ascii_digit());
+                    Accessor::Val(digits.parse().map_err(|_| QueryError {
+                        position: start,
+                        kind: QueryErrorKind::BadNumber,
+                    })?)
+                }
+                _ => Accessor::Val(0),
+            },
+            "prop" => match self.peek() {
+                Some('"') => Accessor::Prop(self.string()?),
+                _ => Accessor::Prop(self.identifier()?),
+            },
+            "name" => Accessor::Name,
+            "tag" => Accessor::Tag,
+            _ => {
+                return Err(QueryError {
+                    position: start,
+                    kind: QueryErrorKind::UnknownAccessor(name),
+                })
+            }
+        };
+
+        self.skip_whitespace();
+        self.expect(')')?;
+        Ok(accessor)
+    }
+
+    fn op(&mut self) -> Result<Op, QueryError> {
+        let rest = &self.src[self.pos..];
+
+        let (op, len) = [
+            ("!=", Op::NotEq),
+            (">=", Op::GtEq),
+            ("<=", Op::LtEq),
+            ("^=", Op::StartsWith),
+            ("$=", Op::EndsWith),
+            ("*=", Op::Contains),
+            ("=", Op::Eq),
+            (">", Op::Gt),
+            ("<", Op::Lt),
+        ]
+        .iter()
+        .find(|(text, _)| rest.starts_with(text))
+        .map(|(text, op)| (*op, text.len()))
+        .ok_or_else(|| self.unexpected())?;
+
+        self.pos += len;
+        Ok(op)
+    }
+
+    fn literal(&mut self) -> Result<Literal, QueryError> {
+        let start = self.pos;
+
+        match self.peek() {
+            Some('"') => Ok(Literal::String(self.string()?)),
+            Some('(') => {
+                self.bump();
+                let ty = self.identifier()?;
+                self.expect(')')?;
+                Ok(Literal::Type(ty))
+            }
+            Some(c) if c.is_ascii_digit() || c == '-' || c == '+' => {
+                let text = self.take_while(|c| !is_delimiter(c)).replace('_', "");
+                let bad_number = QueryError {
+                    position: start,
+                    kind: QueryErrorKind::BadNumber,
+                };
+
+                if let Ok(i) = text.parse::<i64>() {
+                    Ok(Literal::Integer(i))
+                } else {
+                    text.parse::<f64>()
+                        .map(Literal::Float)
+                        .map_err(|_| bad_number)
+                }
+            }
+            Some(_) => match self.identifier()?.as_str() {
+                "true" => Ok(Literal::Bool(true)),
+                "false" => Ok(Literal::Bool(false)),
+                "null" => Ok(Literal::Null),
+                ident => Ok(Literal::String(ident.to_owned())),
+            },
+            None => Err(self.error(QueryErrorKind::UnexpectedEnd)),
+        }
+    }
+
+    fn string(&mut self) -> Result<String, QueryError> {
+        let start = self.pos;
+        self.expect('"')?;
+
+        let contents_start = self.pos;
+        let mut escaped = false;
+
+        loop {
+            match self.bump() {
+                None => {
+                    return Err(QueryError {
+                        position: start,
+                        kind: QueryErrorKind::UnterminatedString,
+                    })
+                }
+                Some('\\') => {
+                    escaped = true;
+                    self.bump();
+                }
+                Some('"') => break,
+                Some(_) => (),
+            }
+        }
+
+        let raw = &self.src[contents_start..self.pos - 1];
+        let s = if escaped {
+            KdlString::Escaped(raw).unescape()
+        } else {
+            KdlString::Escapeless(raw).unescape()
+        };
+
+        s.map(|s| s.into_owned()).map_err(|_| QueryError {
+            position: start,
+            kind: QueryErrorKind::BadEscape,
+        })
+    }
+
+    fn identifier(&mut self) -> Result<String, QueryError> {
+        match self.peek() {
+            Some(c) if is_identifier_char(c) => Ok(self.take_while(is_identifier_char).to_owned()),
+            _ => Err(self.unexpected()),
+        }
+    }
+
+    fn take_while(&mut self, mut f: impl FnMut(char) -> bool) -> &'q str {
+        let start = self.pos;
+        while matches!(self.peek(), Some(c) if f(c)) {
+            self.bump();
+        }
+
+        &self.src[start..self.pos]
+    }
+
+    fn skip_whitespace(&mut self) -> bool {
+        !self.take_while(char::is_whitespace).is_empty()
+    }
+
+    fn peek(&self) -> Option<char> {
+        self.src[self.pos..].chars().next()
+    }
+
+    fn bump(&mut self) -> Option<char> {
+        let c = self.peek()?;
+        self.pos += c.len_utf8();
+        Some(c)
+    }
+
+    fn eat(&mut self, expected: char) -> bool {
+        if self.peek() == Some(expected) {
+            self.bump();
+            true
+        } else {
+            false
+        }
+    }
+
+    fn expect(&mut self, expected: char) -> Result<(), QueryError> {
+        if self.eat(expected) {
+            Ok(())
+        } else {
+            Err(self.unexpected())
+        }
+    }
+
+    fn unexpected(&self) -> QueryError {
+        match self.peek() {
+            Some(c) => self.error(QueryErrorKind::UnexpectedChar(c)),
+            None => self.error(QueryErrorKind::UnexpectedEnd),
+        }
+    }
+
+    fn error(&self, kind: QueryErrorKind) -> QueryError {
+        QueryError {
+            position: self.pos,
+            kind,
+        }
+    }
+}
+
+fn is_delimiter(c: char) -> bool {
+    c.is_whitespace() || "[]()>+~,=!^$*<\"".contains(c)
+}
+
+fn is_identifier_char(c: char) -> bool {
+    !is_delimiter(c)
+}
@@ -0,0 +1,880 @@
+use std::borrow::Cow;
+use std::collections::HashMap;
+use std::fmt;
+use std::ops::Range;
+
+use regex::Regex;
+
+use crate::assembler::{self, KdlNode, NameLocation};
+use crate::ast::*;
+use crate::parser::Parser;
+use crate::query::Query;
+use crate::ParseError;
+
+/// How many `ref`s are followed in a row before giving up, so schemas referring to themselves can't loop forever.
+const MAX_REF_DEPTH: usize = 32;
+
+/// A loaded KDL Schema document, like `examples/schema.kdl`.
+///
+/// Supported rules:
+/// - `node`: `min`/`max` occurrences, `value`, `prop`, `children`, `other-props-allowed` and `prop-names`. A `node` without a name applies to every node.
+/// - `children` and the top-level `document`: `node`s, `other-nodes-allowed` and `node-names`.
+/// - `value`: `min`/`max` argument counts, plus validations applied to every argument.
+/// - `prop`: `required`, plus validations.
+/// - validations: `type`, `enum`, `pattern`, `min-length`, `max-length`, `format`, `%`, `>`, `>=`, `<`, `<=` and `tag`.
+/// - `ref="<kql query>"` on any rule, which is replaced by the schema node the query selects.
+///
+/// Nodes and properties not covered by a rule are errors unless `other-nodes-allowed`/`other-props-allowed` say otherwise, and so are arguments on nodes without a `value` rule.
+/// Only some formats are checked: integer and float widths, `date`, `time`, `date-time`, `uuid`, `regex` and `kdl-query`; the rest are accepted as-is.
+pub struct Schema<'s> {
+    nodes: Vec<KdlNode<'s>>,
+    /// Index of the `document` node within `nodes`.
+    document: usize,
+    /// Each `ref` in the schema, with the path of child indices to the node it selects.
+    refs: HashMap<String, Vec<usize>>,
+    patterns: HashMap<String, Regex>,
+}
+
+/// A place in a node that a value came from.
+#[derive(Debug, Clone, PartialEq)]
+pub enum Entry {
+    Argument(usize),
+    Property(String),
+    /// The node's name, checked by `node-names`.
+    NodeName,
+    /// A property's key, checked by `prop-names`.
+    PropertyName(String),
+}
+
+/// Why a value failed it's validations.
+#[derive(Debug, Clone, PartialEq)]
+pub enum ValueError {
+    /// The value's type wasn't one of these.
+    WrongType(Vec<String>),
+    NotInEnum,
+    /// Didn't match this pattern.
+    PatternMismatch(String),
+    TooShort(usize),
+    TooLong(usize),
+    /// Didn't fit this format.
+    BadFormat(String),
+    NotMultipleOf(f64),
+    /// Failed a comparison, e.g. `>` 1024.
+    OutOfRange(&'static str, f64),
+    /// A `tag` rule was given, but the value had no type annotation.
+    MissingTag,
+}
+
+/// What went wrong while loading a schema or validating against it.
+#[derive(Debug, Clone)]
+pub enum SchemaErrorKind {
+    Parse(ParseError),
+    /// The schema itself is broken, e.g. it has no `document` node, or a `ref` selects nothing.
+    BadSchema(String),
+    /// A node no rule allows.
+    UnexpectedNode,
+    TooFewNodes {
+        name: String,
+        min: usize,
+        found: usize,
+    },
+    TooManyNodes {
+        name: String,
+        max: usize,
+        found: usize,
+    },
+    TooFewArguments {
+        min: usize,
+        found: usize,
+    },
+    TooManyArguments {
+        max: usize,
+        found: usize,
+    },
+    UnexpectedProperty(String),
+    MissingProperty(String),
+    InvalidValue {
+        entry: Entry,
+        reason: ValueError,
+    },
+}
+
+/// An error from loading a schema or validating a document, with the path to the node it's about.
+#[derive(Debug, Clone)]
+pub struct SchemaError {
+    pub kind: SchemaErrorKind,
+    /// Names of the nodes leading to the one the error is about, outermost first. Empty for the document itself.
+    pub path: Vec<String>,
+    location: Option<NameLocation>,
+}
+
+impl SchemaError {
+    /// The byte range of the name of the node this error is about, within the source it was parsed from.
+    /// Returns `None` for errors about the whole document, nodes whose names had escapes, or nodes not parsed from `source`.
+    pub fn span_in(&self, source: &str) -> Option<Range<usize>> {
+        self.location?.span_in(source)
+    }
+
+    fn bad_schema(msg: String) -> SchemaError {
+        SchemaError {
+            kind: SchemaErrorKind::BadSchema(msg),
+            path: Vec::new(),
+            location: None,
+        }
+    }
+}
+
+impl fmt::Display for ValueError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            ValueError::WrongType(types) => write!(f, "expected {}", types.join(" or ")),
+            ValueError::NotInEnum => f.write_str("not one of the allowed values"),
+            ValueError::PatternMismatch(pattern) => write!(f, "doesn't match `{}`", pattern),
+            ValueError::TooShort(min) => write!(f, "shorter than {}", min),
+            ValueError::TooLong(max) => write!(f, "longer than {}", max),
+            ValueError::BadFormat(format) => write!(f, "not a valid {}", format),
+            ValueError::NotMultipleOf(n) => write!(f, "not a multiple of {}", n),
+            ValueError::OutOfRange(op, n) => write!(f, "not {} {}", op, n),
+            ValueError::MissingTag => f.write_str("missing a type annotation"),
+        }
+    }
+}
+
+impl fmt::Display for SchemaErrorKind {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            SchemaErrorKind::Parse(e) => write!(f, "parse error: {:?}", e),
+            SchemaErrorKind::BadSchema(msg) => write!(f, "bad schema: {}", msg),
+            SchemaErrorKind::UnexpectedNode => f.write_str("node isn't allowed here"),
+            SchemaErrorKind::TooFewNodes { name, min, found } => {
+                write!(f, "expected at least {} `{}`, found {}", min, name, found)
+            }
+            SchemaErrorKind::TooManyNodes { name, max, found } => {
+                write!(f, "expected at most {} `{}`, found {}", max, name, found)
+            }
+            SchemaErrorKind::TooFewArguments { min, found } => {
+                write!(f, "expected at least {} arguments, found {}", min, found)
+            }
+            SchemaErrorKind::TooManyArguments { max, found } => {
+                write!(f, "expected at most {} arguments, found {}", max, found)
+            }
+            SchemaErrorKind::UnexpectedProperty(key) => write!(f, "unexpected property `{}`", key),
+            SchemaErrorKind::MissingProperty(key) => write!(f, "missing property `{}`", key),
+            SchemaErrorKind::InvalidValue { entry, reason } => match entry {
+                Entry::Argument(idx) => write!(f, "argument #{}: {}", idx, reason),
+                Entry::Property(key) => write!(f, "property `{}`: {}", key, reason),
+                Entry::NodeName => write!(f, "node name: {}", reason),
+                Entry::PropertyName(key) => write!(f, "property name `{}`: {}", key, reason),
+            },
+        }
+    }
+}
+
+impl fmt::Display for SchemaError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        if self.path.is_empty() {
+            f.write_str("document")?;
+        } else {
+            f.write_str(&self.path.join(" > "))?;
+        }
+
+        write!(f, ": {}", self.kind)
+    }
+}
+
+impl std::error::Error for SchemaError {}
+
+impl<'s> Schema<'s> {
+    /// Parses and loads a schema.
+    pub fn parse(input: &'s str) -> Result<Schema<'s>, SchemaError> {
+        let nodes =
+            assembler::parse_document(&mut Parser::from_str(input)).map_err(|e| SchemaError {
+                kind: SchemaErrorKind::Parse(e),
+                path: Vec::new(),
+                location: None,
+            })?;
+
+        Schema::from_document(nodes)
+    }
+
+    /// Loads a schema from an assembled document. Every `ref` and `pattern` is checked up front.
+    pub fn from_document(nodes: Vec<KdlNode<'s>>) -> Result<Schema<'s>, SchemaError> {
+        let document = nodes
+            .iter()
+            .position(|node| node.name == "document")
+            .ok_or_else(|| SchemaError::bad_schema("no `document` node".into()))?;
+
+        let mut schema = Schema {
+            nodes,
+            document,
+            refs: HashMap::new(),
+            patterns: HashMap::new(),
+        };
+
+        let mut refs = Vec::new();
+        let mut patterns = Vec::new();
+        collect(&schema.nodes, &mut refs, &mut patterns);
+
+        for query in refs {
+            if schema.refs.contains_key(&query) {
+                continue;
+            }
+
+            let parsed = Query::parse(&query)
+                .map_err(|e| SchemaError::bad_schema(format!("ref `{}`: {}", query, e)))?;
+            let target = parsed.first(&schema.nodes).ok_or_else(|| {
+                SchemaError::bad_schema(format!("ref `{}` selects nothing", query))
+            })?;
+            let path = path_to(&schema.nodes, target).expect("selected nodes are in the tree");
+
+            schema.refs.insert(query, path);
+        }
+
+        for pattern in patterns {
+            if schema.patterns.contains_key(&pattern) {
+                continue;
+            }
+
+            let regex = Regex::new(&pattern)
+                .map_err(|e| SchemaError::bad_schema(format!("pattern `{}`: {}", pattern, e)))?;
+            schema.patterns.insert(pattern, regex);
+        }
+
+        Ok(schema)
+    }
+
+    /// Validates a document, returning every error found.
+    pub fn validate(&self, nodes: &[KdlNode<'_>]) -> Result<(), Vec<SchemaError>> {
+        let mut validator = Validator {
+            schema: self,
+            errors: Vec::new(),
+            path: Vec::new(),
+        };
+
+        let document = &self.nodes[self.document];
+        validator.children(None, nodes, &[document]);
+
+        if validator.errors.is_empty() {
+            Ok(())
+        } else {
+            Err(validator.errors)
+        }
+    }
+
+    /// Parses and validates a document. Errors can be located in `input` with [SchemaError::span_in].
+    pub fn validate_str(&self, input: &str) -> Result<(), Vec<SchemaError>> {
+        let nodes = assembler::parse_document(&mut Parser::from_str(input)).map_err(|e| {
+            vec![SchemaError {
+                kind: SchemaErrorKind::Parse(e),
+                path: Vec::new(),
+                location: None,
+            }]
+        })?;
+
+        self.validate(&nodes)
+    }
+
+    /// The top-level `document` rule.
+    pub(crate) fn document(&self) -> &KdlNode<'s> {
+        &self.nodes[self.document]
+    }
+
+    /// Follows a rule's `ref`s to the rule they point to.
+    pub(crate) fn resolve<'r>(&'r self, mut rule: &'r KdlNode<'s>) -> &'r KdlNode<'s> {
+        for _ in 0..MAX_REF_DEPTH {
+            let path = match string_prop(rule, "ref").and_then(|query| self.refs.get(&*query)) {
+                Some(path) => path,
+                None => break,
+            };
+
+            let mut target = &self.nodes[path[0]];
+            for &idx in &path[1..] {
+                target = &target.children[idx];
+            }
+            rule = target;
+        }
+
+        rule
+    }
+
+    /// A rule's children, with `ref`s followed.
+    pub(crate) fn parts<'r>(
+        &'r self,
+        rule: &'r KdlNode<'s>,
+    ) -> impl Iterator<Item = &'r KdlNode<'s>> {
+        rule.children.iter().map(move |part| self.resolve(part))
+    }
+}
+
+/// Collects every `ref` and `pattern` in the schema.
+fn collect(nodes: &[KdlNode<'_>], refs: &mut Vec<String>, patterns: &mut Vec<String>) {
+    for node in nodes {
+        if let Some(query) = string_prop(node, "ref") {
+            refs.push(query.into_owned());
+        }
+
+        if node.name == "pattern" {
+            patterns.extend(
+                node.values
+                    .iter()
+                    .filter_map(|value| string_of(&value.val).map(Cow::into_owned)),
+            );
+        }
+
+        collect(&node.children, refs, patterns);
+    }
+}
+
+/// The child indices leading from the top of `nodes` to `target`.
+fn path_to(nodes: &[KdlNode<'_>], target: &KdlNode<'_>) -> Option<Vec<usize>> {
+    for (idx, node) in nodes.iter().enumerate() {
+        if core::ptr::eq(node, target) {
+            return Some(vec![idx]);
+        }
+
+        if let Some(mut path) = path_to(&node.children, target) {
+            path.insert(0, idx);
+            return Some(path);
+        }
+    }
+
+    None
+}
+
+pub(crate) fn string_of<'a>(value: &KdlValue<'a>) -> Option<Cow<'a, str>> {
+    match value {
+        KdlValue::String(s) => s.unescape().ok(),
+        _ => None,
+    }
+}
+
+pub(crate) fn string_prop<'a>(node: &KdlNode<'a>, key: &str) -> Option<Cow<'a, str>> {
+    node.attrs
+        .iter()
+        .rev()
+        .find(|attr| attr.key == KdlString::Escapeless(key))
+        .and_then(|attr| string_of(&attr.value.val))
+}
+
+/// The rule's name; it's first argument.
+pub(crate) fn rule_name<'a>(rule: &KdlNode<'a>) -> Option<Cow<'a, str>> {
+    rule.values.first().and_then(|value| string_of(&value.val))
+}
+
+fn number_of(value: &KdlValue<'_>) -> Option<f64> {
+    match *value {
+        KdlValue::Integer(i) => Some(i as f64),
+        KdlValue::Float(f) => Some(f),
+        _ => None,
+    }
+}
+
+/// The first argument of the rule's child called `name`, as a count.
+pub(crate) fn count_of(schema: &Schema<'_>, rule: &KdlNode<'_>, name: &str) -> Option<usize> {
+    schema
+        .parts(rule)
+        .find(|part| part.name == name)
+        .and_then(|part| part.values.first())
+        .and_then(|value| match value.val {
+            KdlValue::Integer(i) => usize::try_from(i).ok(),
+            _ => None,
+        })
+}
+
+/// Whether the rule's child called `name` is set to `true`.
+pub(crate) fn flag_of(schema: &Schema<'_>, rule: &KdlNode<'_>, name: &str) -> bool {
+    schema
+        .parts(rule)
+        .find(|part| part.name == name)
+        .is_some_and(is_true)
+}
+
+/// Whether the rule's first argument is `true`.
+pub(crate) fn is_true(rule: &KdlNode<'_>) -> bool {
+    rule.values.first().map(|value| value.val) == Some(KdlValue::Bool(true))
+}
+
+/// Whether a `node` or `prop` rule applies to a name; rules without a name apply to everything.
+fn applies_to(rule: &KdlNode<'_>, name: &str) -> bool {
+    match rule_name(rule) {
+        Some(rule_name) => rule_name == name,
+        None => true,
+    }
+}
+
+struct Validator<'v, 's> {
+    schema: &'v Schema<'s>,
+    errors: Vec<SchemaError>,
+    path: Vec<String>,
+}
+
+impl<'v, 's> Validator<'v, 's> {
+    fn error(&mut self, node: Option<&KdlNode<'_>>, kind: SchemaErrorKind) {
+        self.errors.push(SchemaError {
+            kind,
+            path: self.path.clone(),
+            location: node.and_then(NameLocation::of),
+        });
+    }
+
+    /// Validates a set of siblings against `children` rules (or the `document`).
+    fn children(
+        &mut self,
+        parent: Option<&KdlNode<'_>>,
+        nodes: &[KdlNode<'_>],
+        rules: &[&'v KdlNode<'s>],
+    ) {
+        let schema = self.schema;
+        let mut node_rules = Vec::new();
+        let mut other_nodes_allowed = false;
+        let mut name_validations = Vec::new();
+
+        for &rule in rules {
+            for part in schema.parts(rule) {
+                match &*part.name {
+                    "node" => node_rules.push(part),
+                    "other-nodes-allowed" => other_nodes_allowed |= is_true(part),
+                    "node-names" => name_validations.extend(schema.parts(part)),
+                    _ => (),
+                }
+            }
+        }
+
+        for node in nodes {
+            self.path.push(node.name.to_string());
+
+            if !name_validations.is_empty() {
+                let name = TypedValue {
+                    ty: None,
+                    val: KdlValue::String(KdlString::Escapeless(&node.name)),
+                };
+                self.value(node, Entry::NodeName, &name, &name_validations);
+            }
+
+            let matching: Vec<_> = node_rules
+                .iter()
+                .filter(|rule| applies_to(rule, &node.name))
+                .collect();
+
+            if matching.is_empty() {
+                if !other_nodes_allowed {
+                    self.error(Some(node), SchemaErrorKind::UnexpectedNode);
+                }
+            } else {
+                for rule in matching {
+                    self.node(node, rule);
+                }
+            }
+
+            self.path.pop();
+        }
+
+        for rule in &node_rules {
+            let name = match rule_name(rule) {
+                Some(name) => name,
+                None => continue,
+            };
+
+            let found = nodes.iter().filter(|node| node.name == name).count();
+
+            if let Some(min) = count_of(schema, rule, "min") {
+                if found < min {
+                    let kind = SchemaErrorKind::TooFewNodes {
+                        name: name.to_string(),
+                        min,
+                        found,
+                    };
+                    self.error(parent, kind);
+                }
+            }
+
+            if let Some(max) = count_of(schema, rule, "max") {
+                if found > max {
+                    let kind = SchemaErrorKind::TooManyNodes {
+                        name: name.to_string(),
+                        max,
+                        found,
+                    };
+                    self.error(parent, kind);
+                }
+            }
+        }
+    }
+
+    /// Validates a node against a single `node` rule. The node is already on the path.
+    fn node(&mut self, node: &KdlNode<'_>, rule: &'v KdlNode<'s>) {
+        let schema = self.schema;
+        let mut value_rules = Vec::new();
+        let mut prop_rules = Vec::new();
+        let mut children_rules = Vec::new();
+        let mut other_props_allowed = false;
+        let mut prop_name_validations = Vec::new();
+
+        for part in schema.parts(rule) {
+            match &*part.name {
+                "value" => value_rules.push(part),
+                "prop" => prop_rules.push(part),
+                "children" => children_rules.push(part),
+                "other-props-allowed" => other_props_allowed |= is_true(part),
+                "prop-names" => prop_name_validations.extend(schema.parts(part)),
+                _ => (),
+            }
+        }
+
+        // arguments
+        if value_rules.is_empty() && !node.values.is_empty() {
+            let kind = SchemaErrorKind::TooManyArguments {
+                max: 0,
+                found: node.values.len(),
+            };
+            self.error(Some(node), kind);
+        }
+
+        for value_rule in value_rules {
+            let found = node.values.len();
+
+            if let Some(min) = count_of(schema, value_rule, "min") {
+                if found < min {
+                    self.error(Some(node), SchemaErrorKind::TooFewArguments { min, found });
+                }
+            }
+
+            if let Some(max) = count_of(schema, value_rule, "max") {
+                if found > max {
+                    self.error(Some(node), SchemaErrorKind::TooManyArguments { max, found });
+                }
+            }
+
+            let validations: Vec<_> = schema.parts(value_rule).collect();
+            for (idx, value) in node.values.iter().enumerate() {
+                self.value(node, Entry::Argument(idx), value, &validations);
+            }
+        }
+
+        // properties; repeated keys are only checked once, using the last value
+        let mut seen = Vec::new();
+        for attr in node.attrs.iter().rev() {
+            let key = match attr.key.unescape() {
+                Ok(key) => key,
+                Err(e) => {
+                    self.error(Some(node), SchemaErrorKind::Parse(e));
+                    continue;
+                }
+            };
+
+            if seen.contains(&key) {
+                continue;
+            }
+
+            if !prop_name_validations.is_empty() {
+                let name = TypedValue {
+                    ty: None,
+                    val: KdlValue::String(KdlString::Escapeless(&key)),
+                };
+                self.value(
+                    node,
+                    Entry::PropertyName(key.to_string()),
+                    &name,
+                    &prop_name_validations,
+                );
+            }
+
+            let matching: Vec<_> = prop_rules
+                .iter()
+                .filter(|rule| applies_to(rule, &key))
+                .collect();
+
+            if matching.is_empty() && !other_props_allowed {
+                self.error(
+                    Some(node),
+                    SchemaErrorKind::UnexpectedProperty(key.to_string()),
+                );
+            }
+
+            for rule in matching {
+                let validations: Vec<_> = schema.parts(rule).collect();
+                self.value(
+                    node,
+                    Entry::Property(key.to_string()),
+                    &attr.value,
+                    &validations,
+                );
+            }
+
+            seen.push(key);
+        }
+
+        for rule in &prop_rules {
+            if let Some(key) = rule_name(rule) {
+                if flag_of(schema, rule, "required") && !seen.contains(&key) {
+                    self.error(
+                        Some(node),
+                        SchemaErrorKind::MissingProperty(key.into_owned()),
+                    );
+                }
+            }
+        }
+
+        // children
+        self.children(Some(node), &node.children, &children_rules);
+    }
+
+    /// Checks a value against a list of validations, ignoring any other rules mixed in with them (like `min` and `max`).
+    fn value(
+        &mut self,
+        node: &KdlNode<'_>,
+        entry: Entry,
+        value: &TypedValue<'_>,
+        validations: &[&'v KdlNode<'s>],
+    ) {
+        for &validation in validations {
+            if let Err(reason) = self.check(value, validation) {
+                self.error(
+                    Some(node),
+                    SchemaErrorKind::InvalidValue {
+                        entry: entry.clone(),
+                        reason,
+                    },
+                );
+            }
+        }
+    }
+
+    fn check(
+        &mut self,
+        value: &TypedValue<'_>,
+        validation: &KdlNode<'s>,
+    ) -> Result<(), ValueError> {
+        let args = &validation.values;
+        let number = number_of(&value.val);
+        let string = string_of(&value.val);
+
+        match &*validation.name {
+            "type" => {
+                let types: Vec<String> = args
+                    .iter()
+                    .filter_map(|arg| string_of(&arg.val).map(Cow::into_owned))
+                    .collect();
+
+                if types.iter().any(|ty| is_type(&value.val, ty)) {
+                    Ok(())
+                } else {
+                    Err(ValueError::WrongType(types))
+                }
+            }
+            "enum" => {
+                if args.iter().any(|arg| values_equal(&arg.val, &value.val)) {
+                    Ok(())
+                } else {
+                    Err(ValueError::NotInEnum)
+                }
+            }
+            "pattern" => {
+                let string = string.ok_or_else(|| ValueError::WrongType(vec!["string".into()]))?;
+
+                for pattern in args.iter().filter_map(|arg| string_of(&arg.val)) {
+                    if !self.schema.patterns[&*pattern].is_match(&string) {
+                        return Err(ValueError::PatternMismatch(pattern.into_owned()));
+                    }
+                }
+
+                Ok(())
+            }
+            "min-length" | "max-length" => {
+                let string = string.ok_or_else(|| ValueError::WrongType(vec!["string".into()]))?;
+                let len = string.chars().count();
+
+                match args.first().and_then(|arg| number_of(&arg.val)) {
+                    Some(min) if validation.name == "min-length" && (len as f64) < min => {
+                        Err(ValueError::TooShort(min as usize))
+                    }
+                    Some(max) if validation.name == "max-length" && (len as f64) > max => {
+                        Err(ValueError::TooLong(max as usize))
+                    }
+                    _ => Ok(()),
+                }
+            }
+            "format" => match args.first().and_then(|arg| string_of(&arg.val)) {
+                Some(format) if !matches_format(&value.val, &format) => {
+                    Err(ValueError::BadFormat(format.into_owned()))
+                }
+                _ => Ok(()),
+            },
+            "%" => {
+                let number = number.ok_or_else(|| ValueError::WrongType(vec!["number".into()]))?;
+
+                for divisor in args.iter().filter_map(|arg| number_of(&arg.val)) {
+                    if divisor != 0.0 && number % divisor != 0.0 {
+                        return Err(ValueError::NotMultipleOf(divisor));
+                    }
+                }
+
+                Ok(())
+            }
+            op @ (">" | ">=" | "<" | "<=") => {
+                let number = number.ok_or_else(|| ValueError::WrongType(vec!["number".into()]))?;
+                let bound = match args.first().and_then(|arg| number_of(&arg.val)) {
+                    Some(bound) => bound,
+                    None => return Ok(()),
+                };
+
+                let (ok, op) = match op {
+                    ">" => (number > bound, ">"),
+                    ">=" => (number >= bound, ">="),
+                    "<" => (number < bound, "<"),
+                    _ => (number <= bound, "<="),
+                };
+
+                if ok {
+                    Ok(())
+                } else {
+                    Err(ValueError::OutOfRange(op, bound))
+                }
+            }
+            "tag" => {
+                let tag = value.ty.ok_or(ValueError::MissingTag)?;
+                let tag = TypedValue {
+                    ty: None,
+                    val: KdlValue::String(KdlString::Escapeless(tag)),
+                };
+
+                let schema = self.schema;
+                for inner in schema.parts(validation) {
+                    self.check(&tag, inner)?;
+                }
+
+                Ok(())
+            }
+            _ => Ok(()),
+        }
+    }
+}
+
+fn is_type(value: &KdlValue<'_>, ty: &str) -> bool {
+    match ty {
+        "string" => matches!(value, KdlValue::String(_)),
+        "number" => matches!(value, KdlValue::Integer(_) | KdlValue::Float(_)),
+        "integer" => matches!(value, KdlValue::Integer(_)),
+        "boolean" | "bool" => matches!(value, KdlValue::Bool(_)),
+        "null" => matches!(value, KdlValue::Null),
+        _ => false,
+    }
+}
+
+fn values_equal(a: &KdlValue<'_>, b: &KdlValue<'_>) -> bool {
+    match (a, b) {
+        (KdlValue::String(_), KdlValue::String(_)) => string_of(a) == string_of(b),
+        _ => match (number_of(a), number_of(b)) {
+            (Some(a), Some(b)) => a == b,
+            _ => a == b,
+        },
+    }
+}
+
+fn matches_format(value: &KdlValue<'_>, format: &str) -> bool {
+    let integer_range = match format {
+        "i8" => Some((i8::MIN as i64, i8::MAX as i64)),
+        "i16" => Some((i16::MIN as i64, i16::MAX as i64)),
+        "i32" => Some((i32::MIN as i64, i32::MAX as i64)),
+        "i64" | "isize" => Some((i64::MIN, i64::MAX)),
+        "u8" => Some((0, u8::MAX as i64)),
+        "u16" => Some((0, u16::MAX as i64)),
+        "u32" => Some((0, u32::MAX as i64)),
+        "u64" | "usize" => Some((0, i64::MAX)),
+        _ => None,
+    };
+
+    if let Some((min, max)) = integer_range {
+        return matches!(*value, KdlValue::Integer(i) if i >= min && i <= max);
+    }
+
+    if let "f32" | "f64" | "decimal64" | "decimal128" = format {
+        return number_of(value).is_some();
+    }
+
+    let string = match string_of(value) {
+        Some(string) => string,
+        None => return false,
+    };
+
+    match format {
+        "date" => is_date(&string),
+        "time" => is_time(&string),
+        "date-time" => matches!(
+            string.split_once('T'),
+            Some((date, time)) if is_date(date) && is_time(time)
+        ),
+        "uuid" => is_uuid(&string),
+        "regex" => Regex::new(&string).is_ok(),
+        "kdl-query" => Query::parse(&string).is_ok(),
+        _ => true,
+    }
+}
+
+/// Checks that `s` is made of digit groups with the given lengths, separated by `sep`, and returns them.
+fn digit_groups(s: &str, sep: char, lens: &[usize]) -> Option<Vec<u32>> {
+    let groups: Vec<&str> = s.split(sep).collect();
+
+    if groups.len() != lens.len() {
+        return None;
+    }
+
+    groups
+        .iter()
+        .zip(lens)
+        .map(|(group, &len)| {
+            if group.len() == len && group.bytes().all(|b| b.is_ascii_digit()) {
+                group.parse().ok()
+            } else {
+                None
+            }
+        })
+        .collect()
+}
+
+/// `YYYY-MM-DD`.
+fn is_date(s: &str) -> bool {
+    match digit_groups(s, '-', &[4, 2, 2]).as_deref() {
+        Some(&[_, month, day]) => (1..=12).contains(&month) && (1..=31).contains(&day),
+        _ => false,
+    }
+}
+
+/// `HH:MM:SS`, with optional fractional seconds and a `Z` or `±HH:MM` offset.
+fn is_time(s: &str) -> bool {
+    let s = s.strip_suffix('Z').unwrap_or(s);
+    let s = match s.rfind(['+', '-']) {
+        Some(idx) => {
+            if digit_groups(&s[idx + 1..], ':', &[2, 2]).is_none() {
+                return false;
+            }
+            &s[..idx]
+        }
+        None => s,
+    };
+    let s = match s.split_once('.') {
+        Some((s, fraction))
+            if !fraction.is_empty() && fraction.bytes().all(|b| b.is_ascii_digit()) =>
+        {
+            s
+        }
+        Some(_) => return false,
+        None => s,
+    };
+
+    match digit_groups(s, ':', &[2, 2, 2]).as_deref() {
+        Some(&[hour, minute, second]) => hour < 24 && minute < 60 && second <= 60,
+        _ => false,
+    }
+}
+
+/// `8-4-4-4-12` hex digits.
+fn is_uuid(s: &str) -> bool {
+    let groups: Vec<&str> = s.split('-').collect();
+
+    groups.len() == 5
+        && groups
+            .iter()
+            .zip([8, 4, 4, 4, 12])
+            .all(|(group, len)| group.len() == len && group.bytes().all(|b| b.is_ascii_hexdigit()))
+}
@@ -0,0 +1,1040 @@
+#[cfg(all(not(feature = "std"), feature = "alloc"))]
+use alloc::{
+    string::{String, ToString},
+    vec::Vec,
+};
+
+use core::fmt;
+use serde::ser::{self, Impossible, Serialize};
+
+use crate::ast::*;
+use crate::format::{FormatOptions, Formatter};
+use crate::{Container, KdlEvent};
+
+/// A serialization error.
+#[derive(Debug)]
+pub enum Error {
+    /// Only structs, maps and sequences can be serialized as a document.
+    NotADocument,
+    /// Map keys have to serialize as strings, chars, integers or bools.
+    KeyMustBeAString,
+    /// Integers have to fit in an i64.
+    IntegerOutOfRange,
+    /// kdl can't represent NaN floats.
+    NotANumber,
+    #[cfg(feature = "std")]
+    Io(std::io::Error),
+    Custom(String),
+}
+
+impl fmt::Display for Error {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            Error::NotADocument => f.write_str("only structs, maps and sequences can be documents"),
+            Error::KeyMustBeAString => f.write_str("map keys have to be strings"),
+            Error::IntegerOutOfRange => f.write_str("integer doesn't fit in an i64"),
+            Error::NotANumber => f.write_str("NaN can't be written as kdl"),
+            #[cfg(feature = "std")]
+            Error::Io(e) => write!(f, "io error: {}", e),
+            Error::Custom(msg) => f.write_str(msg),
+        }
+    }
+}
+
+impl ser::StdError for Error {}
+
+impl ser::Error for Error {
+    fn custom<T: fmt::Display>(msg: T) -> Error {
+        Error::Custom(msg.to_string())
+    }
+}
+
+/// Serializes a value as a kdl document, using the default [FormatOptions].
+///
+/// This is the reverse of the mapping used by [crate::de::Deserializer], so anything serialized here can be read back:
+///
+/// - A struct or map is a document; each field becomes a top-level node. A sequence is a document of `-` nodes.
+/// - Inside a node, plain values (strings, numbers, bools, unit variants and type annotated newtype variants) become properties and everything else becomes a child node named after the field. `None` fields are left out entirely.
+/// - A field named `-` holds the node's arguments.
+/// - A plain value on it's own is a node with a single argument, and a sequence of plain values is a node with them as arguments. Sequences of anything else are children blocks of `-` nodes.
+/// - Unit variants are strings, newtype variants holding a plain value are type annotated values (`(Seconds)30`), and other variants are a children block with a single node named after the variant.
+pub fn to_string<T: Serialize + ?Sized>(value: &T) -> Result<String, Error> {
+    to_string_with_options(value, &FormatOptions::default())
+}
+
+/// Serializes a value as a kdl document, formatted according to the options.
+pub fn to_string_with_options<T: Serialize + ?Sized>(
+    value: &T,
+    options: &FormatOptions,
+) -> Result<String, Error> {
+    let nodes = value.serialize(DocumentSerializer)?;
+
+    let mut formatter = Formatter::new(String::new(), options);
+    for node in &nodes {
+        write_node(&mut formatter, node).expect("writing to a String can't fail");
+    }
+
+    Ok(formatter.into_inner())
+}
+
+/// Serializes a value as a kdl document into an io sink.
+#[cfg(feature = "std")]
+pub fn to_writer<W: std::io::Write, T: Serialize + ?Sized>(
+    mut writer: W,
+    value: &T,
+) -> Result<(), Error> {
+    writer
+        .write_all(to_string(value)?.as_bytes())
+        .map_err(Error::Io)
+}
+
+/// An owned node, built up while serializing.
+struct Node {
+    name: String,
+    args: Vec<Value>,
+    props: Vec<(String, Value)>,
+    children: Vec<Node>,
+}
+
+impl Node {
+    fn new(name: String) -> Node {
+        Node {
+            name,
+            args: Vec::new(),
+            props: Vec::new(),
+            children: Vec::new(),
+        }
+    }
+}
+
+/// An owned value, with it's type annotation.
+struct Value {
+    ty: Option<String>,
+    val: Scalar,
+}
+
+enum Scalar {
+    String(String),
+    Integer(i64),
+    Float(f64),
+    Bool(bool),
+    Null,
+}
+
+impl Value {
+    fn plain(val: Scalar) -> Value {
+        Value { ty: None, val }
+    }
+
+    fn borrow(&self) -> TypedValue<'_> {
+        TypedValue {
+            ty: self.ty.as_deref(),
+            val: match &self.val {
+                Scalar::String(s) => KdlValue::String(KdlString::Escapeless(s)),
+                Scalar::Integer(i) => KdlValue::Integer(*i),
+                Scalar::Float(f) => KdlValue::Float(*f),
+                Scalar::Bool(b) => KdlValue::Bool(*b),
+                Scalar::Null => KdlValue::Null,
+            },
+        }
+    }
+}
+
+/// Writes a node out through the formatter, as events.
+fn write_node<W: fmt::Write>(formatter: &mut Formatter<'_, W>, node: &Node) -> fmt::Result {
+    let name = KdlString::Escapeless(&node.name);
+    let has_children = !node.children.is_empty();
+
+    formatter.write_event(&KdlEvent::NodeOpen {
+        ty: None,
+        name,
+        attrs: node
+            .props
+            .iter()
+            .map(|(key, value)| KdlProperty {
+                key: KdlString::Escapeless(key),
+                value: value.borrow(),
+            })
+            .collect::<Container<_>>(),
+        values: node.args.iter().map(Value::borrow).collect(),
+        has_children,
+    })?;
+
+    if has_children {
+        for child in &node.children {
+            write_node(formatter, child)?;
+        }
+        formatter.write_event(&KdlEvent::BracketedNodeClose(name))
+    } else {
+        formatter.write_event(&KdlEvent::NodeClose(name))
+    }
+}
+
+fn integer<I: TryInto<i64>>(i: I) -> Result<Scalar, Error> {
+    i.try_into()
+        .map(Scalar::Integer)
+        .map_err(|_| Error::IntegerOutOfRange)
+}
+
+fn float(f: f64) -> Result<Scalar, Error> {
+    if f.is_nan() {
+        Err(Error::NotANumber)
+    } else {
+        Ok(Scalar::Float(f))
+    }
+}
+
+/// What a value serializes to when it's in a property's position.
+enum Entry {
+    /// A plain value, which fits in a property or argument.
+    Value(Value),
+    /// `None`, which is left out.
+    Nothing,
+    /// Anything else, which needs a node of it's own.
+    Complex,
+}
+
+/// Serializes plain values, bailing out with [Entry::Complex] on anything that needs a node.
+struct ValueSerializer;
+
+macro_rules! serialize_integers {
+    ($($method:ident: $ty:ty),*) => {
+        $(
+            fn $method(self, v: $ty) -> Result<Entry, Error> {
+                Ok(Entry::Value(Value::plain(integer(v)?)))
+            }
+        )*
+    };
+}
+
+impl ser::Serializer for ValueSerializer {
+    type Ok = Entry;
+    type Error = Error;
+    type SerializeSeq = Skip;
+    type SerializeTuple = Skip;
+    type SerializeTupleStruct = Skip;
+    type SerializeTupleVariant = Skip;
+    type SerializeMap = Skip;
+    type SerializeStruct = Skip;
+    type SerializeStructVariant = Skip;
+
+    serialize_integers! {
+        serialize_i8: i8, serialize_i16: i16, serialize_i32: i32, serialize_i64: i64,
+        serialize_i128: i128, serialize_u8: u8, serialize_u16: u16, serialize_u32: u32,
+        serialize_u64: u64, serialize_u128: u128
+    }
+
+    fn serialize_bool(self, v: bool) -> Result<Entry, Error> {
+        Ok(Entry::Value(Value::plain(Scalar::Bool(v))))
+    }
+
+    fn serialize_f32(self, v: f32) -> Result<Entry, Error> {
+        self.serialize_f64(v as f64)
+    }
+
+    fn serialize_f64(self, v: f64) -> Result<Entry, Error> {
+        Ok(Entry::Value(Value::plain(float(v)?)))
+    }
+
+    fn serialize_char(self, v: char) -> Result<Entry, Error> {
+        self.serialize_str(v.encode_utf8(&mut [0; 4]))
+    }
+
+    fn serialize_str(self, v: &str) -> Result<Entry, Error> {
+        Ok(Entry::Value(Value::plain(Scalar::String(v.into()))))
+    }
+
+    fn serialize_bytes(self, _v: &[u8]) -> Result<Entry, Error> {
+        Ok(Entry::Complex)
+    }
+
+    fn serialize_none(self) -> Result<Entry, Error> {
+        Ok(Entry::Nothing)
+    }
+
+    fn serialize_some<T: Serialize + ?Sized>(self, value: &T) -> Result<Entry, Error> {
+        value.serialize(self)
+    }
+
+    fn serialize_unit(self) -> Result<Entry, Error> {
+        Ok(Entry::Value(Value::plain(Scalar::Null)))
+    }
+
+    fn serialize_unit_struct(self, _name: &'static str) -> Result<Entry, Error> {
+        self.serialize_unit()
+    }
+
+    fn serialize_unit_variant(
+        self,
+        _name: &'static str,
+        _variant_index: u32,
+        variant: &'static str,
+    ) -> Result<Entry, Error> {
+        self.serialize_str(variant)
+    }
+
+    fn serialize_newtype_struct<T: Serialize + ?Sized>(
+        self,
+        _name: &'static str,
+        value: &T,
+    ) -> Result<Entry, Error> {
+        value.serialize(self)
+    }
+
+    fn serialize_newtype_variant<T: Serialize + ?Sized>(
+        self,
+        _name: &'static str,
+        _variant_index: u32,
+        variant: &'static str,
+        value: &T,
+    ) -> Result<Entry, Error> {
+        Ok(match value.serialize(ValueSerializer)? {
+            Entry::Value(Value { ty: None, val }) => Entry::Value(Value {
+                ty: Some(variant.into()),
+                val,
+            }),
+            _ => Entry::Complex,
+        })
+    }
+
+    fn serialize_seq(self, _len: Option<usize>) -> Result<Skip, Error> {
+        Ok(Skip)
+    }
+
+    fn serialize_tuple(self, _len: usize) -> Result<Skip, Error> {
+        Ok(Skip)
+    }
+
+    fn serialize_tuple_struct(self, _name: &'static str, _len: usize) -> Result<Skip, Error> {
+        Ok(Skip)
+    }
+
+    fn serialize_tuple_variant(
+        self,
+        _name: &'static str,
+        _variant_index: u32,
+        _variant: &'static str,
+        _len: usize,
+    ) -> Result<Skip, Error> {
+        Ok(Skip)
+    }
+
+    fn serialize_map(self, _len: Option<usize>) -> Result<Skip, Error> {
+        Ok(Skip)
+    }
+
+    fn serialize_struct(self, _name: &'static str, _len: usize) -> Result<Skip, Error> {
+        Ok(Skip)
+    }
+
+    fn serialize_struct_variant(
+        self,
+        _name: &'static str,
+        _variant_index: u32,
+        _variant: &'static str,
+        _len: usize,
+    ) -> Result<Skip, Error> {
+        Ok(Skip)
+    }
+}
+
+/// Ignores the contents of a compound value; it'll be serialized again as a node.
+struct Skip;
+
+macro_rules! impl_skip {
+    ($($trait:ident: $method:ident $(, $key:ident)?);*) => {
+        $(
+            impl ser::$trait for Skip {
+                type Ok = Entry;
+                type Error = Error;
+
+                fn $method<T: Serialize + ?Sized>(&mut self, $($key: &'static str,)? _value: &T) -> Result<(), Error> {
+                    Ok(())
+                }
+
+                fn end(self) -> Result<Entry, Error> {
+                    Ok(Entry::Complex)
+                }
+            }
+        )*
+    };
+}
+
+impl_skip! {
+    SerializeSeq: serialize_element;
+    SerializeTuple: serialize_element;
+    SerializeTupleStruct: serialize_field;
+    SerializeTupleVariant: serialize_field;
+    SerializeStruct: serialize_field, _key;
+    SerializeStructVariant: serialize_field, _key
+}
+
+impl ser::SerializeMap for Skip {
+    type Ok = Entry;
+    type Error = Error;
+
+    fn serialize_key<T: Serialize + ?Sized>(&mut self, _key: &T) -> Result<(), Error> {
+        Ok(())
+    }
+
+    fn serialize_value<T: Serialize + ?Sized>(&mut self, _value: &T) -> Result<(), Error> {
+        Ok(())
+    }
+
+    fn end(self) -> Result<Entry, Error> {
+        Ok(Entry::Complex)
+    }
+}
+
+/// Serializes a value as a whole node with the given name.
+struct NodeSerializer {
+    name: String,
+}
+
+impl NodeSerializer {
+    fn with_arg(self, val: Scalar) -> Result<Node, Error> {
+        let mut node = Node::new(self.name);
+        node.args.push(Value::plain(val));
+        Ok(node)
+    }
+
+    /// A node with a children block holding a single node, named after the variant.
+    fn variant(self, variant: Node) -> Node {
+        let mut node = Node::new(self.name);
+        node.children.push(variant);
+        node
+    }
+}
+
+impl ser::Serializer for NodeSerializer {
+    type Ok = Node;
+    type Error = Error;
+    type SerializeSeq = SeqCollector;
+    type SerializeTuple = SeqCollector;
+    type SerializeTupleStruct = SeqCollector;
+    type SerializeTupleVariant = SeqCollector;
+    type SerializeMap = StructCollector;
+    type SerializeStruct = StructCollector;
+    type SerializeStructVariant = StructCollector;
+
+    fn serialize_bool(self, v: bool) -> Result<Node, Error> {
+        self.with_arg(Scalar::Bool(v))
+    }
+
+    fn serialize_i8(self, v: i8) -> Result<Node, Error> {
+        self.with_arg(integer(v)?)
+    }
+
+    fn serialize_i16(self, v: i16) -> Result<Node, Error> {
+        self.with_arg(integer(v)?)
+    }
+
+    fn serialize_i32(self, v: i32) -> Result<Node, Error> {
+        self.with_arg(integer(v)?)
+    }
+
+    fn serialize_i64(self, v: i64) -> Result<Node, Error> {
+        self.with_arg(integer(v)?)
+    }
+
+    fn serialize_i128(self, v: i128) -> Result<Node, Error> {
+        self.with_arg(integer(v)?)
+    }
+
+    fn serialize_u8(self, v: u8) -> Result<Node, Error> {
+        self.with_arg(integer(v)?)
+    }
+
+    fn serialize_u16(self, v: u16) -> Result<Node, Error> {
+        self.with_arg(integer(v)?)
+    }
+
+    fn serialize_u32(self, v: u32) -> Result<Node, Error> {
+        self.with_arg(integer(v)?)
+    }
+
+    fn serialize_u64(self, v: u64) -> Result<Node, Error> {
+        self.with_arg(integer(v)?)
+    }
+
+    fn serialize_u128(self, v: u128) -> Result<Node, Error> {
+        self.with_arg(integer(v)?)
+    }
+
+    fn serialize_f32(self, v: f32) -> Result<Node, Error> {
+        self.with_arg(float(v as f64)?)
+    }
+
+    fn serialize_f64(self, v: f64) -> Result<Node, Error> {
+        self.with_arg(float(v)?)
+    }
+
+    fn serialize_char(self, v: char) -> Result<Node, Error> {
+        self.with_arg(Scalar::String(v.into()))
+    }
+
+    fn serialize_str(self, v: &str) -> Result<Node, Error> {
+        self.with_arg(Scalar::String(v.into()))
+    }
+
+    fn serialize_bytes(self, v: &[u8]) -> Result<Node, Error> {
+        let mut node = Node::new(self.name);
+        node.args = v
+            .iter()
+            .map(|b| Value::plain(Scalar::Integer(*b as i64)))
+            .collect();
+        Ok(node)
+    }
+
+    fn serialize_none(self) -> Result<Node, Error> {
+        self.with_arg(Scalar::Null)
+    }
+
+    fn serialize_some<T: Serialize + ?Sized>(self, value: &T) -> Result<Node, Error> {
+        value.serialize(self)
+    }
+
+    fn serialize_unit(self) -> Result<Node, Error> {
+        Ok(Node::new(self.name))
+    }
+
+    fn serialize_unit_struct(self, _name: &'static str) -> Result<Node, Error> {
+        self.serialize_unit()
+    }
+
+    fn serialize_unit_variant(
+        self,
+        _name: &'static str,
+        _variant_index: u32,
+        variant: &'static str,
+    ) -> Result<Node, Error> {
+        self.with_arg(Scalar::String(variant.into()))
+    }
+
+    fn serialize_newtype_struct<T: Serialize + ?Sized>(
+        self,
+        _name: &'static str,
+        value: &T,
+    ) -> Result<Node, Error> {
+        value.serialize(self)
+    }
+
+    fn serialize_newtype_variant<T: Serialize + ?Sized>(
+        self,
+        _name: &'static str,
+        _variant_index: u32,
+        variant: &'static str,
+        value: &T,
+    ) -> Result<Node, Error> {
+        match value.serialize(ValueSerializer)? {
+            Entry::Value(Value { ty: None, val }) => {
+                let mut node = Node::new(self.name);
+                node.args.push(Value {
+                    ty: Some(variant.into()),
+                    val,
+                });
+                Ok(node)
+            }
+            _ => {
+                let inner = value.serialize(NodeSerializer {
+                    name: variant.into(),
+                })?;
+                Ok(self.variant(inner))
+            }
+        }
+    }
+
+    fn serialize_seq(self, len: Option<usize>) -> Result<SeqCollector, Error> {
+        Ok(SeqCollector {
+            node: Node::new(self.name),
+            elements: Vec::with_capacity(len.unwrap_or(0)),
+            variant: None,
+        })
+    }
+
+    fn serialize_tuple(self, len: usize) -> Result<SeqCollector, Error> {
+        self.serialize_seq(Some(len))
+    }
+
+    fn serialize_tuple_struct(
+        self,
+        _name: &'static str,
+        len: usize,
+    ) -> Result<SeqCollector, Error> {
+        self.serialize_seq(Some(len))
+    }
+
+    fn serialize_tuple_variant(
+        self,
+        _name: &'static str,
+        _variant_index: u32,
+        variant: &'static str,
+        len: usize,
+    ) -> Result<SeqCollector, Error> {
+        Ok(SeqCollector {
+            node: Node::new(variant.into()),
+            elements: Vec::with_capacity(len),
+            variant: Some(self.name),
+        })
+    }
+
+    fn serialize_map(self, _len: Option<usize>) -> Result<StructCollector, Error> {
+        Ok(StructCollector {
+            node: Node::new(self.name),
+            key: None,
+            variant: None,
+        })
+    }
+
+    fn serialize_struct(self, _name: &'static str, _len: usize) -> Result<StructCollector, Error> {
+        self.serialize_map(None)
+    }
+
+    fn serialize_struct_variant(
+        self,
+        _name: &'static str,
+        _variant_index: u32,
+        variant: &'static str,
+        _len: usize,
+    ) -> Result<StructCollector, Error> {
+        Ok(StructCollector {
+            node: Node::new(variant.into()),
+            key: None,
+            variant: Some(self.name),
+        })
+    }
+}
+
+/// An element of a sequence; plain values are kept as values until it's clear whether they'll be arguments or `-` nodes.
+enum Element {
+    Value(Value),
+    Node(Node),
+}
+
+/// Collects a sequence; plain values become arguments, anything else makes it a children block of `-` nodes.
+struct SeqCollector {
+    node: Node,
+    elements: Vec<Element>,
+    /// For tuple variants, the name of the node wrapping the variant.
+    variant: Option<String>,
+}
+
+impl SeqCollector {
+    fn push<T: Serialize + ?Sized>(&mut self, value: &T) -> Result<(), Error> {
+        let element = match value.serialize(ValueSerializer)? {
+            Entry::Value(value) => Element::Value(value),
+            Entry::Nothing => Element::Value(Value::plain(Scalar::Null)),
+            Entry::Complex => Element::Node(value.serialize(NodeSerializer { name: "-".into() })?),
+        };
+
+        self.elements.push(element);
+        Ok(())
+    }
+
+    fn finish(mut self) -> Result<Node, Error> {
+        if self
+            .elements
+            .iter()
+            .all(|element| matches!(element, Element::Value(_)))
+        {
+            self.node.args = self
+                .elements
+                .into_iter()
+                .filter_map(|element| match element {
+                    Element::Value(value) => Some(value),
+                    Element::Node(_) => None,
+                })
+                .collect();
+        } else {
+            self.node.children = self
+                .elements
+                .into_iter()
+                .map(|element| match element {
+                    Element::Value(value) => {
+                        let mut node = Node::new("-".into());
+                        node.args.push(value);
+                        node
+                    }
+                    Element::Node(node) => node,
+                })
+                .collect();
+        }
+
+        Ok(match self.variant {
+            Some(name) => NodeSerializer { name }.variant(self.node),
+            None => self.node,
+        })
+    }
+}
+
+macro_rules! impl_seq_collector {
+    ($($trait:ident: $method:ident),*) => {
+        $(
+            impl ser::$trait for SeqCollector {
+                type Ok = Node;
+                type Error = Error;
+
+                fn $method<T: Serialize + ?Sized>(&mut self, value: &T) -> Result<(), Error> {
+                    self.push(value)
+                }
+
+                fn end(self) -> Result<Node, Error> {
+                    self.finish()
+                }
+            }
+        )*
+    };
+}
+
+impl_seq_collector! {
+    SerializeSeq: serialize_element,
+    SerializeTuple: serialize_element,
+    SerializeTupleStruct: serialize_field,
+    SerializeTupleVariant: serialize_field
+}
+
+/// Collects the fields of a struct or map into properties, arguments and children.
+struct StructCollector {
+    node: Node,
+    /// The key of a map entry whose value hasn't been serialized yet.
+    key: Option<String>,
+    /// For struct variants, the name of the node wrapping the variant.
+    variant: Option<String>,
+}
+
+impl StructCollector {
+    fn field<T: Serialize + ?Sized>(&mut self, key: String, value: &T) -> Result<(), Error> {
+        if key == "-" {
+            self.node.args = value.serialize(NodeSerializer { name: key })?.args;
+            return Ok(());
+        }
+
+        match value.serialize(ValueSerializer)? {
+            Entry::Value(value) => self.node.props.push((key, value)),
+            Entry::Nothing => (),
+            Entry::Complex => self
+                .node
+                .children
+                .push(value.serialize(NodeSerializer { name: key })?),
+        }
+
+        Ok(())
+    }
+
+    fn finish(self) -> Result<Node, Error> {
+        Ok(match self.variant {
+            Some(name) => NodeSerializer { name }.variant(self.node),
+            None => self.node,
+        })
+    }
+}
+
+impl ser::SerializeStruct for StructCollector {
+    type Ok = Node;
+    type Error = Error;
+
+    fn serialize_field<T: Serialize + ?Sized>(
+        &mut self,
+        key: &'static str,
+        value: &T,
+    ) -> Result<(), Error> {
+        self.field(key.into(), value)
+    }
+
+    fn end(self) -> Result<Node, Error> {
+        self.finish()
+    }
+}
+
+impl ser::SerializeStructVariant for StructCollector {
+    type Ok = Node;
+    type Error = Error;
+
+    fn serialize_field<T: Serialize + ?Sized>(
+        &mut self,
+        key: &'static str,
+        value: &T,
+    ) -> Result<(), Error> {
+        self.field(key.into(), value)
+    }
+
+    fn end(self) -> Result<Node, Error> {
+        self.finish()
+    }
+}
+
+impl ser::SerializeMap for StructCollector {
+    type Ok = Node;
+    type Error = Error;
+
+    fn serialize_key<T: Serialize + ?Sized>(&mut self, key: &T) -> Result<(), Error> {
+        self.key = Some(map_key(key)?);
+        Ok(())
+    }
+
+    fn serialize_value<T: Serialize + ?Sized>(&mut self, value: &T) -> Result<(), Error> {
+        let key = self.key.take().ok_or(Error::KeyMustBeAString)?;
+        self.field(key, value)
+    }
+
+    fn end(self) -> Result<Node, Error> {
+        self.finish()
+    }
+}
+
+/// Turns a map key into a string.
+fn map_key<T: Serialize + ?Sized>(key: &T) -> Result<String, Error> {
+    match key.serialize(ValueSerializer)? {
+        Entry::Value(Value {
+            ty: None,
+            val: Scalar::String(s),
+        }) => Ok(s),
+        Entry::Value(Value {
+            ty: None,
+            val: Scalar::Integer(i),
+        }) => Ok(i.to_string()),
+        Entry::Value(Value {
+            ty: None,
+            val: Scalar::Bool(b),
+        }) => Ok(b.to_string()),
+        _ => Err(Error::KeyMustBeAString),
+    }
+}
+
+/// Serializes a struct, map or sequence as the top-level nodes of a document.
+struct DocumentSerializer;
+
+/// Collects top-level nodes.
+struct DocumentCollector {
+    nodes: Vec<Node>,
+    key: Option<String>,
+}
+
+impl DocumentCollector {
+    fn field<T: Serialize + ?Sized>(&mut self, name: String, value: &T) -> Result<(), Error> {
+        if !matches!(value.serialize(ValueSerializer)?, Entry::Nothing) {
+            self.nodes.push(value.serialize(NodeSerializer { name })?);
+        }
+
+        Ok(())
+    }
+}
+
+impl ser::Serializer for DocumentSerializer {
+    type Ok = Vec<Node>;
+    type Error = Error;
+    type SerializeSeq = DocumentCollector;
+    type SerializeTuple = DocumentCollector;
+    type SerializeTupleStruct = DocumentCollector;
+    type SerializeTupleVariant = Impossible<Vec<Node>, Error>;
+    type SerializeMap = DocumentCollector;
+    type SerializeStruct = DocumentCollector;
+    type SerializeStructVariant = Impossible<Vec<Node>, Error>;
+
+    fn serialize_bool(self, _v: bool) -> Result<Vec<Node>, Error> {
+        Err(Error::NotADocument)
+    }
+
+    fn serialize_i8(self, _v: i8) -> Result<Vec<Node>, Error> {
+        Err(Error::NotADocument)
+    }
+
+    fn serialize_i16(self, _v: i16) -> Result<Vec<Node>, Error> {
+        Err(Error::NotADocument)
+    }
+
+    fn serialize_i32(self, _v: i32) -> Result<Vec<Node>, Error> {
+        Err(Error::NotADocument)
+    }
+
+    fn serialize_i64(self, _v: i64) -> Result<Vec<Node>, Error> {
+        Err(Error::NotADocument)
+    }
+
+    fn serialize_u8(self, _v: u8) -> Result<Vec<Node>, Error> {
+        Err(Error::NotADocument)
+    }
+
+    fn serialize_u16(self, _v: u16) -> Result<Vec<Node>, Error> {
+        Err(Error::NotADocument)
+    }
+
+    fn serialize_u32(self, _v: u32) -> Result<Vec<Node>, Error> {
+        Err(Error::NotADocument)
+    }
+
+    fn serialize_u64(self, _v: u64) -> Result<Vec<Node>, Error> {
+        Err(Error::NotADocument)
+    }
+
+    fn serialize_f32(self, _v: f32) -> Result<Vec<Node>, Error> {
+        Err(Error::NotADocument)
+    }
+
+    fn serialize_f64(self, _v: f64) -> Result<Vec<Node>, Error> {
+        Err(Error::NotADocument)
+    }
+
+    fn serialize_char(self, _v: char) -> Result<Vec<Node>, Error> {
+        Err(Error::NotADocument)
+    }
+
+    fn serialize_str(self, _v: &str) -> Result<Vec<Node>, Error> {
+        Err(Error::NotADocument)
+    }
+
+    fn serialize_bytes(self, _v: &[u8]) -> Result<Vec<Node>, Error> {
+        Err(Error::NotADocument)
+    }
+
+    fn serialize_none(self) -> Result<Vec<Node>, Error> {
+        Ok(Vec::new())
+    }
+
+    fn serialize_some<T: Serialize + ?Sized>(self, value: &T) -> Result<Vec<Node>, Error> {
+        value.serialize(self)
+    }
+
+    fn serialize_unit(self) -> Result<Vec<Node>, Error> {
+        Ok(Vec::new())
+    }
+
+    fn serialize_unit_struct(self, _name: &'static str) -> Result<Vec<Node>, Error> {
+        Ok(Vec::new())
+    }
+
+    fn serialize_unit_variant(
+        self,
+        _name: &'static str,
+        _variant_index: u32,
+        _variant: &'static str,
+    ) -> Result<Vec<Node>, Error> {
+        Err(Error::NotADocument)
+    }
+
+    fn serialize_newtype_struct<T: Serialize + ?Sized>(
+        self,
+        _name: &'static str,
+        value: &T,
+    ) -> Result<Vec<Node>, Error> {
+        value.serialize(self)
+    }
+
+    fn serialize_newtype_variant<T: Serialize + ?Sized>(
+        self,
+        _name: &'static str,
+        _variant_index: u32,
+        _variant: &'static str,
+        _value: &T,
+    ) -> Result<Vec<Node>, Error> {
+        Err(Error::NotADocument)
+    }
+
+    fn serialize_seq(self, len: Option<usize>) -> Result<DocumentCollector, Error> {
+        Ok(DocumentCollector {
+            nodes: Vec::with_capacity(len.unwrap_or(0)),
+            key: None,
+        })
+    }
+
+    fn serialize_tuple(self, len: usize) -> Result<DocumentCollector, Error> {
+        self.serialize_seq(Some(len))
+    }
+
+    fn serialize_tuple_struct(
+        self,
+        _name: &'static str,
+        len: usize,
+    ) -> Result<DocumentCollector, Error> {
+        self.serialize_seq(Some(len))
+    }
+
+    fn serialize_tuple_variant(
+        self,
+        _name: &'static str,
+        _variant_index: u32,
+        _variant: &'static str,
+        _len: usize,
+    ) -> Result<Self::SerializeTupleVariant, Error> {
+        Err(Error::NotADocument)
+    }
+
+    fn serialize_map(self, len: Option<usize>) -> Result<DocumentCollector, Error> {
+        self.serialize_seq(len)
+    }
+
+    fn serialize_struct(self, _name: &'static str, len: usize) -> Result<DocumentCollector, Error> {
+        self.serialize_seq(Some(len))
+    }
+
+    fn serialize_struct_variant(
+        self,
+        _name: &'static str,
+        _variant_index: u32,
+        _variant: &'static str,
+        _len: usize,
+    ) -> Result<Self::SerializeStructVariant, Error> {
+        Err(Error::NotADocument)
+    }
+}
+
+macro_rules! impl_document_seq {
+    ($($trait:ident: $method:ident),*) => {
+        $(
+            impl ser::$trait for DocumentCollector {
+                type Ok = Vec<Node>;
+                type Error = Error;
+
+                fn $method<T: Serialize + ?Sized>(&mut self, value: &T) -> Result<(), Error> {
+                    self.field("-".into(), value)
+                }
+
+                fn end(self) -> Result<Vec<Node>, Error> {
+                    Ok(self.nodes)
+                }
+            }
+        )*
+    };
+}
+
+impl_document_seq! {
+    SerializeSeq: serialize_element,
+    SerializeTuple: serialize_element,
+    SerializeTupleStruct: serialize_field
+}
+
+impl ser::SerializeStruct for DocumentCollector {
+    type Ok = Vec<Node>;
+    type Error = Error;
+
+    fn serialize_field<T: Serialize + ?Sized>(
+        &mut self,
+        key: &'static str,
+        value: &T,
+    ) -> Result<(), Error> {
+        self.field(key.into(), value)
+    }
+
+    fn end(self) -> Result<Vec<Node>, Error> {
+        Ok(self.nodes)
+    }
+}
+
+impl ser::SerializeMap for DocumentCollector {
+    type Ok = Vec<Node>;
+    type Error = Error;
+
+    fn serialize_key<T: Serialize + ?Sized>(&mut self, key: &T) -> Result<(), Error> {
+        self.key = Some(map_key(key)?);
+        Ok(())
+    }
+
+    fn serialize_value<T: Serialize + ?Sized>(&mut self, value: &T) -> Result<(), Error> {
+        let key = self.key.take().ok_or(Error::KeyMustBeAString)?;
+        self.field(key, value)
+    }
+
+    fn end(self) -> Result<Vec<Node>, Error> {
+        Ok(self.nodes)
+    }
+}
@@ -84,6 +84,13 @@ use alloc::string::String;
 #[cfg(any(feature = "std", feature = "alloc"))]
 pub(crate) fn unescape_std(s: &str) -> Result<String, ParseError> {
     let mut buf = String::with_capacity(s.len());
+    unescape_into(s, &mut buf)?;
+    Ok(buf)
+}
+
+/// Unescapes `s` onto the end of `buf`.
+#[cfg(any(feature = "std", feature = "alloc"))]
+pub(crate) fn unescape_into(s: &str, buf: &mut String) -> Result<(), ParseError> {
     let mut chars = s.chars();
 
     loop {
@@ -133,5 +140,5 @@ pub(crate) fn unescape_std(s: &str) -> Result<String, ParseError> {
         }
     }
 
-    Ok(buf)
+    Ok(())
 }
@@ -0,0 +1,398 @@
+use std::borrow::Cow;
+use std::fmt::{self, Write};
+use std::io::BufRead;
+
+use quick_xml::escape::escape;
+use quick_xml::events::{BytesStart, Event};
+use quick_xml::Reader;
+
+use crate::ast::*;
+use crate::format::{FormatOptions, Formatter};
+use crate::lex::Token;
+use crate::parser::Parser;
+use crate::{KdlEvent, ParseError};
+
+/// What went wrong while transcoding.
+#[derive(Debug)]
+pub enum XikError {
+    Parse(ParseError),
+    Xml(quick_xml::Error),
+    /// A node name or property key that isn't a valid XML name.
+    InvalidName(String),
+    /// Comment, doctype or processing instruction text that would end it early, e.g. `-->` in a comment.
+    InvalidText(String),
+    Write(fmt::Error),
+}
+
+impl fmt::Display for XikError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            XikError::Parse(e) => write!(f, "parse error: {:?}", e),
+            XikError::Xml(e) => write!(f, "xml error: {}", e),
+            XikError::InvalidName(name) => write!(f, "`{}` isn't a valid XML name", name),
+            XikError::InvalidText(text) => write!(f, "`{}` would end it's markup early", text),
+            XikError::Write(e) => write!(f, "{}", e),
+        }
+    }
+}
+
+impl std::error::Error for XikError {}
+
+impl From<quick_xml::Error> for XikError {
+    fn from(e: quick_xml::Error) -> XikError {
+        XikError::Xml(e)
+    }
+}
+
+impl From<quick_xml::encoding::EncodingError> for XikError {
+    fn from(e: quick_xml::encoding::EncodingError) -> XikError {
+        XikError::Xml(e.into())
+    }
+}
+
+impl From<quick_xml::events::attributes::AttrError> for XikError {
+    fn from(e: quick_xml::events::attributes::AttrError) -> XikError {
+        XikError::Xml(e.into())
+    }
+}
+
+impl From<fmt::Error> for XikError {
+    fn from(e: fmt::Error) -> XikError {
+        XikError::Write(e)
+    }
+}
+
+/// Transcodes XML into XiK, writing each node out as soon as it's read; only the current element's text is buffered.
+///
+/// - elements become nodes, and their attributes become properties.
+/// - an element holding only text gets it as it's single argument, e.g. `title "Hello"`.
+/// - otherwise text becomes `-` nodes among the children; text that's only whitespace is dropped.
+/// - comments become `!` nodes, the doctype a `!doctype` node, and processing instructions `?target` nodes, with their contents as an argument. The XML declaration becomes `?xml`, with it's pseudo-attributes as properties.
+pub fn xml_to_kdl<R: BufRead, W: Write>(
+    input: R,
+    out: W,
+    options: &FormatOptions,
+) -> Result<W, XikError> {
+    let mut reader = Reader::from_reader(input);
+    let mut formatter = Formatter::new(out, options);
+    let mut buf = Vec::new();
+    let mut open = Vec::new();
+    // an event read ahead while checking whether an element only holds text
+    let mut pending: Option<Event<'static>> = None;
+
+    loop {
+        let event = match pending.take() {
+            Some(event) => event,
+            None => {
+                buf.clear();
+                reader.read_event_into(&mut buf)?.into_owned()
+            }
+        };
+
+        match event {
+            Event::Start(start) => {
+                let name = reader.decoder().decode(start.name().as_ref())?.into_owned();
+                let attrs = attributes(&reader, &start)?;
+
+                let mut text = String::new();
+                let next = loop {
+                    buf.clear();
+                    match reader.read_event_into(&mut buf)? {
+                        Event::Text(t) => text.push_str(&t.unescape()?),
+                        Event::CData(c) => text.push_str(&c.decode()?),
+                        other => break other.into_owned(),
+                    }
+                };
+
+                if let Event::End(_) = next {
+                    let text = Some(text.as_str()).filter(|text| !text.is_empty());
+                    write_node(&mut formatter, &name, &attrs, text, false)?;
+                } else {
+                    write_node(&mut formatter, &name, &attrs, None, true)?;
+                    write_text(&mut formatter, &text)?;
+                    open.push(name);
+                    pending = Some(next);
+                }
+            }
+            Event::Empty(start) => {
+                let name = reader.decoder().decode(start.name().as_ref())?.into_owned();
+                let attrs = attributes(&reader, &start)?;
+                write_node(&mut formatter, &name, &attrs, None, false)?;
+            }
+            Event::End(_) => {
+                let name = open.pop().unwrap_or_default();
+                formatter
+                    .write_event(&KdlEvent::BracketedNodeClose(KdlString::Escapeless(&name)))?;
+            }
+            Event::Text(t) => write_text(&mut formatter, &t.unescape()?)?,
+            Event::CData(c) => write_text(&mut formatter, &c.decode()?)?,
+            Event::Comment(c) => {
+                let text = reader.decoder().decode(&c)?;
+                write_node(&mut formatter, "!", &[], Some(&text), false)?;
+            }
+            Event::DocType(d) => {
+                let text = reader.decoder().decode(&d)?;
+                write_node(&mut formatter, "!doctype", &[], Some(text.trim()), false)?;
+            }
+            Event::PI(pi) => {
+                let target = reader.decoder().decode(pi.target())?;
+                let content = reader.decoder().decode(pi.content())?;
+                let content = Some(content.trim()).filter(|content| !content.is_empty());
+                write_node(&mut formatter, &format!("?{}", target), &[], content, false)?;
+            }
+            Event::Decl(decl) => {
+                let mut attrs = vec![(
+                    "version".to_string(),
+                    reader.decoder().decode(&decl.version()?)?.into_owned(),
+                )];
+                if let Some(encoding) = decl.encoding() {
+                    let encoding = reader.decoder().decode(&encoding?)?.into_owned();
+                    attrs.push(("encoding".into(), encoding));
+                }
+                if let Some(standalone) = decl.standalone() {
+                    let standalone = reader.decoder().decode(&standalone?)?.into_owned();
+                    attrs.push(("standalone".into(), standalone));
+                }
+
+                write_node(&mut formatter, "?xml", &attrs, None, false)?;
+            }
+            Event::Eof => break,
+        }
+    }
+
+    Ok(formatter.into_inner())
+}
+
+/// Transcodes an XML string into XiK.
+pub fn xml_str_to_kdl(input: &str, options: &FormatOptions) -> Result<String, XikError> {
+    xml_to_kdl(input.as_bytes(), String::new(), options)
+}
+
+/// Transcodes XiK into XML straight from the parser's events, the reverse of [xml_to_kdl].
+///
+/// A node's arguments are concatenated into it's text, which comes before any of it's children. The output isn't indented, since that would change the text of mixed content; top-level nodes are put on their own lines.
+pub fn kdl_to_xml<'a, T: Iterator<Item = Token<'a>>, W: Write>(
+    parser: Parser<'a, T>,
+    mut out: W,
+) -> Result<W, XikError> {
+    // the closing tag for each node with a children block, if it's an element
+    let mut open: Vec<Option<String>> = Vec::new();
+
+    for event in parser {
+        match event.map_err(XikError::Parse)? {
+            KdlEvent::NodeOpen {
+                name,
+                attrs,
+                values,
+                has_children,
+                ..
+            } => {
+                let name = name.unescape().map_err(XikError::Parse)?;
+                let close = write_element(&mut out, &name, &attrs, &values, has_children)?;
+
+                if has_children {
+                    open.push(close);
+                } else if open.is_empty() {
+                    out.write_char('\n')?;
+                }
+            }
+            KdlEvent::NodeClose(_) => (),
+            KdlEvent::BracketedNodeClose(_) => {
+                if let Some(Some(tag)) = open.pop() {
+                    write!(out, "</{}>", tag)?;
+                }
+
+                if open.is_empty() {
+                    out.write_char('\n')?;
+                }
+            }
+        }
+    }
+
+    Ok(out)
+}
+
+/// Transcodes a XiK string into XML.
+pub fn kdl_str_to_xml(input: &str) -> Result<String, XikError> {
+    kdl_to_xml(Parser::from_str(input), String::new())
+}
+
+fn attributes<R>(
+    reader: &Reader<R>,
+    start: &BytesStart<'_>,
+) -> Result<Vec<(String, String)>, XikError> {
+    let mut attrs = Vec::new();
+
+    for attr in start.attributes() {
+        let attr = attr?;
+        let key = reader.decoder().decode(attr.key.as_ref())?.into_owned();
+        let value = attr
+            .decode_and_unescape_value(reader.decoder())?
+            .into_owned();
+        attrs.push((key, value));
+    }
+
+    Ok(attrs)
+}
+
+fn write_node<W: Write>(
+    formatter: &mut Formatter<'_, W>,
+    name: &str,
+    attrs: &[(String, String)],
+    text: Option<&str>,
+    has_children: bool,
+) -> fmt::Result {
+    let name = KdlString::Escapeless(name);
+
+    formatter.write_event(&KdlEvent::NodeOpen {
+        ty: None,
+        name,
+        attrs: attrs
+            .iter()
+            .map(|(key, value)| KdlProperty {
+                key: KdlString::Escapeless(key),
+                value: string(value),
+            })
+            .collect(),
+        values: text.map(string).into_iter().collect(),
+        has_children,
+    })?;
+
+    if has_children {
+        Ok(())
+    } else {
+        formatter.write_event(&KdlEvent::NodeClose(name))
+    }
+}
+
+/// Writes text in mixed content as a `-` node, unless it's only whitespace.
+fn write_text<W: Write>(formatter: &mut Formatter<'_, W>, text: &str) -> fmt::Result {
+    if text.trim().is_empty() {
+        return Ok(());
+    }
+
+    write_node(formatter, "-", &[], Some(text), false)
+}
+
+fn string(s: &str) -> TypedValue<'_> {
+    TypedValue {
+        ty: None,
+        val: KdlValue::String(KdlString::Escapeless(s)),
+    }
+}
+
+/// Writes a node's opening tag and text, returning the closing tag if it's still open.
+fn write_element<W: Write>(
+    out: &mut W,
+    name: &str,
+    attrs: &[KdlProperty<'_>],
+    values: &[TypedValue<'_>],
+    has_children: bool,
+) -> Result<Option<String>, XikError> {
+    let mut text = String::new();
+    for value in values {
+        text.push_str(&text_of(&value.val)?);
+    }
+
+    match name {
+        "-" => out.write_str(&escape(text))?,
+        "!" => {
+            check_text(&text, !text.contains("--") && !text.ends_with('-'))?;
+            write!(out, "<!--{}-->", text)?;
+        }
+        _ if name.eq_ignore_ascii_case("!doctype") => {
+            check_text(&text, doctype_closed(&text))?;
+            write!(out, "<!DOCTYPE {}>", text)?;
+        }
+        _ if name.starts_with('?') => {
+            check_name(&name[1..])?;
+            check_text(&text, !text.contains("?>"))?;
+            write!(out, "<{}", name)?;
+            write_attrs(out, attrs)?;
+            if !text.is_empty() {
+                write!(out, " {}", text)?;
+            }
+            out.write_str("?>")?;
+        }
+        _ => {
+            check_name(name)?;
+            write!(out, "<{}", name)?;
+            write_attrs(out, attrs)?;
+
+            if text.is_empty() && !has_children {
+                out.write_str("/>")?;
+            } else {
+                write!(out, ">{}", escape(text))?;
+
+                if has_children {
+                    return Ok(Some(name.into()));
+                }
+                write!(out, "</{}>", name)?;
+            }
+        }
+    }
+
+    Ok(None)
+}
+
+fn write_attrs<W: Write>(out: &mut W, attrs: &[KdlProperty<'_>]) -> Result<(), XikError> {
+    for attr in attrs {
+        let key = attr.key.unescape().map_err(XikError::Parse)?;
+        check_name(&key)?;
+        write!(out, " {}=\"{}\"", key, escape(text_of(&attr.value.val)?))?;
+    }
+
+    Ok(())
+}
+
+fn text_of<'a>(val: &KdlValue<'a>) -> Result<Cow<'a, str>, XikError> {
+    Ok(match *val {
+        KdlValue::String(s) => s.unescape().map_err(XikError::Parse)?,
+        KdlValue::Integer(i) => Cow::Owned(i.to_string()),
+        KdlValue::Float(f) => Cow::Owned(format!("{:?}", f)),
+        KdlValue::Bool(b) => Cow::Owned(b.to_string()),
+        KdlValue::Null => Cow::Borrowed("null"),
+    })
+}
+
+/// A loose check that a name can go in a tag: no whitespace or markup characters, and not starting with a digit, `-` or `.`.
+fn check_name(name: &str) -> Result<(), XikError> {
+    let valid = !name.is_empty()
+        && !name.starts_with(|c: char| c.is_ascii_digit() || c == '-' || c == '.')
+        && !name
+            .chars()
+            .any(|c| c.is_whitespace() || "<>&\"'=/!?".contains(c));
+
+    if valid {
+        Ok(())
+    } else {
+        Err(XikError::InvalidName(name.into()))
+    }
+}
+
+fn check_text(text: &str, valid: bool) -> Result<(), XikError> {
+    if valid {
+        Ok(())
+    } else {
+        Err(XikError::InvalidText(text.into()))
+    }
+}
+
+/// Whether doctype text leaves the `<!DOCTYPE` open until we close it: any `>` has to be inside the `[...]` internal subset.
+fn doctype_closed(text: &str) -> bool {
+    let mut depth = 0usize;
+
+    for c in text.chars() {
+        match c {
+            '[' => depth += 1,
+            ']' => match depth.checked_sub(1) {
+                Some(d) => depth = d,
+                None => return false,
+            },
+            '>' if depth == 0 => return false,
+            _ => (),
+        }
+    }
+
+    depth == 0
+}
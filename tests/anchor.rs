@@ -0,0 +1,80 @@
+use sleepyhead_kdl::anchor::*;
+use sleepyhead_kdl::assembler::KdlDocument;
+use sleepyhead_kdl::format::{format_document, FormatOptions};
+
+#[test]
+fn resolves_references() {
+    let doc = KdlDocument::parse(
+        r#"
+templates {
+    base (anchor)"base" retries=3
+    service (anchor)"service" (ref)"base" timeout=30 {
+        log-level "info"
+    }
+}
+api "api.example.com" (ref)"service" timeout=60 {
+    port 8080
+}
+"#,
+    )
+    .unwrap();
+
+    assert_eq!(
+        format_document(&resolve(&doc).unwrap().nodes, &FormatOptions::default()).unwrap(),
+        r#"templates {
+    base retries=3
+    service retries=3 timeout=30 {
+        log-level "info"
+    }
+}
+api "api.example.com" retries=3 timeout=60 {
+    log-level "info"
+    port 8080
+}
+"#
+    );
+}
+
+#[test]
+fn reports_bad_references() {
+    let err = resolve(&KdlDocument::parse("a {\n    b (ref)\"missing\"\n}").unwrap()).unwrap_err();
+    assert!(matches!(err.kind, AnchorErrorKind::Undefined(ref name) if name == "missing"));
+    assert_eq!(err.to_string(), "a > b: no anchor named `missing`");
+
+    let doc = KdlDocument::parse(
+        "a (anchor)\"a\" {\n    b (anchor)\"b\" (ref)\"c\"\n}\nc (anchor)\"c\" (ref)\"a\"\n",
+    )
+    .unwrap();
+    let err = resolve(&doc).unwrap_err();
+    assert!(matches!(err.kind, AnchorErrorKind::Cycle(ref names) if names == &["a", "b", "c"]));
+    assert_eq!(
+        err.to_string(),
+        "a > b > c > a: anchor `a` references itself through `b`, `c`"
+    );
+
+    let err =
+        resolve(&KdlDocument::parse("a (anchor)\"x\"\nb (anchor)\"x\"").unwrap()).unwrap_err();
+    assert!(matches!(err.kind, AnchorErrorKind::Duplicate(_)));
+
+    let err = resolve(&KdlDocument::parse("a (ref)1").unwrap()).unwrap_err();
+    assert!(matches!(err.kind, AnchorErrorKind::NotAName));
+}
+
+#[test]
+fn resolves_each_anchor_once() {
+    // each anchor references the one before it twice, so following every reference would take 2^64 steps
+    let mut input = String::from("a0 (anchor)\"a0\" x=1\n");
+    for i in 1..64 {
+        input.push_str(&format!(
+            "a{0} (anchor)\"a{0}\" (ref)\"a{1}\" (ref)\"a{1}\"\n",
+            i,
+            i - 1
+        ));
+    }
+    input.push_str("top (ref)\"a63\"\n");
+
+    let resolved = resolve(&KdlDocument::parse(&input).unwrap()).unwrap();
+    let top = resolved.get("top").unwrap();
+    assert_eq!(top.attrs.len(), 1);
+    assert_eq!(top.prop("x"), resolved.nodes[0].prop("x"));
+}
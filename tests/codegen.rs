@@ -0,0 +1,55 @@
+#![cfg(feature = "schema")]
+
+use sleepyhead_kdl::codegen::*;
+use sleepyhead_kdl::decode::{decode_str, Value};
+use sleepyhead_kdl::schema::Schema;
+
+mod service {
+    include!("codegen/service.rs");
+}
+
+#[test]
+fn generates_decodable_types() {
+    let schema = Schema::parse(include_str!("codegen/service.kdl")).unwrap();
+    let code = generate(&schema, &CodegenOptions::default());
+
+    // the generated file is included above, so this also checks it compiles
+    assert_eq!(code, include_str!("codegen/service.rs"));
+
+    let doc: service::Document = decode_str(
+        "server \"web\" port=8080 mode=\"prod\" {\n    timeout 2.5\n    route \"/\" \"/static\"\n}\ntags \"a\" 1\n",
+    )
+    .unwrap();
+
+    let server = &doc.server[0];
+    assert_eq!(server.value, "web");
+    assert_eq!(server.port, 8080);
+    assert_eq!(server.mode, Some(service::ServerMode::Prod));
+    assert_eq!(server.timeout, Some(service::Timeout { value: 2.5 }));
+    assert_eq!(server.route[0].values, vec!["/", "/static"]);
+    assert_eq!(
+        doc.tags.unwrap().values,
+        vec![Value::String("a".into()), Value::Integer(1)]
+    );
+
+    let err = decode_str::<service::Document>("server \"web\" mode=\"test\" port=1\n").unwrap_err();
+    assert_eq!(err.to_string(), "server: invalid value: test");
+}
+
+#[test]
+fn generates_from_the_meta_schema() {
+    let schema = Schema::parse(include_str!("../examples/schema.kdl")).unwrap();
+    let code = generate(
+        &schema,
+        &CodegenOptions {
+            document_name: "Schema".into(),
+            derives: vec!["Debug".into()],
+        },
+    );
+
+    assert!(code.contains("pub struct Schema {\n    pub document: Document,\n}"));
+    assert!(code.contains("pub enum LinkRel {\n    SelfValue,\n    Documentation,\n}"));
+    // `tag` rules can nest, so the single child has to be boxed
+    assert!(code.contains("pub tag: Option<Box<"));
+    assert!(!code.contains("PartialEq"));
+}
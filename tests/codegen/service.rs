@@ -0,0 +1,122 @@
+// Generated by sleepyhead-kdl from a KDL Schema; don't edit by hand.
+
+#[derive(Debug, Clone, PartialEq)]
+pub struct Document {
+    /// A server to run
+    pub server: Vec<Server>,
+    pub tags: Option<Tags>,
+}
+
+impl<'kdl> ::sleepyhead_kdl::decode::DecodeKdl<'kdl> for Document {
+    fn decode_node(
+        node: &::sleepyhead_kdl::assembler::KdlNode<'kdl>,
+    ) -> ::core::result::Result<Self, ::sleepyhead_kdl::decode::DecodeError> {
+        ::core::result::Result::Ok(Document {
+            server: ::sleepyhead_kdl::decode::children(node, Some("server"))?,
+            tags: ::sleepyhead_kdl::decode::child(node, "tags")?,
+        })
+    }
+}
+
+/// A server to run
+#[derive(Debug, Clone, PartialEq)]
+pub struct Server {
+    pub value: String,
+    pub port: u16,
+    pub mode: Option<ServerMode>,
+    pub timeout: Option<Timeout>,
+    pub route: Vec<Route>,
+}
+
+impl<'kdl> ::sleepyhead_kdl::decode::DecodeKdl<'kdl> for Server {
+    fn decode_node(
+        node: &::sleepyhead_kdl::assembler::KdlNode<'kdl>,
+    ) -> ::core::result::Result<Self, ::sleepyhead_kdl::decode::DecodeError> {
+        ::core::result::Result::Ok(Server {
+            value: ::sleepyhead_kdl::decode::argument(node, 0)?.ok_or_else(|| {
+                ::sleepyhead_kdl::decode::missing(
+                    node,
+                    ::sleepyhead_kdl::decode::DecodeErrorKind::MissingArgument(0),
+                )
+            })?,
+            port: ::sleepyhead_kdl::decode::property(node, "port")?.ok_or_else(|| {
+                ::sleepyhead_kdl::decode::missing(
+                    node,
+                    ::sleepyhead_kdl::decode::DecodeErrorKind::MissingProperty("port".into()),
+                )
+            })?,
+            mode: ::sleepyhead_kdl::decode::property(node, "mode")?,
+            timeout: ::sleepyhead_kdl::decode::child(node, "timeout")?,
+            route: ::sleepyhead_kdl::decode::children(node, Some("route"))?,
+        })
+    }
+}
+
+#[derive(Debug, Clone, PartialEq)]
+pub struct Tags {
+    pub values: Vec<::sleepyhead_kdl::decode::Value>,
+}
+
+impl<'kdl> ::sleepyhead_kdl::decode::DecodeKdl<'kdl> for Tags {
+    fn decode_node(
+        node: &::sleepyhead_kdl::assembler::KdlNode<'kdl>,
+    ) -> ::core::result::Result<Self, ::sleepyhead_kdl::decode::DecodeError> {
+        ::core::result::Result::Ok(Tags {
+            values: ::sleepyhead_kdl::decode::arguments(node, 0)?,
+        })
+    }
+}
+
+#[derive(Debug, Clone, PartialEq)]
+pub struct Timeout {
+    pub value: f64,
+}
+
+impl<'kdl> ::sleepyhead_kdl::decode::DecodeKdl<'kdl> for Timeout {
+    fn decode_node(
+        node: &::sleepyhead_kdl::assembler::KdlNode<'kdl>,
+    ) -> ::core::result::Result<Self, ::sleepyhead_kdl::decode::DecodeError> {
+        ::core::result::Result::Ok(Timeout {
+            value: ::sleepyhead_kdl::decode::argument(node, 0)?.ok_or_else(|| {
+                ::sleepyhead_kdl::decode::missing(
+                    node,
+                    ::sleepyhead_kdl::decode::DecodeErrorKind::MissingArgument(0),
+                )
+            })?,
+        })
+    }
+}
+
+#[derive(Debug, Clone, PartialEq)]
+pub struct Route {
+    pub values: Vec<String>,
+}
+
+impl<'kdl> ::sleepyhead_kdl::decode::DecodeKdl<'kdl> for Route {
+    fn decode_node(
+        node: &::sleepyhead_kdl::assembler::KdlNode<'kdl>,
+    ) -> ::core::result::Result<Self, ::sleepyhead_kdl::decode::DecodeError> {
+        ::core::result::Result::Ok(Route {
+            values: ::sleepyhead_kdl::decode::arguments(node, 0)?,
+        })
+    }
+}
+
+#[derive(Debug, Clone, PartialEq)]
+pub enum ServerMode {
+    Dev,
+    Prod,
+}
+
+impl<'kdl> ::sleepyhead_kdl::decode::DecodeValue<'kdl> for ServerMode {
+    fn decode_value(
+        value: &::sleepyhead_kdl::ast::TypedValue<'kdl>,
+    ) -> ::core::result::Result<Self, ::sleepyhead_kdl::decode::DecodeErrorKind> {
+        let s: ::std::borrow::Cow<'kdl, str> = ::sleepyhead_kdl::decode::DecodeValue::decode_value(value)?;
+        match &*s {
+            "dev" => ::core::result::Result::Ok(ServerMode::Dev),
+            "prod" => ::core::result::Result::Ok(ServerMode::Prod),
+            _ => ::core::result::Result::Err(::sleepyhead_kdl::decode::DecodeErrorKind::InvalidValue(s.into_owned())),
+        }
+    }
+}
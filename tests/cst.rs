@@ -0,0 +1,124 @@
+use sleepyhead_kdl::ast::*;
+use sleepyhead_kdl::cst::*;
+
+const CONFIG: &str = r#"// deploy config
+service "api" {
+    image "registry/api:1.2.3" pull=(policy)"always"   // pinned
+    /-replicas 3
+    port 8080 ; healthcheck path="/health" \
+        interval=30
+}
+
+/* trailing comment */
+"#;
+
+#[test]
+fn renders_back_byte_identical() {
+    assert_eq!(CstDocument::parse(CONFIG).unwrap().to_string(), CONFIG);
+
+    let schema = include_str!("../examples/schema.kdl");
+    assert_eq!(CstDocument::parse(schema).unwrap().to_string(), schema);
+
+    for entry in std::fs::read_dir("tests/input").unwrap() {
+        let path = entry.unwrap().path();
+        let input = std::fs::read_to_string(&path).unwrap();
+        if let Ok(doc) = CstDocument::parse(&input) {
+            assert_eq!(
+                doc.to_string(),
+                input,
+                "{} didn't round-trip",
+                path.display()
+            );
+        }
+    }
+}
+
+#[test]
+fn edits_only_touch_what_they_change() {
+    let mut doc = CstDocument::parse(CONFIG).unwrap();
+    let service = doc.get_mut("service").unwrap();
+
+    let image = service.get_mut("image").unwrap();
+    image.set_arg(
+        0,
+        &TypedValue {
+            ty: None,
+            val: KdlValue::String(KdlString::Escapeless("registry/api:1.3.0")),
+        },
+    );
+    assert_eq!(
+        image.prop("pull").unwrap().val,
+        KdlValue::String(KdlString::Escapeless("always"))
+    );
+
+    service.get_mut("healthcheck").unwrap().set_prop(
+        "interval",
+        &TypedValue {
+            ty: None,
+            val: KdlValue::Integer(10),
+        },
+    );
+
+    let mut env = CstNode::new("env");
+    env.set_prop(
+        "LOG LEVEL",
+        &TypedValue {
+            ty: None,
+            val: KdlValue::String(KdlString::Escapeless("debug")),
+        },
+    );
+    service.insert_child(3, env);
+    service.remove_child(0);
+
+    assert_eq!(
+        doc.to_string(),
+        r#"// deploy config
+service "api" {
+    /-replicas 3
+    port 8080 ; healthcheck path="/health" \
+        interval=10
+    env "LOG LEVEL"="debug"
+}
+
+/* trailing comment */
+"#
+    );
+}
+
+#[test]
+fn adds_children_blocks() {
+    let mut doc = CstDocument::parse("a 1\nb 2\n").unwrap();
+    let mut child = CstNode::new("child");
+    child.push_arg(&TypedValue {
+        ty: Some("u8"),
+        val: KdlValue::Integer(1),
+    });
+    doc.get_mut("a").unwrap().insert_child(0, child);
+    doc.insert_node(0, CstNode::new("first"));
+    assert!(!doc.nodes[2].remove_prop("missing"));
+    assert!(doc.remove_node(3).is_none());
+    assert!(doc.nodes[2].remove_child(0).is_none());
+
+    assert_eq!(doc.to_string(), "first\na 1 {\n    child (u8)1\n}\nb 2\n");
+}
+
+#[test]
+fn keeps_node_type_annotations() {
+    let input = "(ty)node 1\n(other)\"quoted\" {\n    (inner)child\n}\n";
+    let mut doc = CstDocument::parse(input).unwrap();
+    assert_eq!(doc.to_string(), input);
+
+    let node = doc.get_mut("node").unwrap();
+    assert_eq!(node.ty(), Some("ty"));
+    assert_eq!(node.args().next().unwrap().val, KdlValue::Integer(1));
+
+    node.set_ty(Some("renamed"));
+    doc.get_mut("quoted").unwrap().set_ty(None);
+    assert_eq!(
+        doc.to_string(),
+        "(renamed)node 1\n\"quoted\" {\n    (inner)child\n}\n"
+    );
+
+    assert!(CstDocument::parse("(ty) node").is_err());
+    assert!(CstDocument::parse("(ty)/*huh*/node").is_err());
+}
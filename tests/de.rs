@@ -0,0 +1,124 @@
+#![cfg(feature = "serde")]
+
+use serde::Deserialize;
+use sleepyhead_kdl::de::{from_str, Error};
+use std::collections::HashMap;
+
+#[derive(Debug, Deserialize, PartialEq)]
+struct Config<'a> {
+    name: &'a str,
+    port: u16,
+    timeout: Timeout,
+    mode: Mode,
+    tags: Vec<String>,
+    upstream: Upstream<'a>,
+    servers: Vec<Server>,
+    retries: Option<u8>,
+}
+
+#[derive(Debug, Deserialize, PartialEq)]
+struct Upstream<'a> {
+    host: &'a str,
+    weight: f64,
+    #[serde(rename = "-")]
+    args: (i64, bool),
+    tls: Option<bool>,
+}
+
+#[derive(Debug, Deserialize, PartialEq)]
+struct Server {
+    id: u32,
+    addr: String,
+}
+
+#[derive(Debug, Deserialize, PartialEq)]
+enum Timeout {
+    Seconds(u64),
+    Never,
+}
+
+#[derive(Debug, Deserialize, PartialEq)]
+enum Mode {
+    Fast,
+    Careful { checks: u8 },
+}
+
+const CONFIG: &str = r#"
+name "api"
+port 8080
+timeout (Seconds)30
+mode {
+    Careful checks=3
+}
+tags "a" "b\tc"
+upstream 1 true host="localhost" weight=0.5 {
+    tls null
+}
+servers {
+    - id=1 addr="10.0.0.1"
+    - id=2 addr="10.0.0.2"
+}
+retries null
+"#;
+
+#[test]
+fn deserializes_structs() {
+    let config: Config = from_str(CONFIG).unwrap();
+
+    assert_eq!(
+        config,
+        Config {
+            name: "api",
+            port: 8080,
+            timeout: Timeout::Seconds(30),
+            mode: Mode::Careful { checks: 3 },
+            tags: vec!["a".into(), "b\tc".into()],
+            upstream: Upstream {
+                host: "localhost",
+                weight: 0.5,
+                args: (1, true),
+                tls: None,
+            },
+            servers: vec![
+                Server {
+                    id: 1,
+                    addr: "10.0.0.1".into(),
+                },
+                Server {
+                    id: 2,
+                    addr: "10.0.0.2".into(),
+                },
+            ],
+            retries: None,
+        }
+    );
+}
+
+#[test]
+fn deserializes_maps_and_unit_variants() {
+    let doc: HashMap<String, Timeout> = from_str("a \"Never\"\nb (Seconds)5").unwrap();
+    assert_eq!(doc["a"], Timeout::Never);
+    assert_eq!(doc["b"], Timeout::Seconds(5));
+
+    let nodes: Vec<(i64, i64)> = from_str("point 1 2\npoint 3 4").unwrap();
+    assert_eq!(nodes, vec![(1, 2), (3, 4)]);
+}
+
+#[test]
+fn skips_unknown_nodes() {
+    #[derive(Debug, Deserialize)]
+    struct Partial {
+        port: u16,
+    }
+
+    let partial: Partial = from_str("other {\n  deep {\n    deeper 1\n  }\n}\nport 1").unwrap();
+    assert_eq!(partial.port, 1);
+}
+
+#[test]
+fn reports_node_names() {
+    match from_str::<HashMap<String, u16>>("port 1 2") {
+        Err(Error::ExpectedSingleArgument(name)) => assert_eq!(name, "port"),
+        other => panic!("unexpected result {:?}", other),
+    }
+}
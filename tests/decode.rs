@@ -0,0 +1,121 @@
+#![cfg(feature = "derive")]
+
+use sleepyhead_kdl::decode::{decode_str, DecodeErrorKind, DecodeKdl};
+
+#[derive(DecodeKdl, Debug, PartialEq)]
+struct Config<'a> {
+    #[kdl(child)]
+    server: Server<'a>,
+    #[kdl(children(name = "route"))]
+    routes: Vec<Route>,
+    #[kdl(child(name = "log-level"))]
+    log_level: Option<LogLevel>,
+}
+
+#[derive(DecodeKdl, Debug, PartialEq)]
+struct Server<'a> {
+    #[kdl(argument)]
+    host: &'a str,
+    #[kdl(argument)]
+    port: u16,
+    #[kdl(property)]
+    workers: Option<u32>,
+    #[kdl(property(name = "tls"), default)]
+    use_tls: bool,
+}
+
+#[derive(DecodeKdl, Debug, PartialEq)]
+struct Route {
+    #[kdl(argument)]
+    path: String,
+    #[kdl(arguments)]
+    methods: Vec<String>,
+    #[kdl(child)]
+    timeout: Option<Timeout>,
+}
+
+#[derive(DecodeKdl, Debug, PartialEq)]
+struct Timeout {
+    #[kdl(type_name)]
+    unit: Option<String>,
+    #[kdl(argument)]
+    amount: u64,
+}
+
+#[derive(DecodeKdl, Debug, PartialEq)]
+struct LogLevel {
+    #[kdl(node_name)]
+    name: String,
+    #[kdl(argument)]
+    level: String,
+}
+
+#[test]
+fn decodes_nested_structs() {
+    let input = r#"
+server "localhost" 8080 workers=2 workers=4
+route "/" "GET" "HEAD"
+route "/upload" "POST" {
+    (seconds)timeout 30
+}
+"#;
+
+    let config: Config = decode_str(input).unwrap();
+
+    assert_eq!(
+        config,
+        Config {
+            server: Server {
+                host: "localhost",
+                port: 8080,
+                workers: Some(4),
+                use_tls: false,
+            },
+            routes: vec![
+                Route {
+                    path: "/".into(),
+                    methods: vec!["GET".into(), "HEAD".into()],
+                    timeout: None,
+                },
+                Route {
+                    path: "/upload".into(),
+                    methods: vec!["POST".into()],
+                    timeout: Some(Timeout {
+                        unit: Some("seconds".into()),
+                        amount: 30,
+                    }),
+                },
+            ],
+            log_level: None,
+        }
+    );
+}
+
+#[test]
+fn errors_carry_path_and_span() {
+    let input = "server \"localhost\" 8080\nroute \"/\" {\n    timeout \"soon\"\n}\n";
+    let err = decode_str::<Config>(input).unwrap_err();
+
+    assert!(matches!(err.kind, DecodeErrorKind::UnexpectedType(_)));
+    assert_eq!(err.path, vec!["route", "timeout"]);
+    assert_eq!(err.to_string(), "route > timeout: expected an integer");
+
+    let span = err.span_in(input).unwrap();
+    assert_eq!(&input[span.clone()], "timeout");
+    assert_eq!(span.start, 40);
+
+    // spans are best-effort: names with escapes are copied, so there's nothing to point back at
+    let input = "server \"localhost\" 8080\nroute \"/\" {\n    \"time\\u{6f}ut\" \"soon\"\n}\n";
+    let err = decode_str::<Config>(input).unwrap_err();
+    assert_eq!(err.path, vec!["route", "timeout"]);
+    assert_eq!(err.span_in(input), None);
+
+    let err = decode_str::<Config>("server \"localhost\"\n").unwrap_err();
+    assert!(matches!(err.kind, DecodeErrorKind::MissingArgument(1)));
+
+    let err = decode_str::<Config>("route \"/\"\n").unwrap_err();
+    assert!(matches!(err.kind, DecodeErrorKind::MissingChild(ref name) if name == "server"));
+
+    let err = decode_str::<Config>("server \"localhost\" 70000\n").unwrap_err();
+    assert!(matches!(err.kind, DecodeErrorKind::InvalidValue(_)));
+}
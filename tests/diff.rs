@@ -0,0 +1,96 @@
+use sleepyhead_kdl::assembler::KdlDocument;
+use sleepyhead_kdl::ast::*;
+use sleepyhead_kdl::diff::*;
+
+const OLD: &str = r#"
+server "localhost" 8080 workers=4 {
+    route "/" handler="index"
+    route "/about" handler="about"
+}
+user name="em" role="admin"
+user name="kit" role="user"
+"#;
+
+const NEW: &str = r#"
+server "localhost" 9090 workers=4 timeout=30 {
+    route "/" handler="home"
+    tls
+}
+user name="kit" role="admin"
+user name="em" role="admin"
+"#;
+
+#[test]
+fn diffs_by_name() {
+    let old = KdlDocument::parse(OLD).unwrap();
+    let new = KdlDocument::parse(NEW).unwrap();
+    let diff = diff_documents(&old.nodes, &new.nodes, &DiffOptions::default());
+
+    assert_eq!(
+        diff.changes[0],
+        Change {
+            path: vec![Step {
+                name: "server".into(),
+                index: 0
+            }],
+            kind: ChangeKind::ChangedArg {
+                idx: 1,
+                old: TypedValue {
+                    ty: None,
+                    val: KdlValue::Integer(8080)
+                },
+                new: TypedValue {
+                    ty: None,
+                    val: KdlValue::Integer(9090)
+                },
+            },
+        }
+    );
+
+    assert_eq!(
+        diff.to_string(),
+        r#"server: ~ argument 1: 8080 -> 9090
+server: + timeout=30
+server > route: ~ handler="index" -> handler="home"
+server > route[1]: - route "/about" handler="about"
+server: + tls
+user: ~ name="em" -> name="kit"
+user[1]: ~ name="kit" -> name="em"
+user[1]: ~ role="user" -> role="admin"
+"#
+    );
+}
+
+#[test]
+fn diffs_by_key_or_position() {
+    let old = KdlDocument::parse(OLD).unwrap();
+    let new = KdlDocument::parse(NEW).unwrap();
+
+    let by_key = diff_documents(
+        &old.nodes,
+        &new.nodes,
+        &DiffOptions {
+            matching: Matching::Key("name".into()),
+        },
+    );
+    assert!(by_key
+        .to_string()
+        .ends_with("user[1]: ~ role=\"user\" -> role=\"admin\"\n"));
+    assert!(!by_key.to_string().contains("name="));
+
+    let a = KdlDocument::parse("(list)a 1 {\n    b\n}").unwrap();
+    let b = KdlDocument::parse("c 1 2 {\n    b {\n        d\n    }\n}").unwrap();
+    let by_position = diff(
+        &a.nodes[0],
+        &b.nodes[0],
+        &DiffOptions {
+            matching: Matching::Position,
+        },
+    );
+    assert_eq!(
+        by_position.to_string(),
+        "(root): ~ name: a -> c\n(root): ~ type: list -> none\n(root): + argument 1: 2\nb: + d\n"
+    );
+
+    assert!(diff_documents(&old.nodes, &old.nodes, &DiffOptions::default()).is_empty());
+}
@@ -0,0 +1,153 @@
+use sleepyhead_kdl::assembler::{
+    parse_document, parse_document_with_max_depth, KdlDocument, KdlNode, DEFAULT_MAX_DEPTH,
+};
+use sleepyhead_kdl::ast::*;
+use sleepyhead_kdl::decode::DecodeErrorKind;
+use sleepyhead_kdl::format::{format_document, FormatOptions};
+use sleepyhead_kdl::parser::Parser;
+use sleepyhead_kdl::ParseError;
+
+const DOC: &str = r#"
+server "localhost" 8080 workers=2 workers=4 {
+    tls {
+        cert "server.pem"
+    }
+    route "/"
+    route "/about"
+}
+server "backup" 8081
+"#;
+
+#[test]
+fn looks_up_nodes() {
+    let doc = KdlDocument::parse(DOC).unwrap();
+    let server = doc.get("server").unwrap();
+
+    assert_eq!(doc.get_all("server").count(), 2);
+    assert!(doc.get("client").is_none());
+
+    assert_eq!(server.arg(1).unwrap().val, KdlValue::Integer(8080));
+    assert!(server.arg(2).is_none());
+    assert_eq!(server.prop("workers").unwrap().val, KdlValue::Integer(4));
+    assert!(server.prop("port").is_none());
+
+    let routes: Vec<_> = server
+        .get_all("route")
+        .map(|route| route.arg(0).unwrap().val)
+        .collect();
+    assert_eq!(
+        routes,
+        [
+            KdlValue::String(KdlString::Escapeless("/")),
+            KdlValue::String(KdlString::Escapeless("/about"))
+        ]
+    );
+
+    let names: Vec<_> = doc.descendants().map(|node| node.name.as_ref()).collect();
+    assert_eq!(names, ["server", "tls", "cert", "route", "route", "server"]);
+    assert_eq!(server.descendants().count(), 4);
+}
+
+#[test]
+fn typed_getters_name_the_missing_path() {
+    let doc = KdlDocument::parse(DOC).unwrap();
+    let server = doc.require("server").unwrap();
+
+    assert_eq!(server.require_arg::<&str>(0).unwrap(), "localhost");
+    assert_eq!(server.require_arg::<u16>(1).unwrap(), 8080);
+    assert_eq!(server.require_prop::<u32>("workers").unwrap(), 4);
+    assert_eq!(
+        doc.require_path(&["server", "tls", "cert"])
+            .unwrap()
+            .require_arg::<String>(0)
+            .unwrap(),
+        "server.pem"
+    );
+
+    let err = doc.require_path(&["server", "tls", "key"]).unwrap_err();
+    assert!(matches!(err.kind, DecodeErrorKind::MissingChild(ref name) if name == "key"));
+    assert_eq!(err.to_string(), "server > tls: missing child node `key`");
+
+    let err = doc.require("client").unwrap_err();
+    assert_eq!(err.to_string(), "missing child node `client`");
+
+    let err = server.require_prop::<u16>("port").unwrap_err();
+    assert_eq!(err.to_string(), "server: missing property `port`");
+
+    let err = server.require_arg::<u8>(1).unwrap_err();
+    assert_eq!(err.to_string(), "server: invalid value: 8080");
+}
+
+#[test]
+fn edits_documents() {
+    let mut doc = KdlDocument::parse(DOC).unwrap();
+    let int = |i| TypedValue {
+        ty: None,
+        val: KdlValue::Integer(i),
+    };
+
+    let server = doc.get_mut("server").unwrap();
+    assert_eq!(server.set_prop("workers", int(8)), Some(int(4)));
+    assert_eq!(server.set_prop("timeout", int(30)), None);
+    assert_eq!(server.remove_prop("timeout"), Some(int(30)));
+    server.set_prop(KdlString::Escaped("two\\nlines"), int(2));
+    assert_eq!(server.remove_prop("two\nlines"), Some(int(2)));
+    assert_eq!(server.set_arg(1, int(9090)), Some(int(8080)));
+    assert_eq!(server.set_arg(5, int(0)), None);
+    server.insert_arg(0, int(1));
+    assert_eq!(server.remove_arg(0), Some(int(1)));
+    server.rename(String::from("service"));
+
+    let mut health = KdlNode::new("route");
+    health.push_arg(TypedValue {
+        ty: None,
+        val: KdlValue::String(KdlString::Escapeless("/health")),
+    });
+    server.insert_child(1, health);
+    assert_eq!(server.remove_child(3).unwrap().name, "route");
+
+    // move tls into the other server
+    assert!(!doc.move_node(&[0, 0], &[0, 0, 0]));
+    assert!(!doc.move_node(&[0, 7], &[1, 0]));
+    assert!(doc.move_node(&[0, 0], &[1, 0]));
+    assert!(doc.node_at(&[1, 0, 0]).is_some());
+
+    assert_eq!(
+        format_document(&doc.nodes, &FormatOptions::default()).unwrap(),
+        r#"service "localhost" 9090 workers=8 {
+    route "/health"
+    route "/"
+}
+server "backup" 8081 {
+    tls {
+        cert "server.pem"
+    }
+}
+"#
+    );
+}
+
+#[test]
+fn assembles_deep_documents_without_recursing() {
+    let deep = |depth: usize| format!("{}{}", "a {\n".repeat(depth), "}\n".repeat(depth));
+
+    let shallow = deep(300);
+    let nodes = parse_document(&mut Parser::from_str(&shallow)).unwrap();
+    assert_eq!(KdlDocument::from(nodes).descendants().count(), 300);
+
+    let deeper = deep(DEFAULT_MAX_DEPTH + 1);
+    let err = parse_document(&mut Parser::from_str(&deeper)).unwrap_err();
+    assert!(matches!(err, ParseError::TooDeeplyNested));
+
+    let deepest = deep(5_000);
+    let nodes = parse_document_with_max_depth(&mut Parser::from_str(&deepest), 5_000).unwrap();
+    assert_eq!(KdlDocument::from(nodes).descendants().count(), 5_000);
+
+    let err = parse_document_with_max_depth(&mut Parser::from_str(&deepest), 64).unwrap_err();
+    assert!(matches!(err, ParseError::TooDeeplyNested));
+
+    let nested = "a {\n    b {\n        c\n    }\n}";
+    let nodes = parse_document_with_max_depth(&mut Parser::from_str(nested), 2).unwrap();
+    assert_eq!(nodes[0].children[0].children[0].name, "c");
+    assert!(parse_document_with_max_depth(&mut Parser::from_str(nested), 1).is_err());
+}
@@ -0,0 +1,215 @@
+#![cfg(feature = "derive")]
+
+use sleepyhead_kdl::decode::{decode_str, DecodeKdl};
+use sleepyhead_kdl::encode::{encode_str, write_events, EncodeError, EncodeKdl, EncodeValue};
+use sleepyhead_kdl::format::{FormatOptions, Formatter};
+
+#[derive(DecodeKdl, EncodeKdl, Debug, PartialEq)]
+struct Config {
+    #[kdl(child)]
+    server: Server,
+    #[kdl(children(name = "route"))]
+    routes: Vec<Route>,
+    #[kdl(children)]
+    plugins: Vec<Plugin>,
+}
+
+#[derive(DecodeKdl, EncodeKdl, Debug, PartialEq)]
+struct Server {
+    #[kdl(argument)]
+    host: String,
+    #[kdl(argument)]
+    port: u16,
+    #[kdl(property)]
+    workers: Option<u32>,
+    #[kdl(property(name = "tls"), skip_if_default)]
+    use_tls: bool,
+}
+
+#[derive(DecodeKdl, EncodeKdl, Debug, PartialEq)]
+struct Route {
+    #[kdl(argument)]
+    path: String,
+    #[kdl(arguments)]
+    methods: Vec<String>,
+    #[kdl(type_name)]
+    kind: Option<String>,
+    #[kdl(child(name = "max-body"))]
+    max_body: Option<Limit>,
+}
+
+#[derive(DecodeKdl, EncodeKdl, Debug, PartialEq)]
+struct Limit {
+    #[kdl(argument)]
+    bytes: u64,
+}
+
+#[derive(DecodeKdl, EncodeKdl, Debug, PartialEq)]
+struct Plugin {
+    #[kdl(node_name)]
+    name: String,
+    #[kdl(property, skip_if_default)]
+    enabled: bool,
+}
+
+fn config() -> Config {
+    Config {
+        server: Server {
+            host: "localhost".into(),
+            port: 8080,
+            workers: None,
+            use_tls: true,
+        },
+        routes: vec![
+            Route {
+                path: "/".into(),
+                methods: vec!["GET".into(), "HEAD".into()],
+                kind: Some("static".into()),
+                max_body: None,
+            },
+            Route {
+                path: "/upload \"big\"".into(),
+                methods: vec![],
+                kind: None,
+                max_body: Some(Limit { bytes: 1024 }),
+            },
+        ],
+        plugins: vec![
+            Plugin {
+                name: "gzip".into(),
+                enabled: true,
+            },
+            Plugin {
+                name: "cache".into(),
+                enabled: false,
+            },
+        ],
+    }
+}
+
+#[test]
+fn encodes_with_same_attributes_as_decoding() {
+    let config = config();
+    let out = encode_str(&config, &FormatOptions::default()).unwrap();
+
+    assert_eq!(
+        out,
+        r#"server "localhost" 8080 tls=true
+(static)route "/" "GET" "HEAD"
+route "/upload \"big\"" {
+    max-body 1024
+}
+gzip enabled=true
+cache
+"#
+    );
+
+    let decoded: Config = decode_str(&out).unwrap();
+    assert_eq!(decoded, config);
+
+    assert!(matches!(
+        f64::NAN.encode_value(),
+        Err(EncodeError::NotANumber)
+    ));
+}
+
+#[test]
+fn round_trips_none_arguments_as_null() {
+    #[derive(DecodeKdl, EncodeKdl, Debug, PartialEq)]
+    struct Point {
+        #[kdl(argument)]
+        a: Option<u16>,
+        #[kdl(argument)]
+        b: u16,
+        #[kdl(property)]
+        c: Option<u16>,
+    }
+
+    #[derive(DecodeKdl, Debug)]
+    struct Check {
+        #[kdl(child)]
+        pt: Point,
+    }
+
+    let point = Point {
+        a: None,
+        b: 3,
+        c: None,
+    };
+    let out = sleepyhead_kdl::format::format_document(
+        &[point.encode_node("pt").unwrap()],
+        &FormatOptions::default(),
+    )
+    .unwrap();
+    assert_eq!(out, "pt null 3\n");
+
+    let decoded: Check = decode_str(&out).unwrap();
+    assert_eq!(decoded.pt, point);
+
+    let decoded: Check = decode_str("pt 1 2 c=null").unwrap();
+    assert_eq!((decoded.pt.a, decoded.pt.c), (Some(1), None));
+}
+
+#[test]
+fn writes_events() {
+    let config = config();
+    let node = config.encode_node("config").unwrap();
+
+    let options = FormatOptions::default();
+    let mut formatter = Formatter::new(String::new(), &options);
+    write_events(&node, &mut |event| formatter.write_event(&event).unwrap());
+
+    assert_eq!(
+        formatter.into_inner(),
+        sleepyhead_kdl::format::format_document(std::slice::from_ref(&node), &options).unwrap()
+    );
+}
+
+#[test]
+fn renames_fields_with_the_container_rule() {
+    #[derive(DecodeKdl, EncodeKdl, Debug, PartialEq)]
+    #[kdl(rename_all = "camelCase")]
+    struct Pool {
+        #[kdl(property)]
+        max_idle: u32,
+        #[kdl(property(name = "keep_alive"))]
+        keep_alive: bool,
+        #[kdl(child)]
+        idle_limit: Limit,
+    }
+
+    let pool = Pool {
+        max_idle: 4,
+        keep_alive: true,
+        idle_limit: Limit { bytes: 64 },
+    };
+
+    let node = pool.encode_node("pool").unwrap();
+    let out = sleepyhead_kdl::format::format_document(&[node], &FormatOptions::default()).unwrap();
+    assert_eq!(
+        out,
+        "pool maxIdle=4 keep_alive=true {\n    idleLimit 64\n}\n"
+    );
+
+    #[derive(DecodeKdl)]
+    struct Check {
+        #[kdl(child)]
+        pool: Pool,
+    }
+
+    let decoded: Check = decode_str(&out).unwrap();
+    assert_eq!(decoded.pool, pool);
+
+    #[derive(EncodeKdl)]
+    #[kdl(rename_all = "UPPERCASE")]
+    struct Shouting {
+        #[kdl(property)]
+        max_idle: u32,
+    }
+
+    let node = Shouting { max_idle: 4 }.encode_node("pool").unwrap();
+    assert_eq!(
+        node.attrs[0].key,
+        sleepyhead_kdl::ast::KdlString::Escapeless("MAX_IDLE")
+    );
+}
@@ -0,0 +1,79 @@
+use sleepyhead_kdl::ast::*;
+use sleepyhead_kdl::fixed::{Entry, FixedDocument, FixedError};
+use sleepyhead_kdl::parser::Parser;
+
+const SENSORS: &str = r#"
+bus "i2c0" speed=400000 {
+    sensor "bme280" interval=1000 118
+    sensor "sht31" 68 interval=500 interval=250
+}
+"status\tled" pin=13
+"#;
+
+fn int(i: i64) -> TypedValue<'static> {
+    TypedValue {
+        ty: None,
+        val: KdlValue::Integer(i),
+    }
+}
+
+#[test]
+fn keeps_arguments_before_properties() {
+    let doc = FixedDocument::<8, 16>::parse(SENSORS).unwrap();
+    let bus = doc.get("bus").unwrap();
+    let bme = bus.get("sensor").unwrap();
+
+    assert_eq!(
+        bme.entries(),
+        [
+            Entry::Arg(TypedValue {
+                ty: None,
+                val: KdlValue::String(KdlString::Escapeless("bme280")),
+            }),
+            Entry::Arg(int(118)),
+            Entry::Prop(KdlProperty {
+                key: KdlString::Escapeless("interval"),
+                value: int(1000),
+            }),
+        ]
+    );
+    assert_eq!(bme.arg(1), Some(&int(118)));
+    assert!(bme.arg(2).is_none());
+
+    let sht = bme.next_sibling().unwrap();
+    assert_eq!(sht.prop("interval"), Some(&int(250)));
+    assert_eq!(sht.props().count(), 2);
+    assert_eq!(bus.get_all("sensor").count(), 2);
+    assert_eq!(sht.parent(), Some(bus));
+}
+
+#[test]
+fn compares_names_without_unescaping() {
+    let doc = FixedDocument::<8, 16>::parse(SENSORS).unwrap();
+    let led = doc.get("status\tled").unwrap();
+
+    assert_eq!(led.name(), KdlString::Escaped("status\\tled"));
+    assert_eq!(led.prop("pin"), Some(&int(13)));
+    assert_eq!(doc.roots().count(), 2);
+}
+
+#[test]
+fn reports_full_pools() {
+    let mut doc = FixedDocument::<3, 16>::new();
+    assert!(matches!(
+        doc.read(&mut Parser::from_str(SENSORS)),
+        Err(FixedError::TooManyNodes)
+    ));
+    assert!(doc.is_empty());
+
+    let mut doc = FixedDocument::<8, 6>::new();
+    assert!(matches!(
+        doc.read(&mut Parser::from_str(SENSORS)),
+        Err(FixedError::TooManyEntries)
+    ));
+
+    doc.read(&mut Parser::from_str("a 1 2 3 { b x=1; }"))
+        .unwrap();
+    assert_eq!(doc.len(), 2);
+    assert_eq!(doc.get("a").unwrap().args().count(), 3);
+}
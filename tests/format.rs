@@ -0,0 +1,111 @@
+use sleepyhead_kdl::assembler::*;
+use sleepyhead_kdl::format::*;
+use sleepyhead_kdl::parser::Parser;
+
+const MESSY: &str = r##"
+node   1 "two"   b=(u8)3 a=r#"qu"ote"#
+"quoted name" {
+  child null; other 1.5 true
+    "with\tescape" key="val"
+  nested {
+  }
+}
+"##;
+
+#[test]
+fn formats_events_and_trees_the_same() {
+    let options = FormatOptions::default();
+    let from_events = format_str(MESSY, &options).unwrap();
+
+    let doc = parse_document(&mut Parser::from_str(MESSY)).unwrap();
+    let from_tree = format_document(&doc, &options).unwrap();
+
+    assert_eq!(
+        from_events,
+        "node 1 \"two\" b=(u8)3 a=\"qu\\\"ote\"\n\"quoted name\" {\n    child null\n    other 1.5 true\n    \"with\\tescape\" key=\"val\"\n    nested\n}\n"
+    );
+    assert_eq!(from_tree, from_events);
+}
+
+#[test]
+fn formatting_is_idempotent() {
+    let options = FormatOptions {
+        indent: Indent::Tabs,
+        max_width: Some(20),
+        property_order: PropertyOrder::Sorted,
+        quote_style: QuoteStyle::AlwaysQuote,
+        numbers: NumberStyle::Scientific,
+        terminator: Terminator::Semicolon,
+    };
+
+    let once = format_str(MESSY, &options).unwrap();
+    let twice = format_str(&once, &options).unwrap();
+    assert_eq!(once, twice);
+    assert!(check(&once, &options).unwrap());
+    assert!(!check(MESSY, &options).unwrap());
+}
+
+#[test]
+fn formats_the_test_inputs_idempotently() {
+    let options = FormatOptions::default();
+    let dir = concat!(env!("CARGO_MANIFEST_DIR"), "/tests/input");
+
+    for entry in std::fs::read_dir(dir).unwrap() {
+        let path = entry.unwrap().path();
+        let input = std::fs::read_to_string(&path).unwrap();
+
+        // inputs that are meant to fail have nothing to format
+        let Ok(once) = format_str(&input, &options) else {
+            continue;
+        };
+        let twice = format_str(&once, &options).unwrap();
+        assert_eq!(once, twice, "{}", path.display());
+        assert!(check(&once, &options).unwrap(), "{}", path.display());
+    }
+}
+
+#[test]
+fn breaks_long_lines() {
+    let options = FormatOptions {
+        max_width: Some(16),
+        property_order: PropertyOrder::Sorted,
+        ..FormatOptions::default()
+    };
+
+    let formatted = format_str("node zzz=1 aaa=2 \"some long value\"", &options).unwrap();
+    assert_eq!(
+        formatted,
+        "node \\\n    \"some long value\" \\\n    aaa=2 zzz=1\n"
+    );
+
+    let reparsed = parse_document(&mut Parser::from_str(&formatted)).unwrap();
+    let original = parse_document(&mut Parser::from_str(
+        "node \"some long value\" aaa=2 zzz=1",
+    ))
+    .unwrap();
+    assert_eq!(reparsed, original);
+}
+
+#[test]
+fn writes_infinities_so_they_read_back_and_refuses_nan() {
+    for numbers in [NumberStyle::Shortest, NumberStyle::Scientific] {
+        let options = FormatOptions {
+            numbers,
+            ..FormatOptions::default()
+        };
+
+        let formatted = format_str("node 1e999", &options).unwrap();
+        assert_eq!(formatted, "node 1e999\n");
+        assert!(check(&formatted, &options).unwrap());
+    }
+
+    let mut node = KdlNode::new("node");
+    node.push_arg(sleepyhead_kdl::ast::TypedValue {
+        ty: None,
+        val: sleepyhead_kdl::ast::KdlValue::Float(f64::NAN),
+    });
+    let options = FormatOptions::default();
+    let mut formatter = Formatter::new(String::new(), &options);
+    assert!(formatter.write_node(&node).is_err());
+    assert!(format_document(&[node], &options).is_err());
+}
@@ -0,0 +1,77 @@
+use std::collections::HashMap;
+use std::path::PathBuf;
+
+use sleepyhead_kdl::format::{format_document, FormatOptions};
+use sleepyhead_kdl::include::*;
+
+fn files(files: &[(&str, &str)]) -> HashMap<PathBuf, String> {
+    files
+        .iter()
+        .map(|(path, source)| (PathBuf::from(path), source.to_string()))
+        .collect()
+}
+
+#[test]
+fn splices_included_files() {
+    let mut loader = files(&[
+        (
+            "conf/main.kdl",
+            "include \"common.kdl\"\nserver {\n    include \"../shared/routes.kdl\"\n}\n",
+        ),
+        (
+            "conf/common.kdl",
+            "log-level \"info\"\ninclude \"./more/db.kdl\"\n",
+        ),
+        ("conf/more/db.kdl", "database \"postgres\"\n"),
+        ("shared/routes.kdl", "route \"/\"\nroute \"/about\"\n"),
+    ]);
+
+    let sources = Sources::load("conf/main.kdl", &mut loader, &IncludeOptions::default()).unwrap();
+    assert_eq!(sources.paths().count(), 4);
+
+    let doc = sources.document().unwrap();
+    assert_eq!(
+        format_document(&doc.nodes, &FormatOptions::default()).unwrap(),
+        "log-level \"info\"\ndatabase \"postgres\"\nserver {\n    route \"/\"\n    route \"/about\"\n}\n"
+    );
+
+    let mut loader = files(&[("a.kdl", "import \"b.kdl\"\nb.kdl\n"), ("b.kdl", "node\n")]);
+    let options = IncludeOptions {
+        directive: "import".into(),
+    };
+    let doc = Sources::load("a.kdl", &mut loader, &options).unwrap();
+    assert_eq!(doc.document().unwrap().nodes.len(), 2);
+}
+
+#[test]
+fn reports_the_include_chain() {
+    let mut loader = files(&[
+        ("a.kdl", "include \"b.kdl\""),
+        ("b.kdl", "include \"dir/c.kdl\""),
+        ("dir/c.kdl", "include \"../b.kdl\""),
+    ]);
+
+    let err = Sources::load("a.kdl", &mut loader, &IncludeOptions::default()).unwrap_err();
+    assert!(
+        matches!(err.kind, IncludeErrorKind::Cycle(ref path) if path == &PathBuf::from("b.kdl"))
+    );
+    assert_eq!(
+        err.to_string(),
+        "a.kdl -> b.kdl -> dir/c.kdl: `b.kdl` is already being included"
+    );
+
+    let mut loader = files(&[
+        ("a.kdl", "include \"b.kdl\""),
+        ("b.kdl", "include \"missing.kdl\""),
+    ]);
+    let err = Sources::load("a.kdl", &mut loader, &IncludeOptions::default()).unwrap_err();
+    assert!(matches!(err.kind, IncludeErrorKind::Io(_)));
+    assert_eq!(
+        err.chain,
+        ["a.kdl", "b.kdl", "missing.kdl"].map(PathBuf::from)
+    );
+
+    let mut loader = files(&[("a.kdl", "include 1")]);
+    let err = Sources::load("a.kdl", &mut loader, &IncludeOptions::default()).unwrap_err();
+    assert!(matches!(err.kind, IncludeErrorKind::InvalidDirective));
+}
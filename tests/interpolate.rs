@@ -0,0 +1,64 @@
+use std::collections::HashMap;
+
+use sleepyhead_kdl::assembler::KdlDocument;
+use sleepyhead_kdl::ast::*;
+use sleepyhead_kdl::format::{format_document, FormatOptions};
+use sleepyhead_kdl::interpolate::*;
+
+fn vars() -> HashMap<String, String> {
+    [("HOME", "/home/em"), ("DB_URL", "postgres://db")]
+        .into_iter()
+        .map(|(name, value)| (name.to_string(), value.to_string()))
+        .collect()
+}
+
+#[test]
+fn interpolates_values() {
+    let doc = KdlDocument::parse(
+        r#"
+storage "${HOME}/data" cache="${CACHE:-/tmp}/kdl" price="$$5 or $ 6"
+database (env)"DB_URL" pool=(env)"POOL:-4" (path)"${HOME}" 1
+plain "no variables"
+"#,
+    )
+    .unwrap();
+
+    let expansions = interpolate(&doc, &vars()).unwrap();
+    assert_eq!(expansions.len(), 6);
+    assert_eq!(expansions.get(&[0], Slot::Arg(0)), Some("/home/em/data"));
+    assert_eq!(expansions.get(&[2], Slot::Arg(0)), None);
+
+    let expanded = expansions.apply(&doc);
+    assert_eq!(
+        format_document(&expanded.nodes, &FormatOptions::default()).unwrap(),
+        r#"storage "/home/em/data" cache="/tmp/kdl" price="$5 or $ 6"
+database "postgres://db" (path)"/home/em" 1 pool="4"
+plain "no variables"
+"#
+    );
+
+    // the original strings are untouched
+    assert_eq!(
+        doc.nodes[1].values[0].val,
+        KdlValue::String(KdlString::Escapeless("DB_URL"))
+    );
+
+    let from_closure = interpolate(&doc, &|name: &str| Some(name.to_lowercase())).unwrap();
+    assert_eq!(from_closure.get(&[1], Slot::Prop(0)), Some("pool"));
+}
+
+#[test]
+fn errors_name_the_value() {
+    let doc = KdlDocument::parse("server {\n    database url=\"${DB_HOST}/app\"\n}\n").unwrap();
+    let err = interpolate(&doc, &vars()).unwrap_err();
+    assert!(matches!(err.kind, InterpolationErrorKind::Undefined(ref name) if name == "DB_HOST"));
+    assert_eq!(err.entry, Entry::Property("url".into()));
+    assert_eq!(
+        err.to_string(),
+        "server > database: property `url`: undefined variable `DB_HOST`"
+    );
+
+    let doc = KdlDocument::parse("a \"ok\" \"${HOME\"").unwrap();
+    let err = interpolate(&doc, &vars()).unwrap_err();
+    assert_eq!(err.to_string(), "a: argument 1: unterminated `${`");
+}
@@ -0,0 +1,85 @@
+#![cfg(feature = "jik")]
+
+use serde_json::json;
+use sleepyhead_kdl::format::FormatOptions;
+use sleepyhead_kdl::jik::*;
+
+#[test]
+fn reads_jik() {
+    let input = r#"
+- name="sleepyhead" version=1.5 {
+    keywords "kdl" "parser"
+    authors {
+        - name="emily"
+    }
+    empty
+    (array)single "only"
+    (array)none
+    (object)- {
+        - 1 2
+    }
+    nothing null
+}
+"#;
+
+    assert_eq!(
+        str_to_json(input).unwrap(),
+        json!({
+            "name": "sleepyhead",
+            "version": 1.5,
+            "keywords": ["kdl", "parser"],
+            "authors": [{ "name": "emily" }],
+            "empty": {},
+            "single": ["only"],
+            "none": [],
+            "-": { "-": [1, 2] },
+            "nothing": null,
+        })
+    );
+}
+
+#[test]
+fn writes_jik_that_reads_back() {
+    let value = json!({
+        "name": "sleepyhead",
+        "nested": [[], [1], { "-": [1, 2] }, "text \"quoted\""],
+        "numbers": [1, -2, 2.5],
+        "empty": {},
+    });
+
+    let out = json_to_string(&value, &FormatOptions::default());
+    assert_eq!(
+        out,
+        r#"- name="sleepyhead" {
+    empty
+    nested {
+        (array)-
+        (array)- 1
+        (object)- {
+            - 1 2
+        }
+        - "text \"quoted\""
+    }
+    numbers 1 -2 2.5
+}
+"#
+    );
+
+    assert_eq!(str_to_json(&out).unwrap(), value);
+}
+
+#[test]
+fn rejects_ambiguous_nodes() {
+    let err = str_to_json("- {\n    bad 1 key=2\n}\n").unwrap_err();
+    assert_eq!(
+        err.to_string(),
+        "- > bad: node has both array items and object members"
+    );
+
+    let err = str_to_json("(object)- 1\n").unwrap_err();
+    assert!(matches!(err.kind, JikErrorKind::NotAnObject));
+
+    let err = str_to_json("- 1\n- 2\n").unwrap_err();
+    assert!(matches!(err.kind, JikErrorKind::RootCount(2)));
+    assert!(err.path.is_empty());
+}
@@ -0,0 +1,54 @@
+use sleepyhead_kdl::json::*;
+use sleepyhead_kdl::parser::Parser;
+
+#[test]
+fn transcodes_events_to_json() {
+    let input = r#"
+package "sleepyhead" (semver)"0.1.0" license="MIT" license="Apache-2.0" {
+    (dep)logos "0.12" optional=false
+    authors {
+        - "emily \"em\"\n"
+    }
+}
+empty
+"#;
+
+    assert_eq!(
+        to_json_string(input).unwrap(),
+        concat!(
+            r#"[{"name":"package","values":["sleepyhead",{"type":"semver","value":"0.1.0"}],"props":{"license":"Apache-2.0"},"children":["#,
+            r#"{"name":"logos","type":"dep","values":["0.12"],"props":{"optional":false},"children":[]},"#,
+            r#"{"name":"authors","values":[],"props":{},"children":[{"name":"-","values":["emily \"em\"\n"],"props":{},"children":[]}]}"#,
+            r#"]},{"name":"empty","values":[],"props":{},"children":[]}]"#,
+        )
+    );
+
+    assert_eq!(to_json_string("").unwrap(), "[]");
+}
+
+#[test]
+fn writes_to_any_sink_and_closes_open_nodes() {
+    let mut writer = JsonWriter::new(String::new());
+    for event in Parser::from_str("a 1.5 {\n    b null\n").take(2) {
+        writer.write_event(&event.unwrap()).unwrap();
+    }
+
+    assert_eq!(
+        writer.finish().unwrap(),
+        r#"[{"name":"a","values":[1.5],"props":{},"children":[{"name":"b","values":[null],"props":{},"children":[]}]}]"#
+    );
+}
+
+#[test]
+fn transcodes_into_io_sinks() {
+    let input = "a 1 {\n    b \"two\"\n}\n";
+    let out = transcode_to_writer(Parser::from_str(input), Vec::new()).unwrap();
+    assert_eq!(
+        String::from_utf8(out).unwrap(),
+        to_json_string(input).unwrap()
+    );
+
+    let full: &mut [u8] = &mut [0; 8];
+    let err = transcode_to_writer(Parser::from_str(input), full).unwrap_err();
+    assert!(matches!(err, TranscodeError::Io(e) if e.kind() == std::io::ErrorKind::WriteZero));
+}
@@ -0,0 +1,107 @@
+use sleepyhead_kdl::assembler::KdlDocument;
+use sleepyhead_kdl::format::{format_document, FormatOptions};
+use sleepyhead_kdl::merge::*;
+
+const BASE: &str = r#"
+server "localhost" 8080 workers=2 {
+    route "/"
+    tls cert="dev.pem" key="dev.key"
+}
+plugins {
+    plugin "auth"
+}
+log-level "debug"
+"#;
+
+const PROD: &str = r#"
+server "0.0.0.0" workers=16 {
+    (merge-props)tls cert="prod.pem" {
+        ignored
+    }
+}
+plugins {
+    (append)plugin "metrics"
+}
+"#;
+
+const LOCAL: &str = r#"
+(replace)server "127.0.0.1" 9000
+log-level "trace"
+"#;
+
+#[test]
+fn merges_layers() {
+    let layers = [
+        KdlDocument::parse(BASE).unwrap(),
+        KdlDocument::parse(PROD).unwrap(),
+    ];
+    let merged = merge(&layers, &MergeOptions::default());
+
+    assert_eq!(
+        format_document(&merged.document.nodes, &FormatOptions::default()).unwrap(),
+        r#"server "0.0.0.0" workers=16 {
+    route "/"
+    tls cert="prod.pem" key="dev.key"
+}
+plugins {
+    plugin "auth"
+    plugin "metrics"
+}
+log-level "debug"
+"#
+    );
+
+    let server = merged.origin(&[0]).unwrap();
+    assert_eq!(server.layer, 0);
+    assert_eq!(server.arg(0), Some(1));
+    assert_eq!(server.prop("workers"), Some(1));
+
+    let tls = merged.origin(&[0, 1]).unwrap();
+    assert_eq!((tls.prop("cert"), tls.prop("key")), (Some(1), Some(0)));
+    assert_eq!(merged.origin(&[1, 1]).unwrap().layer, 1);
+    assert_eq!(merged.origin(&[2]).unwrap().arg(0), Some(0));
+}
+
+#[test]
+fn replaces_and_uses_options() {
+    let layers = [
+        KdlDocument::parse(BASE).unwrap(),
+        KdlDocument::parse(PROD).unwrap(),
+        KdlDocument::parse(LOCAL).unwrap(),
+    ];
+    let mut options = MergeOptions::default();
+    options.by_name.insert("log-level".into(), Strategy::Append);
+    let merged = merge(&layers, &options);
+
+    assert_eq!(
+        format_document(&merged.document.nodes, &FormatOptions::default()).unwrap(),
+        r#"server "127.0.0.1" 9000
+plugins {
+    plugin "auth"
+    plugin "metrics"
+}
+log-level "debug"
+log-level "trace"
+"#
+    );
+
+    assert_eq!(merged.origin(&[0]).unwrap().layer, 2);
+    assert!(merged.origin(&[0, 0]).is_none());
+    assert_eq!(merged.origin(&[3]).unwrap().layer, 2);
+}
+
+#[test]
+fn never_combines_nodes_from_the_same_overlay() {
+    let layers = [
+        KdlDocument::parse("plugin \"a\"\n").unwrap(),
+        KdlDocument::parse("(append)plugin \"x\"\nplugin \"y\"\n").unwrap(),
+    ];
+    let merged = merge(&layers, &MergeOptions::default());
+
+    assert_eq!(
+        format_document(&merged.document.nodes, &FormatOptions::default()).unwrap(),
+        "plugin \"a\"\nplugin \"x\"\nplugin \"y\"\n"
+    );
+    assert_eq!(merged.origin(&[0]).unwrap().layer, 0);
+    assert_eq!(merged.origin(&[2]).unwrap().layer, 1);
+}
@@ -0,0 +1,61 @@
+use sleepyhead_kdl::parser::Parser;
+#[cfg(all(not(feature = "std"), not(feature = "alloc")))]
+use sleepyhead_kdl::ParseError;
+
+#[cfg(any(feature = "std", feature = "alloc"))]
+#[test]
+fn ignores_capacities_with_an_allocator() {
+    let deep = format!("{}{}", "a {\n".repeat(1_000), "}\n".repeat(1_000));
+    let parser = Parser::<_, 128, 2>::from_str_sized(&deep);
+    assert_eq!(parser.collect::<Result<Vec<_>, _>>().unwrap().len(), 2_000);
+
+    let parser = Parser::<_, 2, 2>::from_str_sized("a 1 2 3 x=1 y=2 z=3");
+    assert!(parser.collect::<Result<Vec<_>, _>>().is_ok());
+}
+
+#[cfg(all(not(feature = "std"), not(feature = "alloc")))]
+#[test]
+fn errors_past_depth() {
+    const NESTED: &str = "a {\n    b {\n        c {\n        }\n    }\n}\n";
+
+    let parser = Parser::<_, 128, 3>::from_str_sized(NESTED);
+    assert_eq!(parser.collect::<Result<Vec<_>, _>>().unwrap().len(), 6);
+
+    let mut parser = Parser::<_, 128, 2>::from_str_sized(NESTED);
+    assert!(parser.next().unwrap().is_ok());
+    assert!(parser.next().unwrap().is_ok());
+    assert!(matches!(
+        parser.next().unwrap(),
+        Err(ParseError::TooDeeplyNested)
+    ));
+}
+
+#[cfg(all(not(feature = "std"), not(feature = "alloc")))]
+#[test]
+fn errors_past_entries() {
+    let parser = Parser::<_, 4, 8>::from_str_sized("a 1 2 x=1 y=2");
+    assert!(parser.collect::<Result<Vec<_>, _>>().is_ok());
+
+    let mut parser = Parser::<_, 2, 8>::from_str_sized("a 1 2 3");
+    assert!(matches!(
+        parser.next().unwrap(),
+        Err(ParseError::TooManyEntries)
+    ));
+}
+
+#[test]
+fn type_annotations_touch_what_they_annotate() {
+    let parses = |input| Parser::from_str(input).collect::<Result<Vec<_>, _>>().is_ok();
+
+    assert!(parses("(type)node (type)1 key=(type)2"));
+    for input in [
+        "(type) node",
+        "(type)/*huh*/node",
+        "(type)\\\nnode",
+        "node (type) 1",
+        "node key=(type) 1",
+        "node key=(type)// huh\n1",
+    ] {
+        assert!(!parses(input), "{:?}", input);
+    }
+}
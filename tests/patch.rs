@@ -0,0 +1,73 @@
+use sleepyhead_kdl::assembler::KdlDocument;
+use sleepyhead_kdl::format::{format_document, FormatOptions};
+use sleepyhead_kdl::patch::*;
+
+const DOC: &str = r#"
+server "localhost" 8080 timeout=10 {
+    tls
+    route "/"
+    route "/old"
+}
+"#;
+
+const MIGRATION: &str = r#"
+set-prop "server" "workers" 8
+remove-prop "server" "timeout"
+replace-arg "server" 1 9090
+insert-arg "server" 0 "http"
+insert-child "server" 1 {
+    route "/health"
+}
+remove-node (kql)"server > route[val() = \"/old\"]"
+rename "server > tls" "ssl"
+set-type "server > route[1]" "static"
+insert-child "" {
+    log-level "info"
+}
+"#;
+
+#[test]
+fn applies_patches() {
+    let mut doc = KdlDocument::parse(DOC).unwrap();
+    let patch = Patch::parse(MIGRATION).unwrap();
+    patch.apply(&mut doc).unwrap();
+
+    assert_eq!(
+        format_document(&doc.nodes, &FormatOptions::default()).unwrap(),
+        r#"server "http" "localhost" 9090 workers=8 {
+    ssl
+    route "/health"
+    (static)route "/"
+}
+log-level "info"
+"#
+    );
+
+    // patches round-trip through their kdl form
+    let written = patch.to_string();
+    assert!(written.starts_with("set-prop \"server\" \"workers\" 8\n"));
+    assert_eq!(Patch::parse(&written).unwrap(), patch);
+}
+
+#[test]
+fn fails_atomically_with_a_location() {
+    let mut doc = KdlDocument::parse(DOC).unwrap();
+    let before = doc.clone();
+
+    let patch = "rename \"server\" \"service\"\nremove-arg \"service\" 5\n";
+    let err = apply(&mut doc, patch).unwrap_err();
+    assert!(matches!(err.kind, PatchErrorKind::MissingArgument(5)));
+    assert_eq!(err.operation, Some(1));
+    assert_eq!(err.span_in(patch), Some(26..36));
+    assert_eq!(err.to_string(), "operation #1: missing argument #5");
+    assert_eq!(doc, before);
+
+    let err = apply(&mut doc, "remove-node \"server > route[2]\"").unwrap_err();
+    assert!(matches!(err.kind, PatchErrorKind::NoMatch));
+
+    let err = Patch::parse("set-prop \"server\" 8").unwrap_err();
+    assert_eq!(
+        err.to_string(),
+        "operation #0: expected `set-prop <target> <key> <value>`"
+    );
+}
@@ -0,0 +1,125 @@
+use sleepyhead_kdl::assembler::*;
+use sleepyhead_kdl::parser::Parser;
+use sleepyhead_kdl::query::*;
+
+const DOC: &str = r#"
+server "web" port=8080 {
+    listen "0.0.0.0"
+    tls enabled=true
+}
+server "admin" port=443 {
+    listen "127.0.0.1"
+}
+(lru)cache size=(mb)512 name="lru-main"
+listen "top-level"
+route "/"
+route "/static"
+"#;
+
+fn names(nodes: &[&KdlNode<'_>]) -> Vec<String> {
+    nodes
+        .iter()
+        .map(|node| match node.values.first() {
+            Some(value) => format!("{} {:?}", node.name, value.val),
+            None => node.name.to_string(),
+        })
+        .collect()
+}
+
+fn run(query: &str) -> Vec<String> {
+    let doc = parse_document(&mut Parser::from_str(DOC)).unwrap();
+    names(&select(&doc, query).unwrap())
+}
+
+#[test]
+fn selects_with_combinators() {
+    assert_eq!(run("top() > listen").len(), 1);
+    assert_eq!(run("listen").len(), 3);
+    assert_eq!(run("server > listen").len(), 2);
+    assert_eq!(run("server listen, route").len(), 4);
+    assert_eq!(run("top()").len(), 6);
+    assert_eq!(run("listen + tls"), vec!["tls"]);
+    assert_eq!(run("cache ~ route").len(), 2);
+    assert_eq!(run("cache + route"), Vec::<String>::new());
+}
+
+#[test]
+fn selects_with_matchers() {
+    assert_eq!(run("top() > server[port > 1024]").len(), 1);
+    assert_eq!(run("server[port >= 443]").len(), 2);
+    assert_eq!(run("[prop(port) = 443] > listen").len(), 1);
+    assert_eq!(run("[val() ^= \"/s\"]").len(), 1);
+    assert_eq!(run("[val(0) *= \"0.0.0\"]").len(), 1);
+    assert_eq!(run("[name $= \"main\"]"), vec!["cache"]);
+    assert_eq!(run("[size = (mb)]"), vec!["cache"]);
+    assert_eq!(run("(lru)cache"), vec!["cache"]);
+    assert_eq!(run("[tag() = \"lru\"]"), vec!["cache"]);
+    assert_eq!(run("tls[enabled = true]"), vec!["tls"]);
+    assert_eq!(run("[name() = route][val() != \"/\"]").len(), 1);
+    assert_eq!(run("server[]").len(), 2);
+    assert_eq!(run("[enabled]"), vec!["tls"]);
+}
+
+#[test]
+fn reports_errors() {
+    let err = Query::parse("server[port >]").unwrap_err();
+    assert_eq!(err.position, 13);
+    assert_eq!(err.kind, QueryErrorKind::UnexpectedChar(']'));
+
+    assert_eq!(
+        Query::parse("a > top()").unwrap_err().kind,
+        QueryErrorKind::MisplacedTop
+    );
+    assert_eq!(
+        Query::parse("a, ").unwrap_err().kind,
+        QueryErrorKind::EmptySelector
+    );
+    assert_eq!(
+        Query::parse("[foo(1)]").unwrap_err().kind,
+        QueryErrorKind::UnknownAccessor("foo".into())
+    );
+    assert_eq!(
+        Query::parse("[val() = \"open").unwrap_err().kind,
+        QueryErrorKind::UnterminatedString
+    );
+}
+
+#[test]
+fn streams_matching_subtrees() {
+    let doc = parse_document(&mut Parser::from_str(DOC)).unwrap();
+
+    for query in [
+        "top() > listen",
+        "server > listen",
+        "server listen, route",
+        "[port >= 443]",
+        "(lru)cache",
+        "[size = (mb)]",
+    ] {
+        let query = Query::parse(query).unwrap();
+        let streamed: Vec<KdlNode> = query
+            .stream(Parser::from_str(DOC))
+            .unwrap()
+            .collect::<Result<_, _>>()
+            .unwrap();
+        let selected: Vec<KdlNode> = query.select(&doc).into_iter().cloned().collect();
+
+        assert_eq!(streamed, selected);
+    }
+
+    // matches inside a match come along with it's subtree
+    let streamed = Query::parse("server, listen")
+        .unwrap()
+        .stream(Parser::from_str(DOC))
+        .unwrap()
+        .count();
+    assert_eq!(streamed, 3);
+
+    let err = Query::parse("server ~ cache")
+        .unwrap()
+        .stream(Parser::from_str(DOC))
+        .err()
+        .unwrap();
+    assert_eq!(err.kind, QueryErrorKind::NotStreamable);
+    assert_eq!(err.position, 7);
+}
@@ -0,0 +1,130 @@
+#![cfg(feature = "schema")]
+
+use sleepyhead_kdl::schema::*;
+
+const META: &str = include_str!("../examples/schema.kdl");
+
+const SERVICE_SCHEMA: &str = r#"
+document {
+    node "server" {
+        min 1
+        max 1
+        value {
+            min 1
+            max 1
+            type "string"
+            pattern r"^[a-z.]+$"
+        }
+        prop "port" {
+            required true
+            type "number"
+            format "u16"
+            ">=" 1024
+        }
+        prop "mode" {
+            enum "dev" "prod"
+        }
+        children {
+            node "timeout" {
+                max 1
+                value {
+                    min 1
+                    max 1
+                    type "number"
+                    tag {
+                        enum "seconds" "ms"
+                    }
+                }
+            }
+        }
+    }
+    node "released" {
+        value {
+            format "date"
+        }
+    }
+}
+"#;
+
+#[test]
+fn meta_schema_validates_itself() {
+    let schema = Schema::parse(META).unwrap();
+
+    // the meta schema's `contributor` rule has no `children`, unlike `author`
+    let errors = schema.validate_str(META).unwrap_err();
+    assert_eq!(errors.len(), 1);
+    assert_eq!(
+        errors[0].to_string(),
+        "document > info > contributor > link: node isn't allowed here"
+    );
+}
+
+#[test]
+fn accepts_valid_documents() {
+    let schema = Schema::parse(SERVICE_SCHEMA).unwrap();
+
+    schema
+        .validate_str(
+            "server \"example.com\" port=8080 mode=\"prod\" {\n    timeout (seconds)30\n}\nreleased \"2022-02-14\"\n",
+        )
+        .unwrap();
+}
+
+#[test]
+fn reports_every_error_with_path_and_span() {
+    let schema = Schema::parse(SERVICE_SCHEMA).unwrap();
+    let input = "server \"Example.com\" port=80 mode=\"test\" extra=1 {\n    timeout 30\n    retries 3\n}\nreleased \"2022-13-01\"\n";
+
+    let errors = schema.validate_str(input).unwrap_err();
+    let messages: Vec<String> = errors.iter().map(ToString::to_string).collect();
+
+    assert_eq!(
+        messages,
+        vec![
+            "server: argument #0: doesn't match `^[a-z.]+$`",
+            "server: unexpected property `extra`",
+            "server: property `mode`: not one of the allowed values",
+            "server: property `port`: not >= 1024",
+            "server > timeout: argument #0: missing a type annotation",
+            "server > retries: node isn't allowed here",
+            "released: argument #0: not a valid date",
+        ]
+    );
+
+    let retries = errors
+        .iter()
+        .find(|e| matches!(e.kind, SchemaErrorKind::UnexpectedNode))
+        .unwrap();
+    let span = retries.span_in(input).unwrap();
+    assert_eq!(&input[span.clone()], "retries");
+    assert_eq!(span.start, 70);
+
+    let errors = schema
+        .validate_str("released \"2022-02-14\"\n")
+        .unwrap_err();
+    assert!(matches!(
+        &errors[0].kind,
+        SchemaErrorKind::TooFewNodes { name, min: 1, found: 0 } if name == "server"
+    ));
+    assert!(errors[0].path.is_empty());
+
+    let errors = schema.validate_str("server \"a\"\n").unwrap_err();
+    assert!(matches!(
+        &errors[0].kind,
+        SchemaErrorKind::MissingProperty(key) if key == "port"
+    ));
+}
+
+#[test]
+fn rejects_broken_schemas() {
+    let err = Schema::parse("node \"a\"").err().unwrap();
+    assert!(matches!(err.kind, SchemaErrorKind::BadSchema(_)));
+
+    let err = Schema::parse("document {\n    node ref=r#\"[id=\"missing\"]\"#\n}\n")
+        .err()
+        .unwrap();
+    assert_eq!(
+        err.to_string(),
+        "document: bad schema: ref `[id=\"missing\"]` selects nothing"
+    );
+}
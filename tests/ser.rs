@@ -0,0 +1,119 @@
+#![cfg(feature = "serde")]
+
+use serde::{Deserialize, Serialize};
+use sleepyhead_kdl::de::from_str;
+use sleepyhead_kdl::ser::{to_string, Error};
+use std::collections::BTreeMap;
+
+#[derive(Debug, Serialize, Deserialize, PartialEq)]
+struct State {
+    name: String,
+    port: u16,
+    ratio: f64,
+    timeout: Timeout,
+    mode: Mode,
+    tags: Vec<String>,
+    labels: BTreeMap<String, String>,
+    workers: Vec<Worker>,
+    paused: Option<bool>,
+}
+
+#[derive(Debug, Serialize, Deserialize, PartialEq)]
+struct Worker {
+    #[serde(rename = "-")]
+    id: (u32,),
+    host: String,
+    mode: Mode,
+    limits: Option<Limits>,
+}
+
+#[derive(Debug, Serialize, Deserialize, PartialEq)]
+struct Limits {
+    cpu: u8,
+}
+
+#[derive(Debug, Serialize, Deserialize, PartialEq)]
+enum Timeout {
+    Seconds(u64),
+    Never,
+}
+
+#[derive(Debug, Serialize, Deserialize, PartialEq)]
+enum Mode {
+    Fast,
+    Careful { checks: u8 },
+}
+
+fn state() -> State {
+    State {
+        name: "api \"main\"".into(),
+        port: 8080,
+        ratio: 0.25,
+        timeout: Timeout::Seconds(30),
+        mode: Mode::Careful { checks: 3 },
+        tags: vec!["a".into(), "b".into()],
+        labels: [("team".to_string(), "ops".to_string())].into(),
+        workers: vec![
+            Worker {
+                id: (1,),
+                host: "10.0.0.1".into(),
+                mode: Mode::Fast,
+                limits: Some(Limits { cpu: 2 }),
+            },
+            Worker {
+                id: (2,),
+                host: "10.0.0.2".into(),
+                mode: Mode::Careful { checks: 1 },
+                limits: None,
+            },
+        ],
+        paused: None,
+    }
+}
+
+#[test]
+fn serializes_with_the_documented_mapping() {
+    assert_eq!(
+        to_string(&state()).unwrap(),
+        r#"name "api \"main\""
+port 8080
+ratio 0.25
+timeout (Seconds)30
+mode {
+    Careful checks=3
+}
+tags "a" "b"
+labels team="ops"
+workers {
+    - 1 host="10.0.0.1" mode="Fast" {
+        limits cpu=2
+    }
+    - 2 host="10.0.0.2" {
+        mode {
+            Careful checks=1
+        }
+    }
+}
+"#
+    );
+}
+
+#[test]
+fn round_trips_through_the_deserializer() {
+    let serialized = to_string(&state()).unwrap();
+    let deserialized: State = from_str(&serialized).unwrap();
+    assert_eq!(deserialized, state());
+}
+
+#[test]
+fn rejects_things_that_arent_documents() {
+    assert!(matches!(to_string(&5), Err(Error::NotADocument)));
+    assert!(matches!(
+        to_string(&BTreeMap::from([((1, 2), 3)])),
+        Err(Error::KeyMustBeAString)
+    ));
+    assert!(matches!(
+        to_string(&BTreeMap::from([("ratio", f64::NAN)])),
+        Err(Error::NotANumber)
+    ));
+}
@@ -0,0 +1,77 @@
+use sleepyhead_kdl::assembler::KdlDocument;
+use sleepyhead_kdl::ast::*;
+use sleepyhead_kdl::tape::Tape;
+
+const DOC: &str = r#"
+server "localhost" 8080 workers=2 workers=4 {
+    tls {
+        cert "server.pem"
+    }
+    route "/"
+    route "/about"
+}
+(backup)server "backup" 8081
+"#;
+
+#[test]
+fn matches_assembled_nodes() {
+    let tape = Tape::parse(DOC).unwrap();
+    let doc = KdlDocument::parse(DOC).unwrap();
+
+    assert_eq!(tape.len(), 6);
+    assert_eq!(tape.to_document(), doc);
+    assert_eq!(Tape::from(&doc), tape);
+
+    let names: Vec<_> = tape.descendants().map(|node| node.name()).collect();
+    assert_eq!(names, ["server", "tls", "cert", "route", "route", "server"]);
+}
+
+#[test]
+fn navigates_with_cursors() {
+    let tape = Tape::parse(DOC).unwrap();
+    let server = tape.get("server").unwrap();
+
+    assert_eq!(server.arg(1).unwrap().val, KdlValue::Integer(8080));
+    assert_eq!(server.prop("workers").unwrap().val, KdlValue::Integer(4));
+    assert!(server.parent().is_none());
+
+    let routes: Vec<_> = server
+        .children()
+        .filter(|child| child.name() == "route")
+        .map(|route| route.arg(0).unwrap().val)
+        .collect();
+    assert_eq!(
+        routes,
+        [
+            KdlValue::String(KdlString::Escapeless("/")),
+            KdlValue::String(KdlString::Escapeless("/about"))
+        ]
+    );
+
+    let cert = server.get("tls").unwrap().first_child().unwrap();
+    assert_eq!(cert.name(), "cert");
+    assert!(cert.next_sibling().is_none());
+    assert!(!cert.has_children());
+    assert_eq!(cert.parent().unwrap().parent(), Some(server));
+    assert_eq!(tape.cursor(cert.id()), Some(cert));
+
+    let backup = server.next_sibling().unwrap();
+    assert_eq!(backup.ty(), Some("backup"));
+    assert_eq!(backup.values().len(), 2);
+    assert!(backup.next_sibling().is_none());
+    assert_eq!(tape.roots().count(), 2);
+    assert_eq!(server.descendants().count(), 4);
+}
+
+#[test]
+fn unescapes_names() {
+    let input = "\"tab\\tbed\" 1 {\n    plain\n    \"new\\nline\"\n}\n";
+    let tape = Tape::parse(input).unwrap();
+
+    let names: Vec<_> = tape.descendants().map(|node| node.name()).collect();
+    assert_eq!(names, ["tab\tbed", "plain", "new\nline"]);
+    assert_eq!(tape.to_document(), KdlDocument::parse(input).unwrap());
+
+    let copied = Tape::from(&tape.to_document());
+    assert!(copied.get("tab\tbed").unwrap().get("new\nline").is_some());
+}
@@ -0,0 +1,106 @@
+#![cfg(feature = "xik")]
+
+use sleepyhead_kdl::format::FormatOptions;
+use sleepyhead_kdl::parser::Parser;
+use sleepyhead_kdl::xik::*;
+
+const XML: &str = r#"<?xml version="1.0" encoding="UTF-8"?>
+<!DOCTYPE html>
+<!-- a page -->
+<html lang="en">
+    <head>
+        <title>Tom &amp; Jerry</title>
+        <?render mode="fast"?>
+    </head>
+    <body>
+        <p>Hello <b>world</b>!</p>
+        <br/>
+        <script><![CDATA[if (a < b) {}]]></script>
+    </body>
+</html>
+"#;
+
+const KDL: &str = r#"?xml version="1.0" encoding="UTF-8"
+!doctype "html"
+! " a page "
+html lang="en" {
+    head {
+        title "Tom & Jerry"
+        ?render "mode=\"fast\""
+    }
+    body {
+        p {
+            - "Hello "
+            b "world"
+            - "!"
+        }
+        br
+        script "if (a < b) {}"
+    }
+}
+"#;
+
+#[test]
+fn transcodes_xml_to_kdl() {
+    let out = xml_to_kdl(
+        std::io::BufReader::new(XML.as_bytes()),
+        String::new(),
+        &FormatOptions::default(),
+    )
+    .unwrap();
+
+    assert_eq!(out, KDL);
+}
+
+#[test]
+fn transcodes_kdl_to_xml() {
+    let out = kdl_to_xml(Parser::from_str(KDL), String::new()).unwrap();
+
+    assert_eq!(
+        out,
+        "<?xml version=\"1.0\" encoding=\"UTF-8\"?>\n\
+         <!DOCTYPE html>\n\
+         <!-- a page -->\n\
+         <html lang=\"en\"><head><title>Tom &amp; Jerry</title><?render mode=\"fast\"?></head>\
+         <body><p>Hello <b>world</b>!</p><br/><script>if (a &lt; b) {}</script></body></html>\n"
+    );
+
+    // and back again
+    assert_eq!(
+        xml_str_to_kdl(&out, &FormatOptions::default()).unwrap(),
+        KDL
+    );
+}
+
+#[test]
+fn rejects_bad_names() {
+    let err = kdl_str_to_xml("\"not a name\" 1\n").unwrap_err();
+    assert!(matches!(err, XikError::InvalidName(name) if name == "not a name"));
+
+    let err = kdl_str_to_xml("a \"x=\"=1\n").unwrap_err();
+    assert!(matches!(err, XikError::InvalidName(_)));
+
+    assert!(matches!(
+        xml_str_to_kdl("<a></b>", &FormatOptions::default()),
+        Err(XikError::Xml(_))
+    ));
+}
+
+#[test]
+fn rejects_text_that_ends_markup_early() {
+    for kdl in [
+        "! \"a -- b\"\n",
+        "! \"a -->\"\n",
+        "! \"trailing-\"\n",
+        "!doctype \"html><script\"\n",
+        "?render \"a ?> b\"\n",
+    ] {
+        let err = kdl_str_to_xml(kdl).unwrap_err();
+        assert!(matches!(err, XikError::InvalidText(_)), "{}", kdl);
+    }
+
+    assert_eq!(
+        kdl_str_to_xml("!doctype \"note [<!ENTITY a \\\"b\\\">]\"\n").unwrap(),
+        "<!DOCTYPE note [<!ENTITY a \"b\">]>\n"
+    );
+}